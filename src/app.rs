@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use adw::prelude::*;
 use gtk4::prelude::*;
 use libadwaita as adw;
@@ -5,34 +7,148 @@ use relm4::{ComponentParts, ComponentSender, RelmApp, RelmWidgetExt, SimpleCompo
 
 use crate::{
     components::{
-        KeyboardView,
-        remap_dialog::{RemapDialog, RemapType},
+        KeyboardView, LintPanel,
+        remap_dialog::{RemapDialog, RemapTarget},
+        settings_dialog::SettingsDialog,
+    },
+    models::{
+        BUNDLE_EXTENSION, BundleMetadata, History, KeyAction, KeyToken, KeyboardSettings,
+        KinesisLayout, Layer, LayoutBundle, LayoutDiff, LedSettings, Lint, MacroStep,
+        SelectionOperation, SlotOperation,
     },
-    models::{KeyAction, KinesisLayout},
+    vdrive::{self, Backup, VDrive},
 };
 
 #[derive(Debug)]
 pub struct App {
     layouts: [KinesisLayout; 9],
+    /// Layouts as last loaded from or saved to the V-Drive
+    saved_layouts: [KinesisLayout; 9],
+    /// Whether `saved_layouts` were read from the selected V-Drive. Nothing
+    /// is saved until they are, so unchanged layouts are never overwritten.
+    loaded: bool,
+    /// Undo history for each layout slot
+    history: [History; 9],
     current_layout: usize,
+    /// Layer shown and edited in the keyboard view
+    layer: Layer,
+    /// What the current layout is compared against, if anything
+    compare_with: Option<CompareTarget>,
+    /// Physical keys selected in the keyboard view, in reading order
+    selection: Vec<KeyToken>,
     keyboard_view: KeyboardView,
+    lint_panel: LintPanel,
     main_window: adw::ApplicationWindow,
+    vdrive: Option<VDrive>,
+    status: String,
 }
 
 #[derive(Debug)]
 pub enum AppMsg {
     SwitchLayout(usize),
+    SetLayer(Layer),
+    /// Colour keys by their difference from another layout
+    SetCompare(Option<CompareTarget>),
+    /// Choose a copy, swap or reset for the current layout
+    ShowSlotOperations,
+    /// Ask before an operation that overwrites mappings
+    ConfirmSlotOperation(SlotOperation),
+    ApplySlotOperation(SlotOperation),
+    /// Edit the current layout's name and description
+    ShowLayoutDetails,
+    SetLayoutDetails {
+        name: String,
+        description: String,
+    },
+    Undo,
+    Redo,
+    /// The window is about to close
+    CloseRequested,
+    /// Carry out an action held back by the unsaved-changes dialog
+    ResolveUnsaved {
+        action: PendingAction,
+        save: bool,
+    },
     LoadConfig,
     SaveConfig,
+    ShowBackups,
+    RestoreBackup(Backup),
+    /// Open the keyboard-wide settings
+    ShowSettings,
+    /// Write whichever settings files changed, after one backup
+    SaveAllSettings {
+        settings: Option<KeyboardSettings>,
+        leds: Option<LedSettings>,
+    },
+    /// Ask for bundle details and where to save it
+    ExportBundle,
+    WriteBundle {
+        path: PathBuf,
+        metadata: BundleMetadata,
+    },
+    /// Choose a bundle file to import
+    ImportBundle,
+    ReadBundle(PathBuf),
     DetectKeyboard,
-    KeyClicked(String),
+    SelectVDrive(VDrive),
+    KeyClicked(KeyToken),
+    /// A key was dragged onto another: swap them, or with `one_way` make
+    /// `source` send what `target` does
+    DropKey {
+        source: KeyToken,
+        target: KeyToken,
+        one_way: bool,
+    },
+    /// The keys selected in the keyboard view changed
+    SelectionChanged(Vec<KeyToken>),
+    ClearSelection,
+    /// Choose a layout to copy the selected keys' mappings to
+    ShowCopySelection,
+    /// Edit the selected keys on the current layer
+    ApplySelectionOperation(SelectionOperation),
+    /// Apply a lint's fix to the current layout
+    FixLint(Lint),
     ApplyRemap {
-        source: String,
-        target: Option<String>,
-        remap_type: RemapType,
+        source: KeyToken,
+        target: RemapTarget,
     },
 }
 
+/// Choices in the comparison drop-down, in order
+const COMPARE_OPTIONS: [&str; 11] = [
+    "No Comparison",
+    "Saved on Keyboard",
+    "Layout 1",
+    "Layout 2",
+    "Layout 3",
+    "Layout 4",
+    "Layout 5",
+    "Layout 6",
+    "Layout 7",
+    "Layout 8",
+    "Layout 9",
+];
+
+/// Longest layout name shown on a slot button before it is shortened
+const SLOT_NAME_LENGTH: usize = 12;
+
+/// The layout the current one is diffed against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareTarget {
+    /// The same slot as last loaded from or saved to the V-Drive
+    Disk,
+    Slot(usize),
+}
+
+/// Actions that discard in-memory changes and so ask first
+#[derive(Debug, Clone, PartialEq)]
+pub enum PendingAction {
+    Reload,
+    /// Write a backup to the keyboard and reload from it
+    Restore(Backup),
+    Close,
+}
+
 #[relm4::component(pub)]
 impl SimpleComponent for App {
     type Init = ();
@@ -42,7 +158,8 @@ impl SimpleComponent for App {
     view! {
             #[root]
             main_window = adw::ApplicationWindow {
-                set_title: Some("Kinesis Advantage 360 Configurator"),
+                #[watch]
+                set_title: Some(&model.window_title()),
                 set_default_width: 1200,
                 set_default_height: 800,
 
@@ -51,6 +168,18 @@ impl SimpleComponent for App {
                     set_orientation: gtk4::Orientation::Vertical,
 
                     adw::HeaderBar {
+                        #[wrap(Some)]
+                        set_title_widget = &adw::WindowTitle {
+                            #[watch]
+                            set_title: &model.window_title(),
+                            #[watch]
+                            set_subtitle: &model
+                                .vdrive
+                                .as_ref()
+                                .map(|drive| drive.to_string())
+                                .unwrap_or_else(|| "No keyboard detected".to_string()),
+                        },
+
                         pack_start = &gtk4::Box {
                             set_spacing: 6,
 
@@ -65,6 +194,73 @@ impl SimpleComponent for App {
                                 set_tooltip_text: Some("Save Config to Keyboard"),
                                 connect_clicked => AppMsg::SaveConfig
                             },
+
+                            gtk4::Button {
+                                set_icon_name: "document-revert-symbolic",
+                                set_tooltip_text: Some("Restore Config from Backup"),
+                                connect_clicked => AppMsg::ShowBackups
+                            },
+
+                            gtk4::Button {
+                                set_icon_name: "preferences-system-symbolic",
+                                set_tooltip_text: Some("Keyboard Settings"),
+                                connect_clicked => AppMsg::ShowSettings
+                            },
+
+                            gtk4::Box {
+                                add_css_class: "linked",
+
+                                gtk4::Button {
+                                    set_icon_name: "document-send-symbolic",
+                                    set_tooltip_text: Some("Export All Layouts to a Bundle"),
+                                    connect_clicked => AppMsg::ExportBundle
+                                },
+
+                                gtk4::Button {
+                                    set_icon_name: "document-import-symbolic",
+                                    set_tooltip_text: Some("Import Layouts from a Bundle"),
+                                    connect_clicked => AppMsg::ImportBundle
+                                },
+                            },
+
+                            gtk4::Box {
+                                add_css_class: "linked",
+
+                                gtk4::Button {
+                                    set_icon_name: "edit-undo-symbolic",
+                                    #[watch]
+                                    set_tooltip_text: Some(&match model.history[model.current_layout].undo_label() {
+                                        Some(label) => format!("Undo {} (Ctrl+Z)", label),
+                                        None => "Undo (Ctrl+Z)".to_string(),
+                                    }),
+                                    #[watch]
+                                    set_sensitive: model.history[model.current_layout].can_undo(),
+                                    connect_clicked => AppMsg::Undo
+                                },
+
+                                gtk4::Button {
+                                    set_icon_name: "edit-redo-symbolic",
+                                    #[watch]
+                                    set_tooltip_text: Some(&match model.history[model.current_layout].redo_label() {
+                                        Some(label) => format!("Redo {} (Ctrl+Shift+Z)", label),
+                                        None => "Redo (Ctrl+Shift+Z)".to_string(),
+                                    }),
+                                    #[watch]
+                                    set_sensitive: model.history[model.current_layout].can_redo(),
+                                    connect_clicked => AppMsg::Redo
+                                },
+                            },
+
+                            gtk4::ToggleButton {
+                                set_label: "Keypad",
+                                set_tooltip_text: Some("Show and edit the keypad layer"),
+                                #[watch]
+                                set_active: model.layer == Layer::Keypad,
+                                connect_toggled[sender] => move |button| {
+                                    let layer = if button.is_active() { Layer::Keypad } else { Layer::Base };
+                                    sender.input(AppMsg::SetLayer(layer));
+                                },
+                            },
                         },
 
                     pack_end = &gtk4::Button {
@@ -100,60 +296,111 @@ impl SimpleComponent for App {
                 add_css_class: "linked",
 
                 gtk4::Button {
-                    set_label: "1",
+                    #[watch]
+                    set_label: &model.slot_label(0),
+                    #[watch]
+                    set_tooltip_text: model.slot_tooltip(0).as_deref(),
                     #[watch]
                     set_css_classes: if model.current_layout == 0 { &["suggested-action"] } else { &[] },
                     connect_clicked => AppMsg::SwitchLayout(0),
                 },
                 gtk4::Button {
-                    set_label: "2",
+                    #[watch]
+                    set_label: &model.slot_label(1),
+                    #[watch]
+                    set_tooltip_text: model.slot_tooltip(1).as_deref(),
                     #[watch]
                     set_css_classes: if model.current_layout == 1 { &["suggested-action"] } else { &[] },
                     connect_clicked => AppMsg::SwitchLayout(1),
                 },
                 gtk4::Button {
-                    set_label: "3",
+                    #[watch]
+                    set_label: &model.slot_label(2),
+                    #[watch]
+                    set_tooltip_text: model.slot_tooltip(2).as_deref(),
                     #[watch]
                     set_css_classes: if model.current_layout == 2 { &["suggested-action"] } else { &[] },
                     connect_clicked => AppMsg::SwitchLayout(2),
                 },
                 gtk4::Button {
-                    set_label: "4",
+                    #[watch]
+                    set_label: &model.slot_label(3),
+                    #[watch]
+                    set_tooltip_text: model.slot_tooltip(3).as_deref(),
                     #[watch]
                     set_css_classes: if model.current_layout == 3 { &["suggested-action"] } else { &[] },
                     connect_clicked => AppMsg::SwitchLayout(3),
                 },
                 gtk4::Button {
-                    set_label: "5",
+                    #[watch]
+                    set_label: &model.slot_label(4),
+                    #[watch]
+                    set_tooltip_text: model.slot_tooltip(4).as_deref(),
                     #[watch]
                     set_css_classes: if model.current_layout == 4 { &["suggested-action"] } else { &[] },
                     connect_clicked => AppMsg::SwitchLayout(4),
                 },
                 gtk4::Button {
-                    set_label: "6",
+                    #[watch]
+                    set_label: &model.slot_label(5),
+                    #[watch]
+                    set_tooltip_text: model.slot_tooltip(5).as_deref(),
                     #[watch]
                     set_css_classes: if model.current_layout == 5 { &["suggested-action"] } else { &[] },
                     connect_clicked => AppMsg::SwitchLayout(5),
                 },
                 gtk4::Button {
-                    set_label: "7",
+                    #[watch]
+                    set_label: &model.slot_label(6),
+                    #[watch]
+                    set_tooltip_text: model.slot_tooltip(6).as_deref(),
                     #[watch]
                     set_css_classes: if model.current_layout == 6 { &["suggested-action"] } else { &[] },
                     connect_clicked => AppMsg::SwitchLayout(6),
                 },
                 gtk4::Button {
-                    set_label: "8",
+                    #[watch]
+                    set_label: &model.slot_label(7),
+                    #[watch]
+                    set_tooltip_text: model.slot_tooltip(7).as_deref(),
                     #[watch]
                     set_css_classes: if model.current_layout == 7 { &["suggested-action"] } else { &[] },
                     connect_clicked => AppMsg::SwitchLayout(7),
                 },
                 gtk4::Button {
-                    set_label: "9",
+                    #[watch]
+                    set_label: &model.slot_label(8),
+                    #[watch]
+                    set_tooltip_text: model.slot_tooltip(8).as_deref(),
                     #[watch]
                     set_css_classes: if model.current_layout == 8 { &["suggested-action"] } else { &[] },
                     connect_clicked => AppMsg::SwitchLayout(8),
                 },
-            }
+            },
+
+            gtk4::Button {
+                set_icon_name: "document-edit-symbolic",
+                set_tooltip_text: Some("Name and Describe Layout"),
+                connect_clicked => AppMsg::ShowLayoutDetails,
+            },
+
+            gtk4::Button {
+                set_icon_name: "edit-copy-symbolic",
+                set_tooltip_text: Some("Copy, Swap or Reset Layout"),
+                connect_clicked => AppMsg::ShowSlotOperations,
+            },
+
+            gtk4::DropDown::from_strings(&COMPARE_OPTIONS) {
+                set_tooltip_text: Some("Highlight keys that differ from another layout"),
+                connect_selected_notify[sender] => move |dropdown| {
+                    let target = match dropdown.selected() {
+                        0 => None,
+                        1 => Some(CompareTarget::Disk),
+                        n => Some(CompareTarget::Slot(n as usize - 2)),
+                    };
+                    sender.input(AppMsg::SetCompare(target));
+                },
+            },
         },
             gtk4::Separator {
             set_orientation: gtk4::Orientation::Horizontal,
@@ -172,19 +419,115 @@ impl SimpleComponent for App {
 
             gtk4::Label {
                 #[watch]
-                set_label: &format!(
-                    "Layout {} - {} mappings",
-                    model.current_layout + 1,
-                    model.layouts[model.current_layout].mappings.len()
-                ),
+                set_label: &model.layout_summary(),
                 add_css_class: "dim-label",
             },
-            gtk4::Frame {
-            set_halign: gtk4::Align::Center,
-            set_valign: gtk4::Align::Center,
-            #[wrap(Some)]
-            set_child = model.keyboard_view.widget(),
-    },
+
+            gtk4::Label {
+                #[watch]
+                set_label: model.layouts[model.current_layout].description().unwrap_or_default(),
+                #[watch]
+                set_visible: model.layouts[model.current_layout].description().is_some(),
+                set_wrap: true,
+                set_justify: gtk4::Justification::Center,
+            },
+
+            gtk4::Label {
+                #[watch]
+                set_label: &model.diff_summary().unwrap_or_default(),
+                #[watch]
+                set_visible: model.compare_with.is_some(),
+                add_css_class: "caption",
+            },
+
+            gtk4::Label {
+                #[watch]
+                set_label: &model.status,
+                #[watch]
+                set_visible: !model.status.is_empty(),
+                add_css_class: "caption",
+            },
+            gtk4::Paned {
+                set_orientation: gtk4::Orientation::Horizontal,
+                set_vexpand: true,
+                set_resize_end_child: false,
+                set_shrink_end_child: false,
+
+                #[wrap(Some)]
+                set_start_child = &gtk4::Frame {
+                    set_halign: gtk4::Align::Center,
+                    set_valign: gtk4::Align::Center,
+                    #[wrap(Some)]
+                    set_child = model.keyboard_view.widget(),
+                },
+
+                set_end_child: Some(model.lint_panel.widget()),
+            },
+
+            gtk4::ActionBar {
+                #[watch]
+                set_revealed: !model.selection.is_empty(),
+
+                pack_start = &gtk4::Label {
+                    #[watch]
+                    set_label: &format!("{} key(s) selected", model.selection.len()),
+                },
+
+                pack_start = &gtk4::Button {
+                    set_label: "Clear Remaps",
+                    set_tooltip_text: Some("Remove the selected keys' remaps, keeping macros"),
+                    connect_clicked => AppMsg::ApplySelectionOperation(SelectionOperation::ClearRemaps),
+                },
+
+                pack_start = &gtk4::Button {
+                    set_label: "Reset",
+                    set_tooltip_text: Some("Put the selected keys back to their defaults"),
+                    connect_clicked => AppMsg::ApplySelectionOperation(SelectionOperation::Reset),
+                },
+
+                pack_start = &gtk4::Button {
+                    set_label: "Copy to…",
+                    set_tooltip_text: Some("Copy the selected keys' mappings to another layout"),
+                    connect_clicked => AppMsg::ShowCopySelection,
+                },
+
+                pack_start = &gtk4::Box {
+                    add_css_class: "linked",
+
+                    gtk4::Button {
+                        set_icon_name: "object-rotate-left-symbolic",
+                        set_tooltip_text: Some("Rotate: each key sends what the next one did"),
+                        connect_clicked => AppMsg::ApplySelectionOperation(SelectionOperation::Rotate { reverse: true }),
+                    },
+
+                    gtk4::Button {
+                        set_icon_name: "object-rotate-right-symbolic",
+                        set_tooltip_text: Some("Rotate: each key sends what the previous one did"),
+                        connect_clicked => AppMsg::ApplySelectionOperation(SelectionOperation::Rotate { reverse: false }),
+                    },
+                },
+
+                pack_start = &gtk4::Box {
+                    add_css_class: "linked",
+
+                    gtk4::Button {
+                        set_icon_name: "go-previous-symbolic",
+                        set_tooltip_text: Some("Shift: each key sends what the next one did, and the last is reset"),
+                        connect_clicked => AppMsg::ApplySelectionOperation(SelectionOperation::Shift { reverse: true }),
+                    },
+
+                    gtk4::Button {
+                        set_icon_name: "go-next-symbolic",
+                        set_tooltip_text: Some("Shift: each key sends what the previous one did, and the first is reset"),
+                        connect_clicked => AppMsg::ApplySelectionOperation(SelectionOperation::Shift { reverse: false }),
+                    },
+                },
+
+                pack_end = &gtk4::Button {
+                    set_label: "Deselect",
+                    connect_clicked => AppMsg::ClearSelection,
+                },
+            },
 
     }
     }
@@ -198,13 +541,47 @@ impl SimpleComponent for App {
     ) -> ComponentParts<Self> {
         let model = App {
             layouts: std::array::from_fn(|_| KinesisLayout::new()),
+            saved_layouts: std::array::from_fn(|_| KinesisLayout::new()),
+            loaded: false,
+            history: std::array::from_fn(|_| History::new()),
             current_layout: 0,
+            layer: Layer::Base,
+            compare_with: None,
+            selection: Vec::new(),
             keyboard_view: KeyboardView::new(sender.input_sender().clone()),
+            lint_panel: LintPanel::new(sender.input_sender().clone()),
             main_window: root.clone(),
+            vdrive: None,
+            status: String::new(),
         };
 
         let widgets = view_output!();
 
+        let close_sender = sender.input_sender().clone();
+        root.connect_close_request(move |_| {
+            let _ = close_sender.send(AppMsg::CloseRequested);
+            gtk4::glib::Propagation::Stop
+        });
+
+        let shortcuts = gtk4::ShortcutController::new();
+        shortcuts.set_scope(gtk4::ShortcutScope::Global);
+        let history_shortcuts: [(&str, fn() -> AppMsg); 2] = [
+            ("<Control>z", || AppMsg::Undo),
+            ("<Control><Shift>z", || AppMsg::Redo),
+        ];
+        for (trigger, msg) in history_shortcuts {
+            let sender = sender.clone();
+            let action = gtk4::CallbackAction::new(move |_, _| {
+                sender.input(msg());
+                gtk4::glib::Propagation::Stop
+            });
+            shortcuts.add_shortcut(gtk4::Shortcut::new(
+                gtk4::ShortcutTrigger::parse_string(trigger),
+                Some(action),
+            ));
+        }
+        root.add_controller(shortcuts);
+
         ComponentParts { model, widgets }
     }
 
@@ -217,89 +594,655 @@ impl SimpleComponent for App {
                     println!("Switched to layout {}", idx + 1);
                 }
             }
+            AppMsg::SetCompare(target) => {
+                self.compare_with = target;
+                self.refresh_diff();
+            }
+            AppMsg::SetLayer(layer) => {
+                if layer != self.layer {
+                    self.layer = layer;
+                    self.keyboard_view.set_layer(layer);
+                    println!("Showing {} layer", layer);
+                }
+            }
+            AppMsg::ShowSlotOperations => {
+                let operations = gtk4::DropDown::from_strings(&["Copy to", "Swap with", "Reset"]);
+                let slot_names: Vec<String> = (1..=self.layouts.len())
+                    .map(|n| format!("Layout {}", n))
+                    .collect();
+                let slots = gtk4::DropDown::from_strings(
+                    &slot_names.iter().map(String::as_str).collect::<Vec<_>>(),
+                );
+                slots.set_selected(((self.current_layout + 1) % self.layouts.len()) as u32);
+
+                // Reset has no second layout
+                let slots_clone = slots.clone();
+                operations.connect_selected_notify(move |operations| {
+                    slots_clone.set_sensitive(operations.selected() != 2);
+                });
+
+                let content_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+                content_box.set_halign(gtk4::Align::Center);
+                content_box.append(&operations);
+                content_box.append(&slots);
+
+                let dialog = adw::AlertDialog::new(
+                    Some(&format!("Layout {}", self.current_layout + 1)),
+                    Some(
+                        "Copy this layout over another, swap the two, or reset it to the keyboard defaults.",
+                    ),
+                );
+                dialog.set_extra_child(Some(&content_box));
+                dialog.add_response("cancel", "Cancel");
+                dialog.add_response("continue", "Continue");
+                dialog.set_response_appearance("continue", adw::ResponseAppearance::Suggested);
+                dialog.set_default_response(Some("continue"));
+                dialog.set_close_response("cancel");
+
+                let current = self.current_layout;
+                let input = sender.input_sender().clone();
+                let window = self.main_window.clone();
+                relm4::spawn_local(async move {
+                    if dialog.choose_future(&window).await != "continue" {
+                        return;
+                    }
+                    let other = slots.selected() as usize;
+                    let operation = match operations.selected() {
+                        0 => SlotOperation::Copy {
+                            from: current,
+                            to: other,
+                        },
+                        1 => SlotOperation::Swap {
+                            a: current,
+                            b: other,
+                        },
+                        _ => SlotOperation::Reset { slot: current },
+                    };
+                    let _ = input.send(AppMsg::ConfirmSlotOperation(operation));
+                });
+            }
+            AppMsg::ConfirmSlotOperation(operation) => {
+                if operation.affected_slots().is_empty() {
+                    self.show_alert(
+                        "Nothing to Do",
+                        "Choose a different layout to copy to or swap with.",
+                    );
+                    return;
+                }
+                if operation.overwritten(&self.layouts) == 0 {
+                    sender.input(AppMsg::ApplySlotOperation(operation));
+                    return;
+                }
+
+                let dialog = adw::AlertDialog::new(
+                    Some("Overwrite Mappings?"),
+                    Some(&operation.describe(&self.layouts)),
+                );
+                dialog.add_response("cancel", "Cancel");
+                dialog.add_response("apply", "Apply");
+                dialog.set_response_appearance("apply", adw::ResponseAppearance::Destructive);
+                dialog.set_close_response("cancel");
+
+                let input = sender.input_sender().clone();
+                let window = self.main_window.clone();
+                relm4::spawn_local(async move {
+                    if dialog.choose_future(&window).await == "apply" {
+                        let _ = input.send(AppMsg::ApplySlotOperation(operation));
+                    }
+                });
+            }
+            AppMsg::ApplySlotOperation(operation) => {
+                let before = self.layouts.clone();
+                operation.apply(&mut self.layouts);
+                let label = match operation {
+                    SlotOperation::Copy { from, to } => {
+                        format!("copy of layout {} to layout {}", from + 1, to + 1)
+                    }
+                    SlotOperation::Swap { a, b } => {
+                        format!("swap of layouts {} and {}", a + 1, b + 1)
+                    }
+                    SlotOperation::Reset { slot } => format!("reset of layout {}", slot + 1),
+                };
+                for slot in operation.affected_slots() {
+                    self.history[slot].record(
+                        label.clone(),
+                        before[slot].clone(),
+                        self.layouts[slot].clone(),
+                    );
+                }
+                self.status = format!("Applied {}", label);
+                println!("{}", self.status);
+                self.load_layout_into_view();
+            }
+            AppMsg::ShowLayoutDetails => {
+                let layout = &self.layouts[self.current_layout];
+                let form = gtk4::ListBox::new();
+                form.set_selection_mode(gtk4::SelectionMode::None);
+                form.add_css_class("boxed-list");
+                let name = adw::EntryRow::builder()
+                    .title("Name")
+                    .text(layout.name().unwrap_or_default())
+                    .build();
+                let description = adw::EntryRow::builder()
+                    .title("Description")
+                    .text(layout.description().unwrap_or_default())
+                    .build();
+                form.append(&name);
+                form.append(&description);
+
+                let dialog = adw::AlertDialog::new(
+                    Some(&format!("Layout {}", self.current_layout + 1)),
+                    Some(
+                        "The name and description are kept as comments at the top of the layout file.",
+                    ),
+                );
+                dialog.set_extra_child(Some(&form));
+                dialog.add_response("cancel", "Cancel");
+                dialog.add_response("apply", "Apply");
+                dialog.set_response_appearance("apply", adw::ResponseAppearance::Suggested);
+                dialog.set_default_response(Some("apply"));
+                dialog.set_close_response("cancel");
+
+                let input = sender.input_sender().clone();
+                let window = self.main_window.clone();
+                relm4::spawn_local(async move {
+                    if dialog.choose_future(&window).await == "apply" {
+                        let _ = input.send(AppMsg::SetLayoutDetails {
+                            name: name.text().to_string(),
+                            description: description.text().to_string(),
+                        });
+                    }
+                });
+            }
+            AppMsg::SetLayoutDetails { name, description } => {
+                let slot = self.current_layout;
+                let label = format!("rename of layout {}", slot + 1);
+                let changed = self.history[slot].apply(&mut self.layouts[slot], label, |layout| {
+                    layout.set_name(&name);
+                    layout.set_description(&description);
+                });
+                if changed {
+                    self.status = format!("Updated details of layout {}", slot + 1);
+                    println!("{}", self.status);
+                }
+            }
+            AppMsg::Undo => {
+                let slot = self.current_layout;
+                if let Some(label) = self.history[slot].undo(&mut self.layouts[slot]) {
+                    self.status = format!("Undid {}", label);
+                    println!("{}", self.status);
+                    self.load_layout_into_view();
+                }
+            }
+            AppMsg::Redo => {
+                let slot = self.current_layout;
+                if let Some(label) = self.history[slot].redo(&mut self.layouts[slot]) {
+                    self.status = format!("Redid {}", label);
+                    println!("{}", self.status);
+                    self.load_layout_into_view();
+                }
+            }
             AppMsg::LoadConfig => {
-                println!("Load config - not implemented yet");
+                if self.has_unsaved_changes() {
+                    self.confirm_unsaved(PendingAction::Reload, &sender);
+                } else {
+                    self.load_config();
+                }
             }
             AppMsg::SaveConfig => {
-                println!("Save config - not implemented yet");
+                self.save_config();
+            }
+            AppMsg::CloseRequested => {
+                if self.has_unsaved_changes() {
+                    self.confirm_unsaved(PendingAction::Close, &sender);
+                } else {
+                    self.main_window.destroy();
+                }
+            }
+            AppMsg::ResolveUnsaved { action, save } => {
+                if save && !self.save_config() {
+                    return;
+                }
+                match action {
+                    PendingAction::Reload => self.load_config(),
+                    PendingAction::Restore(backup) => self.restore_backup(backup),
+                    PendingAction::Close => self.main_window.destroy(),
+                }
+            }
+            AppMsg::ShowBackups => {
+                let backups = vdrive::backups_dir()
+                    .map(|dir| vdrive::list_backups(&dir).unwrap_or_default())
+                    .unwrap_or_default();
+
+                if backups.is_empty() {
+                    self.show_alert(
+                        "No Backups",
+                        "A backup is made automatically each time you save to the keyboard.",
+                    );
+                    return;
+                }
+
+                let names: Vec<&str> = backups.iter().map(|b| b.name.as_str()).collect();
+                let dropdown = gtk4::DropDown::from_strings(&names);
+
+                let dialog = adw::AlertDialog::new(
+                    Some("Restore Backup"),
+                    Some(
+                        "The selected backup will be written to the keyboard. The current configuration is backed up first.",
+                    ),
+                );
+                dialog.set_extra_child(Some(&dropdown));
+                dialog.add_response("cancel", "Cancel");
+                dialog.add_response("restore", "Restore");
+                dialog.set_response_appearance("restore", adw::ResponseAppearance::Destructive);
+                dialog.set_close_response("cancel");
+
+                let input = sender.input_sender().clone();
+                let window = self.main_window.clone();
+                relm4::spawn_local(async move {
+                    if dialog.choose_future(&window).await == "restore"
+                        && let Some(backup) = backups.get(dropdown.selected() as usize)
+                    {
+                        let _ = input.send(AppMsg::RestoreBackup(backup.clone()));
+                    }
+                });
+            }
+            AppMsg::RestoreBackup(backup) => {
+                if self.vdrive.is_none() {
+                    self.show_alert(
+                        "No Keyboard Selected",
+                        "Click Detect Keyboard to find the V-Drive before restoring a backup.",
+                    );
+                } else if self.has_unsaved_changes() {
+                    self.confirm_unsaved(PendingAction::Restore(backup), &sender);
+                } else {
+                    self.restore_backup(backup);
+                }
+            }
+            AppMsg::ShowSettings => {
+                let Some(drive) = &self.vdrive else {
+                    self.show_alert(
+                        "No Keyboard Selected",
+                        "Click Detect Keyboard to find the V-Drive before changing its settings.",
+                    );
+                    return;
+                };
+
+                let settings_path = drive.settings_path();
+                let led_path = drive.led_path();
+                let result = KeyboardSettings::from_file(&settings_path)
+                    .map_err(|e| (settings_path, e))
+                    .and_then(|settings| {
+                        LedSettings::from_file(&led_path)
+                            .map(|leds| (settings, leds))
+                            .map_err(|e| (led_path, e))
+                    });
+                match result {
+                    Ok((settings, leds)) => {
+                        SettingsDialog::new(settings, leds, sender.input_sender().clone())
+                            .present(&self.main_window);
+                    }
+                    Err((path, e)) => self.show_alert(
+                        "Settings Unreadable",
+                        &format!("Could not read {}: {}", path.display(), e),
+                    ),
+                }
+            }
+            AppMsg::SaveAllSettings { settings, leds } => {
+                self.save_settings(settings, leds);
+            }
+            AppMsg::ExportBundle => {
+                let form = gtk4::ListBox::new();
+                form.set_selection_mode(gtk4::SelectionMode::None);
+                form.add_css_class("boxed-list");
+                let name = adw::EntryRow::builder().title("Name").build();
+                let description = adw::EntryRow::builder().title("Description").build();
+                let author = adw::EntryRow::builder().title("Author").build();
+                form.append(&name);
+                form.append(&description);
+                form.append(&author);
+
+                let dialog = adw::AlertDialog::new(
+                    Some("Export Layouts"),
+                    Some(
+                        "All nine layouts are saved to one file that can be imported on another computer.",
+                    ),
+                );
+                dialog.set_extra_child(Some(&form));
+                dialog.add_response("cancel", "Cancel");
+                dialog.add_response("export", "Export…");
+                dialog.set_response_appearance("export", adw::ResponseAppearance::Suggested);
+                dialog.set_default_response(Some("export"));
+                dialog.set_close_response("cancel");
+
+                let input = sender.input_sender().clone();
+                let window = self.main_window.clone();
+                relm4::spawn_local(async move {
+                    if dialog.choose_future(&window).await != "export" {
+                        return;
+                    }
+                    let metadata = BundleMetadata {
+                        name: name.text().trim().to_string(),
+                        description: description.text().trim().to_string(),
+                        author: author.text().trim().to_string(),
+                        ..BundleMetadata::default()
+                    };
+                    let file_name = match metadata.name.as_str() {
+                        "" => format!("layouts.{}", BUNDLE_EXTENSION),
+                        name => format!("{}.{}", name, BUNDLE_EXTENSION),
+                    };
+
+                    let file_dialog = gtk4::FileDialog::new();
+                    file_dialog.set_title("Export Layouts");
+                    file_dialog.set_initial_name(Some(&file_name));
+                    file_dialog.set_default_filter(Some(&bundle_filter()));
+                    if let Ok(file) = file_dialog.save_future(Some(&window)).await
+                        && let Some(path) = file.path()
+                    {
+                        let _ = input.send(AppMsg::WriteBundle { path, metadata });
+                    }
+                });
+            }
+            AppMsg::WriteBundle { path, metadata } => {
+                let bundle = LayoutBundle::new(metadata, &self.layouts);
+                match bundle.save(&path) {
+                    Ok(()) => {
+                        self.status = format!("Exported layouts to {}", path.display());
+                        println!("{}", self.status);
+                    }
+                    Err(e) => self.show_alert(
+                        "Export Failed",
+                        &format!("Could not write {}: {}", path.display(), e),
+                    ),
+                }
+            }
+            AppMsg::ImportBundle => {
+                let file_dialog = gtk4::FileDialog::new();
+                file_dialog.set_title("Import Layouts");
+                file_dialog.set_default_filter(Some(&bundle_filter()));
+
+                let input = sender.input_sender().clone();
+                let window = self.main_window.clone();
+                relm4::spawn_local(async move {
+                    if let Ok(file) = file_dialog.open_future(Some(&window)).await
+                        && let Some(path) = file.path()
+                    {
+                        let _ = input.send(AppMsg::ReadBundle(path));
+                    }
+                });
+            }
+            AppMsg::ReadBundle(path) => {
+                let bundle = match LayoutBundle::load(&path) {
+                    Ok(bundle) => bundle,
+                    Err(e) => {
+                        self.show_alert(
+                            "Import Failed",
+                            &format!("Could not read {}: {}", path.display(), e),
+                        );
+                        return;
+                    }
+                };
+
+                let name = match bundle.metadata.name.as_str() {
+                    "" => path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    name => name.to_string(),
+                };
+                let label = format!("import of {}", name);
+                let before = std::mem::replace(&mut self.layouts, bundle.into_layouts());
+                for (slot, before) in before.into_iter().enumerate() {
+                    self.history[slot].record(label.clone(), before, self.layouts[slot].clone());
+                }
+
+                // Imported layouts stay unsaved until written to the keyboard
+                self.status = format!("Imported {}", name);
+                println!("{} from {}", self.status, path.display());
+                self.load_layout_into_view();
             }
             AppMsg::DetectKeyboard => {
-                println!("Detect keyboard - not implemented yet");
+                let candidates = vdrive::detect();
+                println!("Found {} V-Drive candidate(s)", candidates.len());
+
+                match candidates.as_slice() {
+                    [] => self.show_alert(
+                        "No Keyboard Found",
+                        "No mounted V-Drive was found. Press SmartSet + Hk3 on the keyboard and mount the drive, then try again.",
+                    ),
+                    [drive] => sender.input(AppMsg::SelectVDrive(drive.clone())),
+                    _ => {
+                        let dialog = adw::AlertDialog::new(
+                            Some("Select Keyboard"),
+                            Some("More than one V-Drive was found. Choose the keyboard to configure."),
+                        );
+                        for (idx, drive) in candidates.iter().enumerate() {
+                            dialog.add_response(&idx.to_string(), &drive.to_string());
+                        }
+                        dialog.add_response("cancel", "Cancel");
+                        dialog.set_close_response("cancel");
+
+                        let input = sender.input_sender().clone();
+                        let window = self.main_window.clone();
+                        relm4::spawn_local(async move {
+                            let response = dialog.choose_future(&window).await;
+                            if let Some(drive) = response
+                                .parse::<usize>()
+                                .ok()
+                                .and_then(|idx| candidates.get(idx))
+                            {
+                                let _ = input.send(AppMsg::SelectVDrive(drive.clone()));
+                            }
+                        });
+                    }
+                }
+            }
+            AppMsg::SelectVDrive(drive) => {
+                println!("Using V-Drive {}", drive);
+                if self.vdrive.as_ref() == Some(&drive) {
+                    self.status = format!("Using {}", drive.label);
+                    return;
+                }
+
+                // What was loaded from another drive says nothing about
+                // this one
+                let edited = self.has_unsaved_changes();
+                self.saved_layouts = std::array::from_fn(|_| KinesisLayout::new());
+                self.loaded = false;
+                self.vdrive = Some(drive);
+                if edited {
+                    self.status = "Keyboard found. Load its layouts before saving.".to_string();
+                    self.refresh_diff();
+                } else {
+                    self.load_config();
+                }
             }
-            AppMsg::KeyClicked(key_label) => {
-                let current_mapping = self.get_current_mapping(&key_label);
-                let key_label_clone = key_label.clone();
+            AppMsg::KeyClicked(key) => {
+                let current_action = self.get_current_mapping(key);
                 let input = sender.input_sender().clone();
 
                 let window = self.main_window.clone();
 
                 relm4::spawn_local(async move {
-                    let dialog = RemapDialog::new(&key_label_clone, current_mapping.as_deref());
+                    let dialog = RemapDialog::new(key, current_action.as_ref());
 
                     if let Some(result) = dialog.run(&window).await {
                         let _ = input.send(AppMsg::ApplyRemap {
                             source: result.source_key,
-                            target: result.target_key,
-                            remap_type: result.remap_type,
+                            target: result.target,
                         });
                     }
                 });
             }
-            AppMsg::ApplyRemap {
+            AppMsg::DropKey {
                 source,
                 target,
-                remap_type,
+                one_way,
             } => {
-                let layout = &mut self.layouts[self.current_layout];
-                layout.remove_by_source(&source);
-
-                if let Some(target_key) = &target {
-                    match remap_type {
-                        RemapType::Simple => {
-                            if !Self::is_valid_key(target_key) {
-                                let error_dialog = adw::AlertDialog::new(
-                                    Some("Invalid Key"),
-                                    Some(&format!(
-                                        "'{}' is not a valid key name. Please enter a single key or a recognized key name like 'Enter', 'Shift', etc.",
-                                        target_key
-                                    )),
-                                );
-                                error_dialog.add_response("ok", "OK");
-                                error_dialog.set_default_response(Some("ok"));
-
-                                let window = self.main_window.clone();
-                                relm4::spawn_local(async move {
-                                    error_dialog.choose_future(&window).await;
-                                });
-                                return;
-                            }
-                            // Normalize and get full label for the target BEFORE mutable borrow
-                            let full_target_label = self.get_full_key_label(target_key);
-                            let layout = &mut self.layouts[self.current_layout];
-                            layout.remove_by_source(&source);
-                            layout.add_remap(source.clone(), full_target_label.clone());
-                            self.keyboard_view
-                                .set_remapping(&source, &full_target_label);
-                            println!("Remapped {} -> {}", source, full_target_label);
-                        }
-                        RemapType::Macro => {
-                            layout.add_macro(source.clone(), target_key.clone());
-                            self.keyboard_view
-                                .set_remapping(&source, &format!("Macro: {}", target_key));
-                            println!("Created macro {} -> {}", source, target_key);
-                        }
+                let slot = self.current_layout;
+                let label = if one_way {
+                    format!("remap of {}", source)
+                } else {
+                    format!("swap of {} and {}", source, target)
+                };
+                let changed = self.history[slot].apply(&mut self.layouts[slot], label, |layout| {
+                    if one_way {
+                        layout.remap_to_key(source, target);
+                    } else {
+                        layout.swap_keys(source, target);
                     }
+                });
+                if changed {
+                    self.status = if one_way {
+                        format!(
+                            "Remapped {} to send {}",
+                            source,
+                            Layer::of(target).default_action(target)
+                        )
+                    } else {
+                        format!("Swapped {} and {}", source, target)
+                    };
+                    println!("{}", self.status);
+                    self.load_layout_into_view();
+                }
+            }
+            AppMsg::SelectionChanged(keys) => {
+                self.selection = keys;
+            }
+            AppMsg::ClearSelection => {
+                self.selection.clear();
+                self.keyboard_view.clear_selection();
+            }
+            AppMsg::ShowCopySelection => {
+                let slot_names: Vec<String> = (1..=self.layouts.len())
+                    .map(|n| format!("Layout {}", n))
+                    .collect();
+                let slots = gtk4::DropDown::from_strings(
+                    &slot_names.iter().map(String::as_str).collect::<Vec<_>>(),
+                );
+                slots.set_selected(((self.current_layout + 1) % self.layouts.len()) as u32);
+                slots.set_halign(gtk4::Align::Center);
+
+                let dialog = adw::AlertDialog::new(
+                    Some(&format!("Copy {} Key(s)", self.selection.len())),
+                    Some(&format!(
+                        "Make the selected keys on the {} layer do what they do in layout {}, replacing their mappings there.",
+                        self.layer,
+                        self.current_layout + 1
+                    )),
+                );
+                dialog.set_extra_child(Some(&slots));
+                dialog.add_response("cancel", "Cancel");
+                dialog.add_response("copy", "Copy");
+                dialog.set_response_appearance("copy", adw::ResponseAppearance::Suggested);
+                dialog.set_default_response(Some("copy"));
+                dialog.set_close_response("cancel");
+
+                let input = sender.input_sender().clone();
+                let window = self.main_window.clone();
+                relm4::spawn_local(async move {
+                    if dialog.choose_future(&window).await == "copy" {
+                        let to = slots.selected() as usize;
+                        let _ = input.send(AppMsg::ApplySelectionOperation(
+                            SelectionOperation::CopyTo { to },
+                        ));
+                    }
+                });
+            }
+            AppMsg::ApplySelectionOperation(operation) => {
+                let slot = self.current_layout;
+                if operation.affected_slots(slot).is_empty() {
+                    self.show_alert(
+                        "Nothing to Do",
+                        "Choose a different layout to copy the keys to.",
+                    );
+                    return;
+                }
+
+                let keys: Vec<KeyToken> = self
+                    .selection
+                    .iter()
+                    .map(|&key| self.layer.position(key))
+                    .collect();
+                let before = self.layouts.clone();
+                operation.apply(&keys, slot, &mut self.layouts);
+                let label = operation.describe(keys.len());
+                let mut changed = false;
+                for affected in operation.affected_slots(slot) {
+                    changed |= self.history[affected].record(
+                        label.clone(),
+                        before[affected].clone(),
+                        self.layouts[affected].clone(),
+                    );
+                }
+                if changed {
+                    self.status = format!("Applied {}", label);
+                    println!("{}", self.status);
+                    self.load_layout_into_view();
                 } else {
-                    let layout = &mut self.layouts[self.current_layout];
-                    layout.remove_by_source(&source);
+                    self.status = "Nothing to change for the selected keys".to_string();
+                }
+            }
+            AppMsg::FixLint(lint) => {
+                let slot = self.current_layout;
+                let label = format!("fix for {}", lint.key());
+                if self.history[slot]
+                    .apply(&mut self.layouts[slot], label, |layout| lint.fix(layout))
+                {
+                    self.status = format!("Fixed: {}", lint);
+                    println!("{}", self.status);
+                    self.load_layout_into_view();
+                }
+            }
+            AppMsg::ApplyRemap { source, target } => {
+                let slot = self.current_layout;
+                let before = self.layouts[slot].clone();
+                let label = match &target {
+                    RemapTarget::Key(_) => format!("remap of {}", source),
+                    RemapTarget::Macro(_) => format!("macro on {}", source),
+                    RemapTarget::Clear => format!("clear of {}", source),
+                };
+                let layout = &mut self.layouts[slot];
 
-                    self.keyboard_view.clear_remapping(&source);
-                    println!("Cleared mapping for {}", source);
+                match target {
+                    RemapTarget::Key(target) => {
+                        layout.set_mapping(KeyAction::SimpleRemap { source, target });
+                        self.keyboard_view.set_remapping(source, target.legend());
+                        println!("Remapped {} -> {}", source, target);
+                    }
+                    RemapTarget::Macro(steps) => {
+                        let text = MacroStep::format_sequence(&steps);
+                        layout.set_mapping(KeyAction::Macro {
+                            trigger: source,
+                            steps,
+                        });
+                        self.keyboard_view
+                            .set_remapping(source, &format!("Macro: {}", text));
+                        println!("Created macro {} -> {}", source, text);
+                    }
+                    RemapTarget::Clear => {
+                        layout.remove_by_source(source);
+                        self.keyboard_view.clear_remapping(source);
+                        println!("Cleared mapping for {}", source);
+                    }
                 }
+
+                let after = self.layouts[slot].clone();
+                self.history[slot].record(label, before, after);
+                self.refresh_diff();
+                self.refresh_lints();
             }
         }
     }
 }
+/// File dialog filter matching layout bundles
+fn bundle_filter() -> gtk4::FileFilter {
+    let filter = gtk4::FileFilter::new();
+    filter.set_name(Some("Layout Bundles"));
+    filter.add_suffix(BUNDLE_EXTENSION);
+    filter
+}
+
 impl App {
     pub fn run() {
         adw::init().expect("Failed to ");
@@ -307,149 +1250,320 @@ impl App {
         app.run::<App>(());
     }
 
-    fn load_layout_into_view(&mut self) {
-        let layout = &self.layouts[self.current_layout];
+    /// Write a backup to the V-Drive, backing it up first, and reload
+    fn restore_backup(&mut self, backup: Backup) {
+        let Some(drive) = &self.vdrive else {
+            return;
+        };
 
-        self.keyboard_view.clear_all_remappings();
+        match vdrive::restore_drive(&backup, drive) {
+            Ok(_) => {
+                println!("Restored backup {}", backup.name);
+                self.load_config();
+            }
+            Err(e) => self.show_alert(
+                "Restore Failed",
+                &format!("Could not restore backup {}: {}", backup.name, e),
+            ),
+        }
+    }
 
-        for mapping in &layout.mappings {
-            if let KeyAction::SimpleRemap { source, target } = mapping {
-                self.keyboard_view.set_remapping(source, target);
+    /// Replace every layout with what is on the V-Drive
+    fn load_config(&mut self) {
+        let Some(drive) = &self.vdrive else {
+            self.show_alert(
+                "No Keyboard Selected",
+                "Click Detect Keyboard to find the V-Drive before loading.",
+            );
+            return;
+        };
+
+        let report = vdrive::load_layouts(drive);
+        println!("{} from {}", report.summary(), drive);
+
+        if !report.errors.is_empty() || !report.warnings.is_empty() {
+            let errors = report
+                .errors
+                .iter()
+                .map(|(idx, e)| format!("layout{}.txt: {}", idx + 1, e));
+            let warnings = report
+                .warnings
+                .iter()
+                .map(|(idx, w)| format!("layout{}.txt, {} (kept as-is)", idx + 1, w));
+            let details = errors.chain(warnings).collect::<Vec<_>>().join("\n");
+            self.show_alert("Some Layouts Had Problems", &details);
+        }
+
+        self.status = report.summary();
+        self.saved_layouts = report.layouts.clone();
+        self.loaded = true;
+        self.layouts = report.layouts;
+        // Edits made before the reload no longer apply
+        for history in &mut self.history {
+            history.clear();
+        }
+        self.load_layout_into_view();
+    }
+
+    /// Write changed layouts to the V-Drive, returning whether everything
+    /// was saved
+    fn save_config(&mut self) -> bool {
+        let Some(drive) = &self.vdrive else {
+            self.show_alert(
+                "No Keyboard Selected",
+                "Click Detect Keyboard to find the V-Drive before saving.",
+            );
+            return false;
+        };
+        if !self.loaded {
+            self.show_alert(
+                "Layouts Not Loaded",
+                "Click Load Config to read this keyboard's layouts before saving, so layouts you have not changed are not overwritten.",
+            );
+            return false;
+        }
+
+        if self.has_unsaved_changes() {
+            match vdrive::backup_drive(drive) {
+                Ok(backup) => println!("Backed up V-Drive to {}", backup.path.display()),
+                Err(e) => {
+                    self.show_alert(
+                        "Backup Failed",
+                        &format!("Nothing was saved because the current configuration could not be backed up: {}", e),
+                    );
+                    return false;
+                }
             }
         }
+
+        let report = vdrive::save_layouts(drive, &self.layouts, &self.saved_layouts);
+        println!("{} to {}", report.summary(), drive);
+
+        for &idx in &report.written {
+            self.saved_layouts[idx] = self.layouts[idx].clone();
+        }
+
+        if !report.errors.is_empty() {
+            let details = report
+                .errors
+                .iter()
+                .map(|(idx, e)| format!("layout{}.txt: {}", idx + 1, e))
+                .collect::<Vec<_>>()
+                .join("\n");
+            self.show_alert("Some Layouts Failed to Save", &details);
+        }
+
+        self.status = report.summary();
+        self.refresh_diff();
+        report.errors.is_empty()
     }
 
-    fn get_current_mapping(&self, key: &str) -> Option<String> {
-        let layout = &self.layouts[self.current_layout];
-        layout.find_by_source(key).first().and_then(|action| {
-            if let KeyAction::SimpleRemap { target, .. } = action {
-                Some(target.clone())
-            } else {
-                None
+    /// Back up the drive once, then write the settings files given
+    fn save_settings(&mut self, settings: Option<KeyboardSettings>, leds: Option<LedSettings>) {
+        let Some(drive) = &self.vdrive else {
+            return;
+        };
+        if settings.is_none() && leds.is_none() {
+            return;
+        }
+
+        let settings_path = drive.settings_path();
+        let led_path = drive.led_path();
+        let result = vdrive::backup_drive(drive)
+            .map_err(|e| format!("Could not back up the keyboard: {}", e))
+            .and_then(|backup| {
+                println!("Backed up V-Drive to {}", backup.path.display());
+                if let Some(settings) = &settings {
+                    settings.to_file(&settings_path).map_err(|e| {
+                        format!("Could not write {}: {}", settings_path.display(), e)
+                    })?;
+                    println!("Wrote {}", settings_path.display());
+                }
+                if let Some(leds) = &leds {
+                    leds.to_file(&led_path)
+                        .map_err(|e| format!("Could not write {}: {}", led_path.display(), e))?;
+                    println!("Wrote {}", led_path.display());
+                }
+                Ok(())
+            });
+
+        match result {
+            Ok(()) => {
+                let saved: Vec<&str> = [
+                    settings.as_ref().map(|_| "keyboard settings"),
+                    leds.as_ref().map(|_| "lighting"),
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+                self.status = format!("Saved {}", saved.join(" and "));
             }
-        })
+            Err(message) => self.show_alert("Settings Not Saved", &message),
+        }
+    }
+
+    fn is_dirty(&self, idx: usize) -> bool {
+        !self.layouts[idx].same_lines(&self.saved_layouts[idx])
     }
 
-    fn is_valid_key(key_str: &str) -> bool {
-        let normalized = key_str.to_uppercase();
+    fn has_unsaved_changes(&self) -> bool {
+        (0..self.layouts.len()).any(|idx| self.is_dirty(idx))
+    }
 
-        if key_str.len() == 1 {
-            return key_str.chars().next().unwrap().is_ascii_graphic();
+    /// Label for a layout slot button, with the layout's name if it has one
+    /// and marked when it has unsaved changes
+    fn slot_label(&self, idx: usize) -> String {
+        let mut label = (idx + 1).to_string();
+        if let Some(name) = self.layouts[idx].name() {
+            label.push(' ');
+            if name.chars().count() > SLOT_NAME_LENGTH {
+                label.extend(name.chars().take(SLOT_NAME_LENGTH - 1));
+                label.push('…');
+            } else {
+                label.push_str(name);
+            }
         }
+        if self.is_dirty(idx) {
+            label.push_str(" •");
+        }
+        label
+    }
 
-        matches!(
-            normalized.as_str(),
-            "TAB"
-                | "ESC"
-                | "ESCAPE"
-                | "SPACE"
-                | "SPC"
-                | "ENTER"
-                | "RETURN"
-                | "BACKSPACE"
-                | "BKSP"
-                | "DELETE"
-                | "DEL"
-                | "SHIFT"
-                | "LSHIFT"
-                | "RSHIFT"
-                | "CTRL"
-                | "CONTROL"
-                | "LCTRL"
-                | "RCTRL"
-                | "ALT"
-                | "LALT"
-                | "RALT"
-                | "HOME"
-                | "END"
-                | "PGUP"
-                | "PAGEUP"
-                | "PGDN"
-                | "PAGEDOWN"
-                | "PGDOWN"
-                | "UP"
-                | "DOWN"
-                | "LEFT"
-                | "RIGHT"
-                | "CAPS"
-                | "CAPSLOCK"
-                | "WIN"
-                | "WINDOWS"
-                | "SUPER"
-                | "META"
-                | "FN"
-                | "LFN"
-                | "RFN"
-                | "F1"
-                | "F2"
-                | "F3"
-                | "F4"
-                | "F5"
-                | "F6"
-                | "F7"
-                | "F8"
-                | "F9"
-                | "F10"
-                | "F11"
-                | "F12"
-                | "SS"
-                | "SMARTSET"
-                | "KP"
-                | "KEYPAD"
-                | "HK1"
-                | "HK2"
-                | "HK3"
-                | "HK4"
+    /// Full name and description of a slot, for its button's tooltip
+    fn slot_tooltip(&self, idx: usize) -> Option<String> {
+        let layout = &self.layouts[idx];
+        match (layout.name(), layout.description()) {
+            (Some(name), Some(description)) => Some(format!("{}\n{}", name, description)),
+            (Some(text), None) | (None, Some(text)) => Some(text.to_string()),
+            (None, None) => None,
+        }
+    }
+
+    /// "Layout N - M mappings" line under the title
+    fn layout_summary(&self) -> String {
+        let layout = &self.layouts[self.current_layout];
+        let title = match layout.name() {
+            Some(name) => format!("Layout {}: {}", self.current_layout + 1, name),
+            None => format!("Layout {}", self.current_layout + 1),
+        };
+        format!(
+            "{} - {} mappings ({} layer: {})",
+            title,
+            layout.mapping_count(),
+            self.layer,
+            layout.mapping_count_on(self.layer)
         )
     }
 
-    fn get_full_key_label(&self, key_str: &str) -> String {
-        // Normalize to uppercase first
-        let normalized = key_str.to_uppercase();
-
-        // Map common number/symbol keys to their full labels
-        match normalized.as_str() {
-            "1" => "!\n1".to_string(),
-            "2" => "@\n2".to_string(),
-            "3" => "#\n3".to_string(),
-            "4" => "$\n4".to_string(),
-            "5" => "%\n5".to_string(),
-            "6" => "^\n6".to_string(),
-            "7" => "&\n7".to_string(),
-            "8" => "*\n8".to_string(),
-            "9" => "(\n9".to_string(),
-            "0" => ")\n0".to_string(),
-            "-" => "_\n-".to_string(),
-            "=" => "+\n=".to_string(),
-            "[" => "{\n[".to_string(),
-            "]" => "}\n]".to_string(),
-            ";" => ":\n;".to_string(),
-            "'" => "\"\n'".to_string(),
-            "," => "<\n,".to_string(),
-            "." => ">\n.".to_string(),
-            "/" => "?\n/".to_string(),
-            "\\" => "|\n\\".to_string(),
-            "`" => "~\n`".to_string(),
-            // Special keys remain as-is
-            "TAB" => "Tab".to_string(),
-            "ESC" | "ESCAPE" => "Esc".to_string(),
-            "SPACE" | "SPC" => "Space".to_string(),
-            "ENTER" | "RETURN" => "Enter".to_string(),
-            "BACKSPACE" | "BKSP" => "Back\nSpace".to_string(),
-            "DELETE" | "DEL" => "Delete".to_string(),
-            "SHIFT" | "LSHIFT" | "RSHIFT" => "Shift".to_string(),
-            "CTRL" | "CONTROL" | "LCTRL" | "RCTRL" => "Ctrl".to_string(),
-            "ALT" | "LALT" | "RALT" => "Alt".to_string(),
-            "HOME" => "Home".to_string(),
-            "END" => "End".to_string(),
-            "PGUP" | "PAGEUP" => "Pg\nUp".to_string(),
-            "PGDN" | "PAGEDOWN" | "PGDOWN" => "Pg\nDown".to_string(),
-            "UP" => "↑".to_string(),
-            "DOWN" => "↓".to_string(),
-            "LEFT" => "←".to_string(),
-            "RIGHT" => "→".to_string(),
-            // Single letters stay uppercase
-            other => other.to_string(),
+    fn window_title(&self) -> String {
+        if self.has_unsaved_changes() {
+            "• Kinesis Advantage 360 Configurator".to_string()
+        } else {
+            "Kinesis Advantage 360 Configurator".to_string()
         }
     }
+
+    /// Ask whether to save, discard or keep unsaved changes before `action`
+    fn confirm_unsaved(&self, action: PendingAction, sender: &ComponentSender<Self>) {
+        let dirty: Vec<String> = (0..self.layouts.len())
+            .filter(|&idx| self.is_dirty(idx))
+            .map(|idx| (idx + 1).to_string())
+            .collect();
+
+        let dialog = adw::AlertDialog::new(
+            Some("Save Changes?"),
+            Some(&format!(
+                "Layout(s) {} have changes that are not on the keyboard yet. Unsaved changes are lost if you don't save.",
+                dirty.join(", ")
+            )),
+        );
+        dialog.add_response("cancel", "Cancel");
+        dialog.add_response("discard", "Discard");
+        dialog.add_response("save", "Save");
+        dialog.set_response_appearance("discard", adw::ResponseAppearance::Destructive);
+        dialog.set_response_appearance("save", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("save"));
+        dialog.set_close_response("cancel");
+
+        let input = sender.input_sender().clone();
+        let window = self.main_window.clone();
+        relm4::spawn_local(async move {
+            let save = match dialog.choose_future(&window).await.as_str() {
+                "save" => true,
+                "discard" => false,
+                _ => return,
+            };
+            let _ = input.send(AppMsg::ResolveUnsaved { action, save });
+        });
+    }
+
+    fn show_alert(&self, heading: &str, body: &str) {
+        let dialog = adw::AlertDialog::new(Some(heading), Some(body));
+        dialog.add_response("ok", "OK");
+        dialog.set_default_response(Some("ok"));
+
+        let window = self.main_window.clone();
+        relm4::spawn_local(async move {
+            dialog.choose_future(&window).await;
+        });
+    }
+
+    fn load_layout_into_view(&mut self) {
+        let layout = &self.layouts[self.current_layout];
+
+        self.keyboard_view.clear_all_remappings();
+
+        for mapping in layout.mappings() {
+            if let KeyAction::SimpleRemap { source, target } = mapping {
+                self.keyboard_view.set_remapping(*source, target.legend());
+            }
+        }
+
+        self.refresh_diff();
+        self.refresh_lints();
+    }
+
+    /// Differences between the current layout and the comparison target
+    fn current_diff(&self) -> Option<LayoutDiff> {
+        let other = match self.compare_with? {
+            CompareTarget::Disk => &self.saved_layouts[self.current_layout],
+            CompareTarget::Slot(idx) => &self.layouts[idx],
+        };
+        Some(LayoutDiff::between(
+            other,
+            &self.layouts[self.current_layout],
+        ))
+    }
+
+    fn diff_summary(&self) -> Option<String> {
+        let diff = self.current_diff()?;
+        let other = match self.compare_with? {
+            CompareTarget::Disk => "the keyboard".to_string(),
+            CompareTarget::Slot(idx) => format!("layout {}", idx + 1),
+        };
+        Some(format!(
+            "Layout {} compared with {}: {}",
+            self.current_layout + 1,
+            other,
+            diff
+        ))
+    }
+
+    fn refresh_diff(&mut self) {
+        let diff = self.current_diff();
+        self.keyboard_view.set_diff(diff.as_ref());
+    }
+
+    fn refresh_lints(&self) {
+        self.lint_panel
+            .set_lints(&Lint::check(&self.layouts[self.current_layout]));
+    }
+
+    fn get_current_mapping(&self, key: KeyToken) -> Option<KeyAction> {
+        let layout = &self.layouts[self.current_layout];
+        layout
+            .find_by_source(key)
+            .first()
+            .map(|action| (*action).clone())
+    }
 }
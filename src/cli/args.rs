@@ -0,0 +1,243 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::models::{KeyToken, MacroStep};
+use crate::vdrive::LAYOUT_COUNT;
+
+pub const USAGE: &str = "\
+Usage: kinesis-configurator [OPTIONS] <COMMAND>
+
+Run without arguments to start the graphical configurator.
+
+Commands:
+  detect                          List mounted V-Drives
+  dump [LAYOUT]                   Print one layout, or every non-empty layout
+  validate [FILE...]              Check layout files, or every layout on the drive
+  set LAYOUT SOURCE TARGET        Remap SOURCE to the key token TARGET
+  set LAYOUT SOURCE --macro TEXT  Make SOURCE play the macro TEXT
+  clear LAYOUT SOURCE...          Remove the mappings for each SOURCE
+  clear LAYOUT --all              Remove every mapping in a layout
+  copy-layout FROM TO             Replace layout TO with a copy of layout FROM
+  backup [create]                 Back up the V-Drive
+  backup list                     List backups, newest first
+  backup restore NAME             Restore a backup onto the V-Drive
+
+Layouts are numbered 1 to 9. Key tokens use SmartSet names such as 'caps',
+'lshift' or 'kp-j'.
+
+Options:
+  --drive PATH       Use the V-Drive mounted at PATH instead of detecting it
+  --backup-dir DIR   Keep backups in DIR instead of the default location
+  -h, --help         Show this help";
+
+/// A parsed command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    pub drive: Option<PathBuf>,
+    pub backup_dir: Option<PathBuf>,
+    pub command: Command,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Help,
+    Detect,
+    /// Zero-based layout slot, or every layout
+    Dump {
+        layout: Option<usize>,
+    },
+    /// Files to check; the drive's layouts when empty
+    Validate {
+        files: Vec<PathBuf>,
+    },
+    Set {
+        layout: usize,
+        source: KeyToken,
+        target: Target,
+    },
+    Clear {
+        layout: usize,
+        /// Empty when clearing every mapping
+        sources: Vec<KeyToken>,
+    },
+    CopyLayout {
+        from: usize,
+        to: usize,
+    },
+    Backup(BackupCommand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Remap(KeyToken),
+    Macro(Vec<MacroStep>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackupCommand {
+    Create,
+    List,
+    Restore(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum UsageError {
+    #[error("no command given")]
+    MissingCommand,
+
+    #[error("unknown command '{0}'")]
+    UnknownCommand(String),
+
+    #[error("'{0}' expects a value")]
+    MissingValue(String),
+
+    #[error("unknown option '{0}'")]
+    UnknownOption(String),
+
+    #[error("missing {0}")]
+    MissingArgument(&'static str),
+
+    #[error("unexpected argument '{0}'")]
+    UnexpectedArgument(String),
+
+    #[error("invalid layout '{0}', expected 1 to {LAYOUT_COUNT}")]
+    InvalidLayout(String),
+
+    #[error("{0}")]
+    InvalidToken(String),
+
+    #[error("invalid macro '{text}': {message}")]
+    InvalidMacro { text: String, message: String },
+}
+
+/// Parse the arguments that follow the program name
+pub fn parse_args(args: &[String]) -> Result<Invocation, UsageError> {
+    let mut drive = None;
+    let mut backup_dir = None;
+    let mut macro_text = None;
+    let mut all = false;
+    let mut positional = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| UsageError::MissingValue(arg.clone()))
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => {
+                return Ok(Invocation {
+                    drive,
+                    backup_dir,
+                    command: Command::Help,
+                });
+            }
+            "--drive" => drive = Some(PathBuf::from(value()?)),
+            "--backup-dir" => backup_dir = Some(PathBuf::from(value()?)),
+            "--macro" => macro_text = Some(value()?),
+            "--all" => all = true,
+            option if option.starts_with("--") => {
+                return Err(UsageError::UnknownOption(option.to_string()));
+            }
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let name = positional.next().ok_or(UsageError::MissingCommand)?;
+
+    let command = match name {
+        "help" => Command::Help,
+        "detect" => Command::Detect,
+        "dump" => Command::Dump {
+            layout: positional.next().map(parse_layout).transpose()?,
+        },
+        "validate" => Command::Validate {
+            files: positional.by_ref().map(PathBuf::from).collect(),
+        },
+        "set" => {
+            let layout = parse_layout(required(&mut positional, "layout number")?)?;
+            let source = parse_token(required(&mut positional, "source key")?)?;
+            let target = match macro_text.take() {
+                Some(text) => Target::Macro(MacroStep::parse_sequence(&text).map_err(|e| {
+                    UsageError::InvalidMacro {
+                        text: text.clone(),
+                        message: e.to_string(),
+                    }
+                })?),
+                None => Target::Remap(parse_token(required(&mut positional, "target key")?)?),
+            };
+            Command::Set {
+                layout,
+                source,
+                target,
+            }
+        }
+        "clear" => {
+            let layout = parse_layout(required(&mut positional, "layout number")?)?;
+            let sources = positional
+                .by_ref()
+                .map(parse_token)
+                .collect::<Result<Vec<_>, _>>()?;
+            match (all, sources.is_empty()) {
+                (true, false) => return Err(UsageError::UnexpectedArgument("--all".to_string())),
+                (false, true) => return Err(UsageError::MissingArgument("source key or --all")),
+                _ => {}
+            }
+            all = false;
+            Command::Clear { layout, sources }
+        }
+        "copy-layout" => Command::CopyLayout {
+            from: parse_layout(required(&mut positional, "source layout number")?)?,
+            to: parse_layout(required(&mut positional, "target layout number")?)?,
+        },
+        "backup" => match positional.next() {
+            None | Some("create") => Command::Backup(BackupCommand::Create),
+            Some("list") => Command::Backup(BackupCommand::List),
+            Some("restore") => Command::Backup(BackupCommand::Restore(
+                required(&mut positional, "backup name")?.to_string(),
+            )),
+            Some(other) => return Err(UsageError::UnknownCommand(format!("backup {}", other))),
+        },
+        other => return Err(UsageError::UnknownCommand(other.to_string())),
+    };
+
+    if let Some(extra) = positional.next() {
+        return Err(UsageError::UnexpectedArgument(extra.to_string()));
+    }
+    if macro_text.is_some() {
+        return Err(UsageError::UnexpectedArgument("--macro".to_string()));
+    }
+    if all {
+        return Err(UsageError::UnexpectedArgument("--all".to_string()));
+    }
+
+    Ok(Invocation {
+        drive,
+        backup_dir,
+        command,
+    })
+}
+
+fn required<'a>(
+    args: &mut impl Iterator<Item = &'a str>,
+    what: &'static str,
+) -> Result<&'a str, UsageError> {
+    args.next().ok_or(UsageError::MissingArgument(what))
+}
+
+/// Parse a 1-based layout number into a zero-based slot
+fn parse_layout(arg: &str) -> Result<usize, UsageError> {
+    match arg.parse::<usize>() {
+        Ok(n @ 1..=LAYOUT_COUNT) => Ok(n - 1),
+        _ => Err(UsageError::InvalidLayout(arg.to_string())),
+    }
+}
+
+fn parse_token(arg: &str) -> Result<KeyToken, UsageError> {
+    arg.parse::<KeyToken>()
+        .map_err(|e| UsageError::InvalidToken(e.to_string()))
+}
@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use super::args::{BackupCommand, Command, Target, UsageError, parse_args};
+use crate::models::{KeyToken, MacroStep};
+
+fn parse(args: &[&str]) -> Result<Command, UsageError> {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    parse_args(&args).map(|invocation| invocation.command)
+}
+
+#[test]
+fn test_parse_set_remap() {
+    assert_eq!(
+        parse(&["set", "2", "caps", "esc"]),
+        Ok(Command::Set {
+            layout: 1,
+            source: KeyToken::Caps,
+            target: Target::Remap(KeyToken::Escape),
+        })
+    );
+}
+
+#[test]
+fn test_parse_set_macro() {
+    assert_eq!(
+        parse(&["set", "1", "f1", "--macro", "{-lctrl}{c}{+lctrl}"]),
+        Ok(Command::Set {
+            layout: 0,
+            source: KeyToken::F1,
+            target: Target::Macro(vec![
+                MacroStep::Press(KeyToken::LCtrl),
+                MacroStep::Tap(KeyToken::C),
+                MacroStep::Release(KeyToken::LCtrl),
+            ]),
+        })
+    );
+    assert!(matches!(
+        parse(&["set", "1", "f1", "--macro", "{oops"]),
+        Err(UsageError::InvalidMacro { .. })
+    ));
+}
+
+#[test]
+fn test_parse_global_options_anywhere() {
+    let args: Vec<String> = ["dump", "--drive", "/mnt/kb", "3", "--backup-dir", "/tmp/b"]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+    let invocation = parse_args(&args).unwrap();
+
+    assert_eq!(invocation.drive, Some(PathBuf::from("/mnt/kb")));
+    assert_eq!(invocation.backup_dir, Some(PathBuf::from("/tmp/b")));
+    assert_eq!(invocation.command, Command::Dump { layout: Some(2) });
+}
+
+#[test]
+fn test_parse_clear() {
+    assert_eq!(
+        parse(&["clear", "9", "a", "kp-j"]),
+        Ok(Command::Clear {
+            layout: 8,
+            sources: vec![KeyToken::A, KeyToken::J.on_keypad()],
+        })
+    );
+    assert_eq!(
+        parse(&["clear", "9", "--all"]),
+        Ok(Command::Clear {
+            layout: 8,
+            sources: Vec::new(),
+        })
+    );
+    assert_eq!(
+        parse(&["clear", "9"]),
+        Err(UsageError::MissingArgument("source key or --all"))
+    );
+}
+
+#[test]
+fn test_parse_backup() {
+    assert_eq!(
+        parse(&["backup"]),
+        Ok(Command::Backup(BackupCommand::Create))
+    );
+    assert_eq!(
+        parse(&["backup", "list"]),
+        Ok(Command::Backup(BackupCommand::List))
+    );
+    assert_eq!(
+        parse(&["backup", "restore", "2025-01-31T09-15-00Z"]),
+        Ok(Command::Backup(BackupCommand::Restore(
+            "2025-01-31T09-15-00Z".to_string()
+        )))
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(parse(&[]), Err(UsageError::MissingCommand));
+    assert_eq!(
+        parse(&["frobnicate"]),
+        Err(UsageError::UnknownCommand("frobnicate".to_string()))
+    );
+    assert_eq!(
+        parse(&["dump", "10"]),
+        Err(UsageError::InvalidLayout("10".to_string()))
+    );
+    assert_eq!(
+        parse(&["set", "1", "caps"]),
+        Err(UsageError::MissingArgument("target key"))
+    );
+    assert!(matches!(
+        parse(&["set", "1", "caps", "nope"]),
+        Err(UsageError::InvalidToken(_))
+    ));
+    assert_eq!(
+        parse(&["copy-layout", "1", "2", "3"]),
+        Err(UsageError::UnexpectedArgument("3".to_string()))
+    );
+    assert_eq!(
+        parse(&["dump", "--drive"]),
+        Err(UsageError::MissingValue("--drive".to_string()))
+    );
+    assert_eq!(
+        parse(&["detect", "--verbose"]),
+        Err(UsageError::UnknownOption("--verbose".to_string()))
+    );
+}
+
+#[test]
+fn test_help_wins_over_errors() {
+    assert_eq!(parse(&["set", "--help"]), Ok(Command::Help));
+}
@@ -0,0 +1,10 @@
+pub mod args;
+pub mod run;
+
+#[cfg(test)]
+mod args_test;
+#[cfg(test)]
+mod run_test;
+
+pub use args::{BackupCommand, Command, Invocation, Target, USAGE, UsageError, parse_args};
+pub use run::{execute, run};
@@ -0,0 +1,265 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+
+use super::args::{BackupCommand, Command, Invocation, Target, USAGE, parse_args};
+use crate::models::{KeyAction, KinesisLayout, LayoutLine, MacroStep};
+use crate::vdrive::{self, BACKUP_RETENTION, LAYOUT_COUNT, VDrive};
+
+/// Parse and run a command line, writing results to `out`
+pub fn run(args: &[String], out: &mut impl Write) -> anyhow::Result<()> {
+    let invocation = parse_args(args)?;
+    execute(&invocation, out)
+}
+
+pub fn execute(invocation: &Invocation, out: &mut impl Write) -> anyhow::Result<()> {
+    match &invocation.command {
+        Command::Help => writeln!(out, "{}", USAGE)?,
+        Command::Detect => {
+            let drives = vdrive::detect();
+            if drives.is_empty() {
+                bail!("no mounted V-Drive found");
+            }
+            for drive in drives {
+                writeln!(out, "{}", drive)?;
+            }
+        }
+        Command::Dump { layout } => {
+            let drive = resolve_drive(invocation)?;
+            match layout {
+                Some(idx) => write!(out, "{}", read_layout(&drive, *idx)?)?,
+                None => {
+                    for idx in 0..LAYOUT_COUNT {
+                        let layout = read_layout(&drive, idx)?;
+                        if layout.lines().next().is_some() {
+                            writeln!(out, "# layout{}.txt", idx + 1)?;
+                            write!(out, "{}", layout)?;
+                        }
+                    }
+                }
+            }
+        }
+        Command::Validate { files } => {
+            let files: Vec<PathBuf> = if files.is_empty() {
+                let drive = resolve_drive(invocation)?;
+                (0..LAYOUT_COUNT)
+                    .map(|idx| drive.layout_path(idx))
+                    .filter(|path| path.exists())
+                    .collect()
+            } else {
+                files.clone()
+            };
+
+            let mut problems = 0;
+            for path in &files {
+                problems += validate_file(path, out)?;
+            }
+            writeln!(
+                out,
+                "Checked {} file(s), {} problem(s) found",
+                files.len(),
+                problems
+            )?;
+            if problems > 0 {
+                bail!("validation failed");
+            }
+        }
+        Command::Set {
+            layout: idx,
+            source,
+            target,
+        } => {
+            let drive = resolve_drive(invocation)?;
+            let mut layout = read_layout(&drive, *idx)?;
+
+            let action = match target {
+                Target::Remap(target) => KeyAction::SimpleRemap {
+                    source: *source,
+                    target: *target,
+                },
+                Target::Macro(steps) => {
+                    if let Some(issue) = MacroStep::validate(steps).first() {
+                        bail!("unbalanced macro: {}", issue);
+                    }
+                    KeyAction::Macro {
+                        trigger: *source,
+                        steps: steps.clone(),
+                    }
+                }
+            };
+            layout.set_mapping(action);
+
+            write_layout(invocation, &drive, *idx, &layout, out)?;
+        }
+        Command::Clear {
+            layout: idx,
+            sources,
+        } => {
+            let drive = resolve_drive(invocation)?;
+            let mut layout = read_layout(&drive, *idx)?;
+            let before = layout.mapping_count();
+
+            if sources.is_empty() {
+                layout.clear_mappings();
+            }
+            for source in sources {
+                if layout.find_by_source(*source).is_empty() {
+                    writeln!(out, "No mapping for {} in layout {}", source, idx + 1)?;
+                }
+                layout.remove_by_source(*source);
+            }
+
+            let removed = before - layout.mapping_count();
+            if removed == 0 {
+                return Ok(());
+            }
+            write_layout(invocation, &drive, *idx, &layout, out)?;
+            writeln!(out, "Removed {} mapping(s)", removed)?;
+        }
+        Command::CopyLayout { from, to } => {
+            let drive = resolve_drive(invocation)?;
+            if !drive.layout_path(*from).exists() {
+                bail!("layout {} does not exist on {}", from + 1, drive);
+            }
+            let layout = read_layout(&drive, *from)?;
+            write_layout(invocation, &drive, *to, &layout, out)?;
+        }
+        Command::Backup(command) => {
+            let drive = resolve_drive(invocation)?;
+            let dir = backup_dir(invocation)?;
+            match command {
+                BackupCommand::Create => {
+                    let backup = backup(&drive, &dir)?;
+                    writeln!(out, "Backed up {} to {}", drive, backup.path.display())?;
+                }
+                BackupCommand::List => {
+                    for backup in vdrive::list_backups(&dir)? {
+                        writeln!(out, "{}", backup.name)?;
+                    }
+                }
+                BackupCommand::Restore(name) => {
+                    let Some(chosen) = vdrive::list_backups(&dir)?
+                        .into_iter()
+                        .find(|b| b.name == *name)
+                    else {
+                        bail!("no backup named '{}' in {}", name, dir.display());
+                    };
+                    let current = vdrive::restore_with_backup(&chosen, &drive, &dir)
+                        .with_context(|| format!("could not restore backup {}", name))?;
+                    writeln!(out, "Backed up {} to {}", drive, current.path.display())?;
+                    writeln!(out, "Restored {} onto {}", name, drive)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The drive given with `--drive`, or the only detected one
+fn resolve_drive(invocation: &Invocation) -> anyhow::Result<VDrive> {
+    if let Some(root) = &invocation.drive {
+        if !vdrive::is_vdrive(root) {
+            bail!(
+                "{} is not a V-Drive (no layouts/ and settings/ directories)",
+                root.display()
+            );
+        }
+        let label = root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| root.display().to_string());
+        return Ok(VDrive {
+            root: root.clone(),
+            label,
+            device: None,
+            fs_type: None,
+        });
+    }
+
+    let mut drives = vdrive::detect();
+    match drives.len() {
+        0 => bail!("no mounted V-Drive found; mount it or pass --drive PATH"),
+        1 => Ok(drives.remove(0)),
+        _ => {
+            let names: Vec<String> = drives.iter().map(|d| d.to_string()).collect();
+            bail!(
+                "more than one V-Drive found, pass --drive PATH to choose one of:\n  {}",
+                names.join("\n  ")
+            )
+        }
+    }
+}
+
+fn backup_dir(invocation: &Invocation) -> anyhow::Result<PathBuf> {
+    match &invocation.backup_dir {
+        Some(dir) => Ok(dir.clone()),
+        None => vdrive::backups_dir()
+            .context("neither XDG_DATA_HOME nor HOME is set; pass --backup-dir DIR"),
+    }
+}
+
+fn backup(drive: &VDrive, dir: &Path) -> anyhow::Result<vdrive::Backup> {
+    let backup = vdrive::create_backup(drive, dir)
+        .with_context(|| format!("could not back up {}", drive))?;
+    vdrive::prune_backups(dir, BACKUP_RETENTION)?;
+    Ok(backup)
+}
+
+fn read_layout(drive: &VDrive, idx: usize) -> anyhow::Result<KinesisLayout> {
+    let path = drive.layout_path(idx);
+    let (layout, _) = KinesisLayout::from_file_lenient(&path)
+        .with_context(|| format!("could not read {}", path.display()))?;
+    Ok(layout)
+}
+
+/// Back up the drive, then write one layout
+fn write_layout(
+    invocation: &Invocation,
+    drive: &VDrive,
+    idx: usize,
+    layout: &KinesisLayout,
+    out: &mut impl Write,
+) -> anyhow::Result<()> {
+    let backup = backup(drive, &backup_dir(invocation)?)?;
+    writeln!(out, "Backed up {} to {}", drive, backup.path.display())?;
+
+    let path = drive.layout_path(idx);
+    layout
+        .to_file(&path)
+        .with_context(|| format!("could not write {}", path.display()))?;
+    writeln!(out, "Wrote {}", path.display())?;
+    Ok(())
+}
+
+/// Print every problem in a layout file, returning how many there were
+fn validate_file(path: &Path, out: &mut impl Write) -> anyhow::Result<usize> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("could not read {}", path.display()))?;
+    let (layout, errors) = KinesisLayout::parse_lenient(&content);
+    let name = path.display();
+
+    for e in &errors {
+        writeln!(out, "{}:{}:{}: {}", name, e.line, e.span.start + 1, e.kind)?;
+    }
+
+    let mut problems = errors.len();
+    for (line_idx, line) in layout.lines().enumerate() {
+        if let LayoutLine::Mapping(KeyAction::Macro { trigger, steps }) = line {
+            for issue in MacroStep::validate(steps) {
+                writeln!(
+                    out,
+                    "{}:{}: macro {{{}}}: {}",
+                    name,
+                    line_idx + 1,
+                    trigger,
+                    issue
+                )?;
+                problems += 1;
+            }
+        }
+    }
+
+    Ok(problems)
+}
@@ -0,0 +1,142 @@
+use std::fs;
+use std::path::Path;
+
+use super::run::run;
+use crate::vdrive::fixture::make_vdrive;
+
+struct Fixture {
+    _dir: tempfile::TempDir,
+    drive: String,
+    backups: String,
+}
+
+fn fixture() -> Fixture {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = make_vdrive(
+        &dir.path().join("ADV360"),
+        &[("layouts/layout1.txt", "# home\n[caps]>[esc]\n")],
+    );
+    Fixture {
+        drive: drive.root.display().to_string(),
+        backups: dir.path().join("backups").display().to_string(),
+        _dir: dir,
+    }
+}
+
+/// Run a command against the fixture drive, returning its output
+fn run_on(fx: &Fixture, args: &[&str]) -> anyhow::Result<String> {
+    let mut full: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    full.extend(["--drive".to_string(), fx.drive.clone()]);
+    full.extend(["--backup-dir".to_string(), fx.backups.clone()]);
+
+    let mut out = Vec::new();
+    run(&full, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+fn layout_file(fx: &Fixture, n: usize) -> String {
+    fs::read_to_string(Path::new(&fx.drive).join(format!("layouts/layout{}.txt", n)))
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_dump_layout() {
+    let fx = fixture();
+    assert_eq!(
+        run_on(&fx, &["dump", "1"]).unwrap(),
+        "# home\n[caps]>[esc]\n"
+    );
+    assert_eq!(
+        run_on(&fx, &["dump"]).unwrap(),
+        "# layout1.txt\n# home\n[caps]>[esc]\n"
+    );
+}
+
+#[test]
+fn test_set_and_clear_keep_comments() {
+    let fx = fixture();
+
+    run_on(&fx, &["set", "1", "a", "b"]).unwrap();
+    run_on(&fx, &["set", "1", "caps", "lctrl"]).unwrap();
+    assert_eq!(layout_file(&fx, 1), "# home\n[caps]>[lctrl]\n[a]>[b]\n");
+
+    let output = run_on(&fx, &["clear", "1", "caps"]).unwrap();
+    assert!(output.contains("Removed 1 mapping(s)"));
+    assert_eq!(layout_file(&fx, 1), "# home\n[a]>[b]\n");
+
+    run_on(&fx, &["clear", "1", "--all"]).unwrap();
+    assert_eq!(layout_file(&fx, 1), "# home\n");
+}
+
+#[test]
+fn test_writes_are_backed_up() {
+    let fx = fixture();
+    run_on(&fx, &["set", "1", "a", "b"]).unwrap();
+
+    let listed = run_on(&fx, &["backup", "list"]).unwrap();
+    let name = listed.lines().next().unwrap();
+    assert_eq!(
+        fs::read_to_string(
+            Path::new(&fx.backups)
+                .join(name)
+                .join("layouts/layout1.txt")
+        )
+        .unwrap(),
+        "# home\n[caps]>[esc]\n"
+    );
+
+    run_on(&fx, &["backup", "restore", name]).unwrap();
+    assert_eq!(layout_file(&fx, 1), "# home\n[caps]>[esc]\n");
+}
+
+#[test]
+fn test_unbalanced_macro_is_rejected() {
+    let fx = fixture();
+    let err = run_on(&fx, &["set", "1", "f1", "--macro", "{-lalt}{tab}"]).unwrap_err();
+    assert!(err.to_string().contains("never released"));
+    assert_eq!(layout_file(&fx, 1), "# home\n[caps]>[esc]\n");
+}
+
+#[test]
+fn test_copy_layout() {
+    let fx = fixture();
+    run_on(&fx, &["copy-layout", "1", "5"]).unwrap();
+    assert_eq!(layout_file(&fx, 5), "# home\n[caps]>[esc]\n");
+
+    assert!(run_on(&fx, &["copy-layout", "2", "5"]).is_err());
+}
+
+#[test]
+fn test_validate_reports_problems() {
+    let fx = fixture();
+    let bad = Path::new(&fx.drive).join("layouts/layout2.txt");
+    fs::write(&bad, "[a]>[b]\n[q]>[nope]\n{f1}>{-lalt}\n").unwrap();
+
+    let mut out = Vec::new();
+    let args: Vec<String> = ["validate", bad.to_str().unwrap()]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+    assert!(run(&args, &mut out).is_err());
+
+    let output = String::from_utf8(out).unwrap();
+    let name = bad.display();
+    assert!(output.contains(&format!("{}:2:6: unknown key token 'nope'", name)));
+    assert!(output.contains(&format!("{}:3: macro {{f1}}: 'lalt' is pressed", name)));
+    assert!(output.contains("Checked 1 file(s), 2 problem(s) found"));
+
+    assert!(run_on(&fx, &["validate"]).is_err());
+    fs::remove_file(&bad).unwrap();
+    assert!(run_on(&fx, &["validate"]).is_ok());
+}
+
+#[test]
+fn test_drive_must_be_a_vdrive() {
+    let dir = tempfile::tempdir().unwrap();
+    let args: Vec<String> = ["dump", "--drive", dir.path().to_str().unwrap()]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+    let err = run(&args, &mut Vec::new()).unwrap_err();
+    assert!(err.to_string().contains("is not a V-Drive"));
+}
@@ -0,0 +1,297 @@
+use gtk4::gdk;
+
+use crate::models::KeyToken;
+
+/// Keys that do not produce a character, by GDK key value
+const NAMED_KEYS: &[(gdk::Key, KeyToken)] = &[
+    // Modifiers, left and right kept apart
+    (gdk::Key::Shift_L, KeyToken::LShift),
+    (gdk::Key::Shift_R, KeyToken::RShift),
+    (gdk::Key::Control_L, KeyToken::LCtrl),
+    (gdk::Key::Control_R, KeyToken::RCtrl),
+    (gdk::Key::Alt_L, KeyToken::LAlt),
+    (gdk::Key::Alt_R, KeyToken::RAlt),
+    (gdk::Key::ISO_Level3_Shift, KeyToken::RAlt),
+    (gdk::Key::Meta_L, KeyToken::LAlt),
+    (gdk::Key::Meta_R, KeyToken::RAlt),
+    (gdk::Key::Super_L, KeyToken::LWin),
+    (gdk::Key::Super_R, KeyToken::RWin),
+    // Editing and navigation
+    (gdk::Key::Tab, KeyToken::Tab),
+    (gdk::Key::ISO_Left_Tab, KeyToken::Tab),
+    (gdk::Key::Caps_Lock, KeyToken::Caps),
+    (gdk::Key::Escape, KeyToken::Escape),
+    (gdk::Key::Return, KeyToken::Enter),
+    (gdk::Key::space, KeyToken::Space),
+    (gdk::Key::BackSpace, KeyToken::Backspace),
+    (gdk::Key::Delete, KeyToken::Delete),
+    (gdk::Key::Insert, KeyToken::Insert),
+    (gdk::Key::Home, KeyToken::Home),
+    (gdk::Key::End, KeyToken::End),
+    (gdk::Key::Page_Up, KeyToken::PageUp),
+    (gdk::Key::Page_Down, KeyToken::PageDown),
+    (gdk::Key::Up, KeyToken::Up),
+    (gdk::Key::Down, KeyToken::Down),
+    (gdk::Key::Left, KeyToken::Left),
+    (gdk::Key::Right, KeyToken::Right),
+    (gdk::Key::Menu, KeyToken::Menu),
+    (gdk::Key::Print, KeyToken::PrintScreen),
+    (gdk::Key::Scroll_Lock, KeyToken::ScrollLock),
+    (gdk::Key::Pause, KeyToken::Pause),
+    // Function keys
+    (gdk::Key::F1, KeyToken::F1),
+    (gdk::Key::F2, KeyToken::F2),
+    (gdk::Key::F3, KeyToken::F3),
+    (gdk::Key::F4, KeyToken::F4),
+    (gdk::Key::F5, KeyToken::F5),
+    (gdk::Key::F6, KeyToken::F6),
+    (gdk::Key::F7, KeyToken::F7),
+    (gdk::Key::F8, KeyToken::F8),
+    (gdk::Key::F9, KeyToken::F9),
+    (gdk::Key::F10, KeyToken::F10),
+    (gdk::Key::F11, KeyToken::F11),
+    (gdk::Key::F12, KeyToken::F12),
+    (gdk::Key::F13, KeyToken::F13),
+    (gdk::Key::F14, KeyToken::F14),
+    (gdk::Key::F15, KeyToken::F15),
+    (gdk::Key::F16, KeyToken::F16),
+    (gdk::Key::F17, KeyToken::F17),
+    (gdk::Key::F18, KeyToken::F18),
+    (gdk::Key::F19, KeyToken::F19),
+    (gdk::Key::F20, KeyToken::F20),
+    (gdk::Key::F21, KeyToken::F21),
+    (gdk::Key::F22, KeyToken::F22),
+    (gdk::Key::F23, KeyToken::F23),
+    (gdk::Key::F24, KeyToken::F24),
+    // Keypad, with and without Num Lock
+    (gdk::Key::Num_Lock, KeyToken::NumLock),
+    (gdk::Key::KP_0, KeyToken::Kp0),
+    (gdk::Key::KP_1, KeyToken::Kp1),
+    (gdk::Key::KP_2, KeyToken::Kp2),
+    (gdk::Key::KP_3, KeyToken::Kp3),
+    (gdk::Key::KP_4, KeyToken::Kp4),
+    (gdk::Key::KP_5, KeyToken::Kp5),
+    (gdk::Key::KP_6, KeyToken::Kp6),
+    (gdk::Key::KP_7, KeyToken::Kp7),
+    (gdk::Key::KP_8, KeyToken::Kp8),
+    (gdk::Key::KP_9, KeyToken::Kp9),
+    (gdk::Key::KP_Insert, KeyToken::Kp0),
+    (gdk::Key::KP_End, KeyToken::Kp1),
+    (gdk::Key::KP_Down, KeyToken::Kp2),
+    (gdk::Key::KP_Page_Down, KeyToken::Kp3),
+    (gdk::Key::KP_Left, KeyToken::Kp4),
+    (gdk::Key::KP_Begin, KeyToken::Kp5),
+    (gdk::Key::KP_Right, KeyToken::Kp6),
+    (gdk::Key::KP_Home, KeyToken::Kp7),
+    (gdk::Key::KP_Up, KeyToken::Kp8),
+    (gdk::Key::KP_Page_Up, KeyToken::Kp9),
+    (gdk::Key::KP_Decimal, KeyToken::KpPeriod),
+    (gdk::Key::KP_Delete, KeyToken::KpPeriod),
+    (gdk::Key::KP_Enter, KeyToken::KpEnter),
+    (gdk::Key::KP_Add, KeyToken::KpPlus),
+    (gdk::Key::KP_Subtract, KeyToken::KpMinus),
+    (gdk::Key::KP_Multiply, KeyToken::KpMultiply),
+    (gdk::Key::KP_Divide, KeyToken::KpDivide),
+    (gdk::Key::KP_Equal, KeyToken::KpEquals),
+    // Media
+    (gdk::Key::AudioMute, KeyToken::Mute),
+    (gdk::Key::AudioLowerVolume, KeyToken::VolumeDown),
+    (gdk::Key::AudioRaiseVolume, KeyToken::VolumeUp),
+    (gdk::Key::AudioPrev, KeyToken::PrevTrack),
+    (gdk::Key::AudioPlay, KeyToken::PlayPause),
+    (gdk::Key::AudioNext, KeyToken::NextTrack),
+    (gdk::Key::Calculator, KeyToken::Calculator),
+];
+
+/// GDK reports Linux evdev key codes offset by this much
+const EVDEV_OFFSET: u32 = 8;
+
+/// Physical keys by Linux evdev code, so the token does not depend on the
+/// active keyboard layout or held modifiers
+const PHYSICAL_KEYS: &[(u32, KeyToken)] = &[
+    (1, KeyToken::Escape),
+    (2, KeyToken::Digit1),
+    (3, KeyToken::Digit2),
+    (4, KeyToken::Digit3),
+    (5, KeyToken::Digit4),
+    (6, KeyToken::Digit5),
+    (7, KeyToken::Digit6),
+    (8, KeyToken::Digit7),
+    (9, KeyToken::Digit8),
+    (10, KeyToken::Digit9),
+    (11, KeyToken::Digit0),
+    (12, KeyToken::Hyphen),
+    (13, KeyToken::Equals),
+    (14, KeyToken::Backspace),
+    (15, KeyToken::Tab),
+    (16, KeyToken::Q),
+    (17, KeyToken::W),
+    (18, KeyToken::E),
+    (19, KeyToken::R),
+    (20, KeyToken::T),
+    (21, KeyToken::Y),
+    (22, KeyToken::U),
+    (23, KeyToken::I),
+    (24, KeyToken::O),
+    (25, KeyToken::P),
+    (26, KeyToken::OpenBracket),
+    (27, KeyToken::CloseBracket),
+    (28, KeyToken::Enter),
+    (29, KeyToken::LCtrl),
+    (30, KeyToken::A),
+    (31, KeyToken::S),
+    (32, KeyToken::D),
+    (33, KeyToken::F),
+    (34, KeyToken::G),
+    (35, KeyToken::H),
+    (36, KeyToken::J),
+    (37, KeyToken::K),
+    (38, KeyToken::L),
+    (39, KeyToken::Semicolon),
+    (40, KeyToken::Quote),
+    (41, KeyToken::Backtick),
+    (42, KeyToken::LShift),
+    (43, KeyToken::Backslash),
+    (44, KeyToken::Z),
+    (45, KeyToken::X),
+    (46, KeyToken::C),
+    (47, KeyToken::V),
+    (48, KeyToken::B),
+    (49, KeyToken::N),
+    (50, KeyToken::M),
+    (51, KeyToken::Comma),
+    (52, KeyToken::Period),
+    (53, KeyToken::Slash),
+    (54, KeyToken::RShift),
+    (55, KeyToken::KpMultiply),
+    (56, KeyToken::LAlt),
+    (57, KeyToken::Space),
+    (58, KeyToken::Caps),
+    (59, KeyToken::F1),
+    (60, KeyToken::F2),
+    (61, KeyToken::F3),
+    (62, KeyToken::F4),
+    (63, KeyToken::F5),
+    (64, KeyToken::F6),
+    (65, KeyToken::F7),
+    (66, KeyToken::F8),
+    (67, KeyToken::F9),
+    (68, KeyToken::F10),
+    (69, KeyToken::NumLock),
+    (70, KeyToken::ScrollLock),
+    (71, KeyToken::Kp7),
+    (72, KeyToken::Kp8),
+    (73, KeyToken::Kp9),
+    (74, KeyToken::KpMinus),
+    (75, KeyToken::Kp4),
+    (76, KeyToken::Kp5),
+    (77, KeyToken::Kp6),
+    (78, KeyToken::KpPlus),
+    (79, KeyToken::Kp1),
+    (80, KeyToken::Kp2),
+    (81, KeyToken::Kp3),
+    (82, KeyToken::Kp0),
+    (83, KeyToken::KpPeriod),
+    (86, KeyToken::IntlBackslash),
+    (87, KeyToken::F11),
+    (88, KeyToken::F12),
+    (89, KeyToken::IntlRo),
+    (96, KeyToken::KpEnter),
+    (97, KeyToken::RCtrl),
+    (98, KeyToken::KpDivide),
+    (99, KeyToken::PrintScreen),
+    (100, KeyToken::RAlt),
+    (102, KeyToken::Home),
+    (103, KeyToken::Up),
+    (104, KeyToken::PageUp),
+    (105, KeyToken::Left),
+    (106, KeyToken::Right),
+    (107, KeyToken::End),
+    (108, KeyToken::Down),
+    (109, KeyToken::PageDown),
+    (110, KeyToken::Insert),
+    (111, KeyToken::Delete),
+    (113, KeyToken::Mute),
+    (114, KeyToken::VolumeDown),
+    (115, KeyToken::VolumeUp),
+    (117, KeyToken::KpEquals),
+    (119, KeyToken::Pause),
+    (124, KeyToken::IntlYen),
+    (125, KeyToken::LWin),
+    (126, KeyToken::RWin),
+    (127, KeyToken::Menu),
+    (140, KeyToken::Calculator),
+    (163, KeyToken::NextTrack),
+    (164, KeyToken::PlayPause),
+    (165, KeyToken::PrevTrack),
+    (183, KeyToken::F13),
+    (184, KeyToken::F14),
+    (185, KeyToken::F15),
+    (186, KeyToken::F16),
+    (187, KeyToken::F17),
+    (188, KeyToken::F18),
+    (189, KeyToken::F19),
+    (190, KeyToken::F20),
+    (191, KeyToken::F21),
+    (192, KeyToken::F22),
+    (193, KeyToken::F23),
+    (194, KeyToken::F24),
+];
+
+/// Shifted symbols on a US layout and the key that produces them
+const SHIFTED_SYMBOLS: &[(char, KeyToken)] = &[
+    ('!', KeyToken::Digit1),
+    ('@', KeyToken::Digit2),
+    ('#', KeyToken::Digit3),
+    ('$', KeyToken::Digit4),
+    ('%', KeyToken::Digit5),
+    ('^', KeyToken::Digit6),
+    ('&', KeyToken::Digit7),
+    ('*', KeyToken::Digit8),
+    ('(', KeyToken::Digit9),
+    (')', KeyToken::Digit0),
+    ('_', KeyToken::Hyphen),
+    ('+', KeyToken::Equals),
+    ('{', KeyToken::OpenBracket),
+    ('}', KeyToken::CloseBracket),
+    ('|', KeyToken::Backslash),
+    (':', KeyToken::Semicolon),
+    ('"', KeyToken::Quote),
+    ('~', KeyToken::Backtick),
+    ('<', KeyToken::Comma),
+    ('>', KeyToken::Period),
+    ('?', KeyToken::Slash),
+];
+
+/// The SmartSet token for the key that produced a GDK key value. Shifted
+/// symbols map back to their unshifted key, so `!` gives `1`.
+pub fn token_from_keyval(keyval: gdk::Key) -> Option<KeyToken> {
+    if let Some((_, token)) = NAMED_KEYS.iter().find(|(key, _)| *key == keyval) {
+        return Some(*token);
+    }
+
+    let ch = keyval.to_lower().to_unicode()?;
+    if let Some((_, token)) = SHIFTED_SYMBOLS.iter().find(|(symbol, _)| *symbol == ch) {
+        return Some(*token);
+    }
+
+    // Letters, digits and unshifted punctuation use the character as the
+    // token name
+    let name = ch.to_string();
+    KeyToken::all().find(|token| token.name() == name)
+}
+
+/// The SmartSet token for the physical key with a GDK hardware key code
+pub fn token_from_keycode(keycode: u32) -> Option<KeyToken> {
+    let code = keycode.checked_sub(EVDEV_OFFSET)?;
+    PHYSICAL_KEYS
+        .iter()
+        .find(|(physical, _)| *physical == code)
+        .map(|(_, token)| *token)
+}
+
+/// The SmartSet token for a key event: the physical key where it is known,
+/// otherwise whatever the key value maps to
+pub fn token_from_key_event(keyval: gdk::Key, keycode: u32) -> Option<KeyToken> {
+    token_from_keycode(keycode).or_else(|| token_from_keyval(keyval))
+}
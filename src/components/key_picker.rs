@@ -0,0 +1,193 @@
+use std::rc::Rc;
+
+use gtk4::prelude::*;
+
+use super::keyboard_view::KeyboardView;
+use crate::models::{KeyCategory, KeyToken};
+
+/// A key in the picker and the flow box child holding it
+#[derive(Debug)]
+struct PickerKey {
+    token: KeyToken,
+    child: gtk4::FlowBoxChild,
+    button: gtk4::ToggleButton,
+}
+
+/// A category heading and its keys
+#[derive(Debug)]
+struct PickerSection {
+    root: gtk4::Box,
+    keys: Vec<PickerKey>,
+}
+
+/// Every base key token, grouped by category and filtered by a search, for
+/// choosing a remap target. At most one key is selected.
+#[derive(Debug, Clone)]
+pub struct KeyPicker {
+    root: gtk4::Box,
+    sections: Rc<Vec<PickerSection>>,
+}
+
+impl KeyPicker {
+    pub fn new() -> Self {
+        let root = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+
+        let search = gtk4::SearchEntry::new();
+        search.set_placeholder_text(Some("Search keys"));
+        root.append(&search);
+
+        let list = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+        let scrolled = gtk4::ScrolledWindow::new();
+        scrolled.set_hscrollbar_policy(gtk4::PolicyType::Never);
+        scrolled.set_min_content_height(260);
+        scrolled.set_vexpand(true);
+        scrolled.set_child(Some(&list));
+        root.append(&scrolled);
+
+        let mut sections = Vec::new();
+        for category in KeyCategory::ALL {
+            let section_box = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+            let heading = gtk4::Label::new(Some(&category.to_string()));
+            heading.set_halign(gtk4::Align::Start);
+            heading.add_css_class("heading");
+            section_box.append(&heading);
+
+            let flow = gtk4::FlowBox::new();
+            flow.set_selection_mode(gtk4::SelectionMode::None);
+            flow.set_homogeneous(true);
+            flow.set_max_children_per_line(10);
+            flow.set_row_spacing(4);
+            flow.set_column_spacing(4);
+            section_box.append(&flow);
+            list.append(&section_box);
+
+            let keys = KeyToken::all()
+                .filter(|token| token.category() == category)
+                .map(|token| {
+                    let button = key_button(token);
+                    let child = gtk4::FlowBoxChild::new();
+                    child.set_child(Some(&button));
+                    flow.insert(&child, -1);
+                    PickerKey {
+                        token,
+                        child,
+                        button,
+                    }
+                })
+                .collect();
+            sections.push(PickerSection {
+                root: section_box,
+                keys,
+            });
+        }
+
+        let picker = Self {
+            root,
+            sections: Rc::new(sections),
+        };
+
+        // Selecting one key deselects the rest. Handlers are owned by the
+        // widgets in the sections, so they only hold weak references to them.
+        for key in picker.keys() {
+            let sections = Rc::downgrade(&picker.sections);
+            let token = key.token;
+            key.button.connect_toggled(move |button| {
+                if button.is_active()
+                    && let Some(sections) = sections.upgrade()
+                {
+                    all_keys(&sections)
+                        .filter(|other| other.token != token)
+                        .for_each(|other| other.button.set_active(false));
+                }
+            });
+        }
+
+        let sections = Rc::downgrade(&picker.sections);
+        search.connect_search_changed(move |search| {
+            let Some(sections) = sections.upgrade() else {
+                return;
+            };
+            let query = search.text();
+            for section in sections.iter() {
+                let mut any_visible = false;
+                for key in &section.keys {
+                    let visible = key.token.matches_search(&query);
+                    key.child.set_visible(visible);
+                    any_visible |= visible;
+                }
+                section.root.set_visible(any_visible);
+            }
+        });
+
+        // Enter picks the first match
+        let sections = Rc::downgrade(&picker.sections);
+        search.connect_activate(move |_| {
+            if let Some(sections) = sections.upgrade()
+                && let Some(first) = all_keys(&sections).find(|key| key.child.is_visible())
+            {
+                first.button.set_active(true);
+                first.button.grab_focus();
+            }
+        });
+
+        picker
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &PickerKey> {
+        all_keys(&self.sections)
+    }
+
+    /// The chosen key, if any
+    pub fn selected(&self) -> Option<KeyToken> {
+        self.keys()
+            .find(|key| key.button.is_active())
+            .map(|key| key.token)
+    }
+
+    /// Choose a key, scrolling it into view, or clear the choice with `None`
+    pub fn select(&self, token: Option<KeyToken>) {
+        let token = token.map(|t| t.base());
+        for key in self.keys() {
+            key.button.set_active(Some(key.token) == token);
+        }
+        if let Some(key) = self.keys().find(|key| Some(key.token) == token)
+            && key.child.is_visible()
+        {
+            key.button.grab_focus();
+        }
+    }
+
+    pub fn widget(&self) -> &gtk4::Box {
+        &self.root
+    }
+}
+
+impl Default for KeyPicker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn all_keys(sections: &[PickerSection]) -> impl Iterator<Item = &PickerKey> {
+    sections.iter().flat_map(|s| s.keys.iter())
+}
+
+/// A toggle button showing a key cap and the token name
+fn key_button(token: KeyToken) -> gtk4::ToggleButton {
+    let content = gtk4::Box::new(gtk4::Orientation::Vertical, 2);
+    content.append(&KeyboardView::key_cap(token));
+    let name = gtk4::Label::new(Some(token.name()));
+    name.add_css_class("caption");
+    content.append(&name);
+
+    let button = gtk4::ToggleButton::new();
+    button.set_child(Some(&content));
+    button.add_css_class("flat");
+    button.set_tooltip_text(Some(token.name()));
+    button.update_property(&[gtk4::accessible::Property::Label(&format!(
+        "{} ({})",
+        token.legend().replace('\n', " "),
+        token.name()
+    ))]);
+    button
+}
@@ -1,18 +1,21 @@
+use super::remap_dialog::RemapTarget;
 use crate::app::AppMsg;
 use crate::constants;
+use crate::models::{DiffKind, KeyToken, Layer, LayoutDiff};
 use gtk4::DrawingArea;
+use gtk4::gdk;
 use gtk4::gio;
 use gtk4::glib;
 use gtk4::prelude::*;
 use relm4::Sender;
 use rsvg;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap};
 use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub struct Key {
-    pub label: String,
+    pub token: KeyToken,
     pub remapped_label: Option<String>,
     pub x: f64,
     pub y: f64,
@@ -22,12 +25,38 @@ pub struct Key {
     pub svg_data: Option<&'static str>,
 }
 
+impl Key {
+    /// What a screen reader announces for the key on a layer
+    fn accessible_name(&self, layer: Layer, selected: bool) -> String {
+        let legend = layer.default_action(self.token).legend().replace('\n', " ");
+        let name = match &self.remapped_label {
+            Some(remapped) => format!("{}, remapped to {}", legend, remapped.replace('\n', " ")),
+            None => legend,
+        };
+        if selected {
+            format!("{}, selected", name)
+        } else {
+            name
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct KeyboardView {
     drawing_area: DrawingArea,
     keys: Rc<RefCell<Vec<Key>>>,
-    remappings: Rc<RefCell<HashMap<String, String>>>,
-    hovered_key: Rc<RefCell<Option<String>>>,
+    remappings: Rc<RefCell<HashMap<KeyToken, String>>>,
+    hovered_key: Rc<RefCell<Option<KeyToken>>>,
+    /// Key the keyboard cursor is on
+    focused_key: Rc<Cell<Option<KeyToken>>>,
+    /// Key under a key being dragged
+    drop_target: Rc<Cell<Option<KeyToken>>>,
+    /// Keys picked for a bulk edit with Ctrl-click or a rubber band
+    selected: Rc<RefCell<BTreeSet<KeyToken>>>,
+    /// Layer whose legends and remaps are shown
+    layer: Rc<Cell<Layer>>,
+    /// Diff status by source key while comparing layouts
+    diff: Rc<RefCell<HashMap<KeyToken, DiffKind>>>,
 }
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum KeySection {
@@ -36,35 +65,265 @@ pub enum KeySection {
     LeftThumb,
     RightThumb,
 }
+
+/// Keys drawn differently from the rest
+#[derive(Debug, Clone, Copy)]
+struct Highlight<'a> {
+    /// Key under the pointer
+    hovered: Option<KeyToken>,
+    /// Key the keyboard cursor is on, while the view has focus
+    focused: Option<KeyToken>,
+    /// Key a dragged key would be dropped on
+    drop_target: Option<KeyToken>,
+    selected: &'a BTreeSet<KeyToken>,
+    /// Rubber band being dragged out, in widget coordinates
+    band: Option<Band>,
+}
+
+/// A rubber band from where the drag started to the pointer
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Band {
+    start: (f64, f64),
+    end: (f64, f64),
+}
+
+impl Band {
+    /// Left, top, width and height
+    fn rectangle(&self) -> (f64, f64, f64, f64) {
+        let (x0, y0) = self.start;
+        let (x1, y1) = self.end;
+        (x0.min(x1), y0.min(y1), (x1 - x0).abs(), (y1 - y0).abs())
+    }
+}
+
+/// A direction the keyboard cursor moves in
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    /// Arrow keys and their vi equivalents
+    fn from_keyval(keyval: gdk::Key) -> Option<Self> {
+        match keyval {
+            gdk::Key::Left | gdk::Key::KP_Left | gdk::Key::h => Some(Direction::Left),
+            gdk::Key::Right | gdk::Key::KP_Right | gdk::Key::l => Some(Direction::Right),
+            gdk::Key::Up | gdk::Key::KP_Up | gdk::Key::k => Some(Direction::Up),
+            gdk::Key::Down | gdk::Key::KP_Down | gdk::Key::j => Some(Direction::Down),
+            _ => None,
+        }
+    }
+}
+
 impl KeyboardView {
     pub fn new(sender: Sender<AppMsg>) -> Self {
         let drawing_area = DrawingArea::new();
         drawing_area.set_size_request(1200, 500);
         drawing_area.set_hexpand(true);
         drawing_area.set_vexpand(true);
+        drawing_area.set_focusable(true);
+        drawing_area.update_property(&[
+            gtk4::accessible::Property::Label("Keyboard"),
+            gtk4::accessible::Property::Description(
+                "Arrow keys or h, j, k and l move between keys, Enter remaps the key and Delete clears its mapping. Control+Space selects keys for a bulk edit and Escape clears the selection.",
+            ),
+        ]);
 
         let keys = Rc::new(RefCell::new(Vec::new()));
         let remappings = Rc::new(RefCell::new(HashMap::new()));
         let hovered_key = Rc::new(RefCell::new(None));
+        let focused_key = Rc::new(Cell::new(None));
+        let drop_target = Rc::new(Cell::new(None));
+        let selected = Rc::new(RefCell::new(BTreeSet::new()));
+        let band: Rc<Cell<Option<Band>>> = Rc::new(Cell::new(None));
+        let layer = Rc::new(Cell::new(Layer::Base));
 
         let gesture = gtk4::GestureClick::new();
         let keys_for_click = keys.clone();
         let sender_for_click = sender.clone();
+        let layer_for_click = layer.clone();
+        let focused_key_for_click = focused_key.clone();
 
+        let selected_for_click = selected.clone();
+
+        let keys_for_press = keys.clone();
+        let layer_for_press = layer.clone();
+        let selected_for_press = selected.clone();
         gesture.connect_pressed(move |gesture, _n, x, y| {
+            if let Some(widget) = gesture.widget() {
+                let width = widget.width();
+                let height = widget.height();
+
+                if let Some(key) = Self::find_key_at_position(&keys_for_press, x, y, width, height)
+                {
+                    focused_key_for_click.set(Some(key.token));
+                    widget.grab_focus();
+                    Self::update_accessible_label(
+                        &widget,
+                        &keys_for_press.borrow(),
+                        Some(key.token),
+                        layer_for_press.get(),
+                        &selected_for_press.borrow(),
+                    );
+                }
+            }
+        });
+
+        // Open the remap dialog on release, as a press may start a drag.
+        // Ctrl-click adds or removes the key from the selection instead.
+        gesture.connect_released(move |gesture, _n, x, y| {
             if let Some(widget) = gesture.widget() {
                 let width = widget.width();
                 let height = widget.height();
 
                 if let Some(key) = Self::find_key_at_position(&keys_for_click, x, y, width, height)
                 {
-                    println!("Clicked key: {}", key.label);
-                    let _ = sender_for_click.send(AppMsg::KeyClicked(key.label.clone()));
+                    if gesture
+                        .current_event_state()
+                        .contains(gdk::ModifierType::CONTROL_MASK)
+                    {
+                        Self::toggle_selected(
+                            &widget,
+                            &sender_for_click,
+                            &keys_for_click.borrow(),
+                            &selected_for_click,
+                            key.token,
+                        );
+                        return;
+                    }
+                    let source = layer_for_click.get().position(key.token);
+                    println!("Clicked key: {}", source);
+                    let _ = sender_for_click.send(AppMsg::KeyClicked(source));
                 }
             }
         });
         drawing_area.add_controller(gesture);
 
+        // Dragging a key onto another swaps them, or with Shift held remaps
+        // the dragged key only. Dragging from between keys draws a rubber
+        // band that selects the keys inside it, adding to the selection
+        // with Ctrl held.
+        let drag = gtk4::GestureDrag::new();
+        let drag_source: Rc<Cell<Option<KeyToken>>> = Rc::new(Cell::new(None));
+        let keys_for_drag = keys.clone();
+        let drag_source_for_begin = drag_source.clone();
+        let drop_target_for_begin = drop_target.clone();
+        let band_for_begin = band.clone();
+        drag.connect_drag_begin(move |gesture, x, y| {
+            let Some(widget) = gesture.widget() else {
+                return;
+            };
+            let key =
+                Self::find_key_at_position(&keys_for_drag, x, y, widget.width(), widget.height());
+            drag_source_for_begin.set(key.as_ref().map(|k| k.token));
+            drop_target_for_begin.set(None);
+            band_for_begin.set(key.is_none().then_some(Band {
+                start: (x, y),
+                end: (x, y),
+            }));
+        });
+
+        let keys_for_drag = keys.clone();
+        let drag_source_for_update = drag_source.clone();
+        let drop_target_for_update = drop_target.clone();
+        let band_for_update = band.clone();
+        drag.connect_drag_update(move |gesture, offset_x, offset_y| {
+            let Some(widget) = gesture.widget() else {
+                return;
+            };
+            if let Some(band) = band_for_update.get() {
+                let (x, y) = band.start;
+                band_for_update.set(Some(Band {
+                    end: (x + offset_x, y + offset_y),
+                    ..band
+                }));
+                widget.queue_draw();
+                return;
+            }
+            let (Some(source), Some((x, y))) =
+                (drag_source_for_update.get(), gesture.start_point())
+            else {
+                return;
+            };
+            let target = Self::find_key_at_position(
+                &keys_for_drag,
+                x + offset_x,
+                y + offset_y,
+                widget.width(),
+                widget.height(),
+            )
+            .map(|k| k.token)
+            .filter(|token| *token != source);
+
+            if drop_target_for_update.get() != target {
+                drop_target_for_update.set(target);
+                widget.set_cursor_from_name(Some(if target.is_some() {
+                    "grabbing"
+                } else {
+                    "no-drop"
+                }));
+                widget.queue_draw();
+            }
+        });
+
+        let keys_for_drop = keys.clone();
+        let layer_for_drop = layer.clone();
+        let drop_target_for_end = drop_target.clone();
+        let selected_for_drop = selected.clone();
+        let band_for_end = band.clone();
+        let sender_for_drop = sender.clone();
+        drag.connect_drag_end(move |gesture, _, _| {
+            let source = drag_source.take();
+            let target = drop_target_for_end.take();
+            let Some(widget) = gesture.widget() else {
+                return;
+            };
+            widget.set_cursor(None);
+            widget.queue_draw();
+
+            if let Some(band) = band_for_end.take() {
+                let keys = keys_for_drop.borrow();
+                let mut selection = if gesture
+                    .current_event_state()
+                    .contains(gdk::ModifierType::CONTROL_MASK)
+                {
+                    selected_for_drop.borrow().clone()
+                } else {
+                    BTreeSet::new()
+                };
+                selection.extend(Self::keys_in_band(
+                    &keys,
+                    band,
+                    widget.width(),
+                    widget.height(),
+                ));
+                Self::select(
+                    &widget,
+                    &sender_for_drop,
+                    &keys,
+                    &selected_for_drop,
+                    selection,
+                );
+                return;
+            }
+
+            if let (Some(source), Some(target)) = (source, target) {
+                let one_way = gesture
+                    .current_event_state()
+                    .contains(gdk::ModifierType::SHIFT_MASK);
+                let layer = layer_for_drop.get();
+                let _ = sender_for_drop.send(AppMsg::DropKey {
+                    source: layer.position(source),
+                    target: layer.position(target),
+                    one_way,
+                });
+            }
+        });
+        drawing_area.add_controller(drag);
+
         // Motion controller for hover
         let motion = gtk4::EventControllerMotion::new();
         let keys_for_motion = keys.clone();
@@ -75,8 +334,8 @@ impl KeyboardView {
             let width = drawing_area_for_motion.width();
             let height = drawing_area_for_motion.height();
 
-            let new_hovered = Self::find_key_at_position(&keys_for_motion, x, y, width, height)
-                .map(|k| k.label.clone());
+            let new_hovered =
+                Self::find_key_at_position(&keys_for_motion, x, y, width, height).map(|k| k.token);
 
             let mut hovered = hovered_key_for_motion.borrow_mut();
             if *hovered != new_hovered {
@@ -97,37 +356,332 @@ impl KeyboardView {
 
         drawing_area.add_controller(motion);
 
+        // Keyboard cursor: arrows or hjkl move it, Enter remaps, Delete
+        // clears, Ctrl+Space selects and Escape clears the selection
+        let key_controller = gtk4::EventControllerKey::new();
+        let keys_for_keyboard = keys.clone();
+        let focused_key_for_keyboard = focused_key.clone();
+        let selected_for_keyboard = selected.clone();
+        let layer_for_keyboard = layer.clone();
+        let drawing_area_for_keyboard = drawing_area.clone();
+
+        key_controller.connect_key_pressed(move |_, keyval, _keycode, state| {
+            let keys = keys_for_keyboard.borrow();
+            let Some(focused) = focused_key_for_keyboard
+                .get()
+                .or_else(|| keys.first().map(|k| k.token))
+            else {
+                return glib::Propagation::Proceed;
+            };
+            let source = layer_for_keyboard.get().position(focused);
+
+            // Ctrl+Space toggles selection as in GTK lists
+            if keyval == gdk::Key::space && state.contains(gdk::ModifierType::CONTROL_MASK) {
+                Self::toggle_selected(
+                    &drawing_area_for_keyboard,
+                    &sender,
+                    &keys,
+                    &selected_for_keyboard,
+                    focused,
+                );
+                Self::update_accessible_label(
+                    &drawing_area_for_keyboard,
+                    &keys,
+                    Some(focused),
+                    layer_for_keyboard.get(),
+                    &selected_for_keyboard.borrow(),
+                );
+                return glib::Propagation::Stop;
+            }
+
+            // Leave shortcuts such as Ctrl+Z to the window
+            let shortcut_mask = gdk::ModifierType::CONTROL_MASK
+                | gdk::ModifierType::ALT_MASK
+                | gdk::ModifierType::SUPER_MASK;
+            if state.intersects(shortcut_mask) {
+                return glib::Propagation::Proceed;
+            }
+
+            if let Some(direction) = Direction::from_keyval(keyval) {
+                let next = Self::neighbour(&keys, focused, direction).unwrap_or(focused);
+                focused_key_for_keyboard.set(Some(next));
+                Self::update_accessible_label(
+                    &drawing_area_for_keyboard,
+                    &keys,
+                    Some(next),
+                    layer_for_keyboard.get(),
+                    &selected_for_keyboard.borrow(),
+                );
+                drawing_area_for_keyboard.queue_draw();
+                return glib::Propagation::Stop;
+            }
+
+            match keyval {
+                gdk::Key::Escape if !selected_for_keyboard.borrow().is_empty() => {
+                    Self::select(
+                        &drawing_area_for_keyboard,
+                        &sender,
+                        &keys,
+                        &selected_for_keyboard,
+                        BTreeSet::new(),
+                    );
+                    glib::Propagation::Stop
+                }
+                gdk::Key::Return | gdk::Key::KP_Enter | gdk::Key::space => {
+                    let _ = sender.send(AppMsg::KeyClicked(source));
+                    glib::Propagation::Stop
+                }
+                gdk::Key::Delete | gdk::Key::KP_Delete | gdk::Key::BackSpace => {
+                    let _ = sender.send(AppMsg::ApplyRemap {
+                        source,
+                        target: RemapTarget::Clear,
+                    });
+                    glib::Propagation::Stop
+                }
+                _ => glib::Propagation::Proceed,
+            }
+        });
+        drawing_area.add_controller(key_controller);
+
+        // Put the cursor on a key as soon as the keyboard gets focus, and
+        // only show it while focused
+        let focus = gtk4::EventControllerFocus::new();
+        let keys_for_focus = keys.clone();
+        let focused_key_for_focus = focused_key.clone();
+        let selected_for_focus = selected.clone();
+        let layer_for_focus = layer.clone();
+        let drawing_area_for_focus = drawing_area.clone();
+        focus.connect_enter(move |_| {
+            let keys = keys_for_focus.borrow();
+            if focused_key_for_focus.get().is_none() {
+                focused_key_for_focus.set(keys.first().map(|k| k.token));
+            }
+            Self::update_accessible_label(
+                &drawing_area_for_focus,
+                &keys,
+                focused_key_for_focus.get(),
+                layer_for_focus.get(),
+                &selected_for_focus.borrow(),
+            );
+            drawing_area_for_focus.queue_draw();
+        });
+        let drawing_area_for_blur = drawing_area.clone();
+        focus.connect_leave(move |_| drawing_area_for_blur.queue_draw());
+        drawing_area.add_controller(focus);
+
         let mut view = Self {
             drawing_area,
             keys: keys.clone(),
             remappings: remappings.clone(),
             hovered_key: hovered_key.clone(),
+            focused_key: focused_key.clone(),
+            drop_target: drop_target.clone(),
+            selected: selected.clone(),
+            layer: layer.clone(),
+            diff: Rc::new(RefCell::new(HashMap::new())),
         };
 
         view.initialize_keys();
 
         let keys_for_draw = view.keys.clone();
         let hovered_key_for_draw = view.hovered_key.clone();
+        let focused_key_for_draw = view.focused_key.clone();
+        let drop_target_for_draw = view.drop_target.clone();
+        let selected_for_draw = view.selected.clone();
+        let layer_for_draw = view.layer.clone();
+        let diff_for_draw = view.diff.clone();
         view.drawing_area
-            .set_draw_func(move |_, cr, width, height| {
+            .set_draw_func(move |area, cr, width, height| {
                 let keys = keys_for_draw.borrow();
-                let hovered = hovered_key_for_draw.borrow();
-                Self::draw(cr, width, height, &keys, hovered.as_deref());
+                let selected = selected_for_draw.borrow();
+                let highlight = Highlight {
+                    hovered: *hovered_key_for_draw.borrow(),
+                    focused: focused_key_for_draw.get().filter(|_| area.has_focus()),
+                    drop_target: drop_target_for_draw.get(),
+                    selected: &selected,
+                    band: band.get(),
+                };
+                let diff = diff_for_draw.borrow();
+                Self::draw(
+                    cr,
+                    width,
+                    height,
+                    &keys,
+                    highlight,
+                    layer_for_draw.get(),
+                    &diff,
+                );
             });
         view
     }
 
     pub fn clear_all_remappings(&mut self) {
         self.remappings.borrow_mut().clear();
+        self.refresh_labels();
+    }
+
+    /// Show the legends and remaps of another layer
+    pub fn set_layer(&mut self, layer: Layer) {
+        self.layer.set(layer);
+        self.refresh_labels();
+    }
+
+    /// Colour keys by how their mappings differ, or stop with `None`
+    pub fn set_diff(&mut self, diff: Option<&LayoutDiff>) {
+        let mut kinds = self.diff.borrow_mut();
+        kinds.clear();
+        if let Some(diff) = diff {
+            kinds.extend(diff.changes.iter().map(|c| (c.source(), c.kind())));
+        }
+        drop(kinds);
+
+        self.drawing_area.queue_draw();
+    }
+
+    /// Update each key's remapped label from the remaps on the current layer
+    fn refresh_labels(&self) {
+        let layer = self.layer.get();
+        let remappings = self.remappings.borrow();
 
         let mut keys = self.keys.borrow_mut();
         for key in keys.iter_mut() {
-            key.remapped_label = None;
+            key.remapped_label = remappings.get(&layer.position(key.token)).cloned();
         }
+        Self::update_accessible_label(
+            &self.drawing_area,
+            &keys,
+            self.focused_key.get(),
+            layer,
+            &self.selected.borrow(),
+        );
 
         self.drawing_area.queue_draw();
     }
 
+    /// Name the keyboard after the focused key so screen readers announce
+    /// its label and remap as the cursor moves
+    fn update_accessible_label(
+        widget: &impl IsA<gtk4::Accessible>,
+        keys: &[Key],
+        focused: Option<KeyToken>,
+        layer: Layer,
+        selected: &BTreeSet<KeyToken>,
+    ) {
+        let name = focused
+            .and_then(|token| keys.iter().find(|k| k.token == token))
+            .map(|key| key.accessible_name(layer, selected.contains(&key.token)))
+            .unwrap_or_else(|| "Keyboard".to_string());
+        widget.update_property(&[gtk4::accessible::Property::Label(&name)]);
+    }
+
+    /// Replace the selection, telling the app if it changed
+    fn select(
+        widget: &impl IsA<gtk4::Widget>,
+        sender: &Sender<AppMsg>,
+        keys: &[Key],
+        selected: &RefCell<BTreeSet<KeyToken>>,
+        selection: BTreeSet<KeyToken>,
+    ) {
+        if *selected.borrow() == selection {
+            return;
+        }
+        let _ = sender.send(AppMsg::SelectionChanged(Self::in_reading_order(
+            keys, &selection,
+        )));
+        *selected.borrow_mut() = selection;
+        widget.queue_draw();
+    }
+
+    /// Add a key to the selection, or remove it if it is already selected
+    fn toggle_selected(
+        widget: &impl IsA<gtk4::Widget>,
+        sender: &Sender<AppMsg>,
+        keys: &[Key],
+        selected: &RefCell<BTreeSet<KeyToken>>,
+        token: KeyToken,
+    ) {
+        let mut selection = selected.borrow().clone();
+        if !selection.remove(&token) {
+            selection.insert(token);
+        }
+        Self::select(widget, sender, keys, selected, selection);
+    }
+
+    /// Selected keys row by row across both halves, left to right within a
+    /// row
+    fn in_reading_order(keys: &[Key], selection: &BTreeSet<KeyToken>) -> Vec<KeyToken> {
+        let pitch = constants::KEY_SIZE + constants::KEY_GAP;
+        let mut ordered: Vec<_> = keys
+            .iter()
+            .filter(|k| selection.contains(&k.token))
+            .map(|k| {
+                let (x, y) = Self::key_center(k);
+                // Staggered keys are a fraction of a row lower than their
+                // neighbours
+                ((y / pitch).round() as i64, x, k.token)
+            })
+            .collect();
+        ordered.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        ordered.into_iter().map(|(_, _, token)| token).collect()
+    }
+
+    /// Keys whose centres lie inside a rubber band
+    fn keys_in_band(keys: &[Key], band: Band, width: i32, height: i32) -> Vec<KeyToken> {
+        let (left, top, band_width, band_height) = band.rectangle();
+        let (x0, y0) = Self::to_keyboard_space(left, top, width, height);
+        let (x1, y1) = Self::to_keyboard_space(left + band_width, top + band_height, width, height);
+
+        keys.iter()
+            .filter(|k| {
+                let (x, y) = Self::key_center(k);
+                (x0..=x1).contains(&x) && (y0..=y1).contains(&y)
+            })
+            .map(|k| k.token)
+            .collect()
+    }
+
+    /// Centre of a key in keyboard space, as drawn
+    fn key_center(key: &Key) -> (f64, f64) {
+        let (offset_x, offset_y, rotation) = Self::get_section_transform(key.section);
+        let x = key.x + key.width / 2.0;
+        let y = key.y + key.height / 2.0;
+
+        // Thumb clusters are rotated about the same point as in draw_section()
+        let (x, y) = if rotation != 0.0 {
+            let pivot = 1.5 * (constants::KEY_SIZE + constants::KEY_GAP);
+            let (sin, cos) = rotation.sin_cos();
+            let (dx, dy) = (x - pivot, y - pivot);
+            (pivot + dx * cos - dy * sin, pivot + dx * sin + dy * cos)
+        } else {
+            (x, y)
+        };
+
+        (x + offset_x, y + offset_y)
+    }
+
+    /// The closest key in a direction, preferring keys in line with `from`
+    /// so the cursor follows rows and columns across both halves
+    fn neighbour(keys: &[Key], from: KeyToken, direction: Direction) -> Option<KeyToken> {
+        let current = keys.iter().find(|k| k.token == from)?;
+        let (x, y) = Self::key_center(current);
+
+        keys.iter()
+            .filter(|k| k.token != from)
+            .filter_map(|k| {
+                let (kx, ky) = Self::key_center(k);
+                let (along, across) = match direction {
+                    Direction::Left => (x - kx, ky - y),
+                    Direction::Right => (kx - x, ky - y),
+                    Direction::Up => (y - ky, kx - x),
+                    Direction::Down => (ky - y, kx - x),
+                };
+                // Keys beside the current one are not in the direction
+                (along > constants::KEY_SIZE / 4.0).then_some((along + across.abs() * 2.0, k.token))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, token)| token)
+    }
+
     fn get_section_transform(section: KeySection) -> (f64, f64, f64) {
         let left_hand_offset_x = -constants::SPLIT_GAP - constants::HAND_WIDTH;
         let right_hand_offset_x = constants::SPLIT_GAP;
@@ -148,16 +702,9 @@ impl KeyboardView {
         }
     }
 
-    fn find_key_at_position(
-        keys: &Rc<RefCell<Vec<Key>>>,
-        click_x: f64,
-        click_y: f64,
-        width: i32,
-        height: i32,
-    ) -> Option<Key> {
-        let keys = keys.borrow();
-
-        // Reverse the transformations from draw()
+    /// Widget coordinates in keyboard space, reversing the transformations
+    /// from draw()
+    fn to_keyboard_space(x: f64, y: f64, width: i32, height: i32) -> (f64, f64) {
         let keyboard_width = constants::KEYBOARD_WIDTH;
         let keyboard_height = constants::KEYBOARD_HEIGHT;
 
@@ -168,9 +715,20 @@ impl KeyboardView {
         let center_x = width as f64 / 2.0;
         let center_y = height as f64 / 2.0;
 
+        ((x - center_x) / scale, (y - center_y) / scale)
+    }
+
+    fn find_key_at_position(
+        keys: &Rc<RefCell<Vec<Key>>>,
+        click_x: f64,
+        click_y: f64,
+        width: i32,
+        height: i32,
+    ) -> Option<Key> {
+        let keys = keys.borrow();
+
         // Transform click coordinates to keyboard space
-        let x = (click_x - center_x) / scale;
-        let y = (click_y - center_y) / scale;
+        let (x, y) = Self::to_keyboard_space(click_x, click_y, width, height);
 
         // Check each section with proper transformations
         for key in keys.iter() {
@@ -202,268 +760,208 @@ impl KeyboardView {
         None
     }
 
-    pub fn set_remapping(&mut self, original: &str, remapped: &str) {
+    /// Record a remap; `original` may be a base or keypad-layer position
+    pub fn set_remapping(&mut self, original: KeyToken, remapped: &str) {
         self.remappings
             .borrow_mut()
-            .insert(original.to_string(), remapped.to_string());
-
-        // Update the keys
-        let mut keys = self.keys.borrow_mut();
-        for key in keys.iter_mut() {
-            if key.label == original {
-                key.remapped_label = Some(remapped.to_string());
-            }
-        }
+            .insert(original, remapped.to_string());
+        self.refresh_labels();
+    }
 
+    /// Deselect every key. The app is not told, as it asked for this.
+    pub fn clear_selection(&mut self) {
+        self.selected.borrow_mut().clear();
+        Self::update_accessible_label(
+            &self.drawing_area,
+            &self.keys.borrow(),
+            self.focused_key.get(),
+            self.layer.get(),
+            &self.selected.borrow(),
+        );
         self.drawing_area.queue_draw();
     }
 
-    pub fn clear_remapping(&mut self, original: &str) {
-        self.remappings.borrow_mut().remove(original);
-
-        let mut keys = self.keys.borrow_mut();
-        for key in keys.iter_mut() {
-            if key.label == original {
-                key.remapped_label = None;
-            }
-        }
-
-        self.drawing_area.queue_draw();
+    pub fn clear_remapping(&mut self, original: KeyToken) {
+        self.remappings.borrow_mut().remove(&original);
+        self.refresh_labels();
     }
 
     fn initialize_keys(&mut self) {
         let mut keys = self.keys.borrow_mut();
 
-        let mut add_key = |label: &str,
+        let mut add_key = |token: KeyToken,
                            col: f64,
                            row: f64,
                            width_mult: f64,
                            height_mult: f64,
-                           section: KeySection,
-                           svg_data: Option<&'static str>| {
+                           section: KeySection| {
             keys.push(Key {
-                label: label.to_string(),
+                token,
                 remapped_label: None,
                 x: col * (constants::KEY_SIZE + constants::KEY_GAP),
                 y: row * (constants::KEY_SIZE + constants::KEY_GAP),
                 width: constants::KEY_SIZE * width_mult,
                 height: constants::KEY_SIZE * height_mult,
                 section,
-                svg_data,
+                svg_data: Self::icon(token),
             });
         };
 
         // Left hand - Row 0
-        add_key("+\n=", 0.0, 0.2, 1.5, 1.0, KeySection::LeftHand, None); // Wide + offset down
-        add_key("!\n1", 1.5, 0.2, 1.0, 1.0, KeySection::LeftHand, None); // Offset down
-        add_key("@\n2", 2.5, 0.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("#\n3", 3.5, 0.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("$\n4", 4.5, 0.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("%\n5", 5.5, 0.0, 1.0, 1.0, KeySection::LeftHand, None);
+        add_key(KeyToken::Equals, 0.0, 0.2, 1.5, 1.0, KeySection::LeftHand); // Wide + offset down
+        add_key(KeyToken::Digit1, 1.5, 0.2, 1.0, 1.0, KeySection::LeftHand); // Offset down
+        add_key(KeyToken::Digit2, 2.5, 0.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::Digit3, 3.5, 0.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::Digit4, 4.5, 0.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::Digit5, 5.5, 0.0, 1.0, 1.0, KeySection::LeftHand);
         add_key(
-            "Kp",
+            KeyToken::KeypadToggle,
             6.5,
             0.0,
             1.0,
             1.0,
             KeySection::LeftHand,
-            Some(constants::KP_ICON),
         );
 
         // Left hand - Row 1
-        add_key("Tab", 0.0, 1.2, 1.5, 1.0, KeySection::LeftHand, None);
-        add_key("Q", 1.5, 1.2, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("W", 2.5, 1.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("E", 3.5, 1.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("R", 4.5, 1.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("T", 5.5, 1.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key(
-            "Hk1",
-            6.5,
-            1.0,
-            1.0,
-            1.0,
-            KeySection::LeftHand,
-            Some(constants::HK1_ICON),
-        );
+        add_key(KeyToken::Tab, 0.0, 1.2, 1.5, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::Q, 1.5, 1.2, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::W, 2.5, 1.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::E, 3.5, 1.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::R, 4.5, 1.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::T, 5.5, 1.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::Hotkey1, 6.5, 1.0, 1.0, 1.0, KeySection::LeftHand);
 
         // Left hand - Row 2
-        add_key("Esc", 0.0, 2.2, 1.5, 1.0, KeySection::LeftHand, None);
-        add_key("A", 1.5, 2.2, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("S", 2.5, 2.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("D", 3.5, 2.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("F", 4.5, 2.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("G", 5.5, 2.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key(
-            "Hk2",
-            6.5,
-            2.0,
-            1.0,
-            1.0,
-            KeySection::LeftHand,
-            Some(constants::HK2_ICON),
-        );
+        add_key(KeyToken::Escape, 0.0, 2.2, 1.5, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::A, 1.5, 2.2, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::S, 2.5, 2.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::D, 3.5, 2.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::F, 4.5, 2.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::G, 5.5, 2.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::Hotkey2, 6.5, 2.0, 1.0, 1.0, KeySection::LeftHand);
 
         // Left hand - Row 3
-        add_key("LShift", 0.0, 3.2, 1.5, 1.0, KeySection::LeftHand, None);
-        add_key("Z", 1.5, 3.2, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("X", 2.5, 3.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("C", 3.5, 3.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("V", 4.5, 3.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("B", 5.5, 3.0, 1.0, 1.0, KeySection::LeftHand, None);
+        add_key(KeyToken::LShift, 0.0, 3.2, 1.5, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::Z, 1.5, 3.2, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::X, 2.5, 3.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::C, 3.5, 3.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::V, 4.5, 3.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::B, 5.5, 3.0, 1.0, 1.0, KeySection::LeftHand);
 
         // Left hand - Row 4 (bottom, KeySection::LeftHand)
-        add_key(
-            "LFn",
-            0.0,
-            4.2,
-            1.5,
-            1.0,
-            KeySection::LeftHand,
-            Some(constants::FN_ICON),
-        );
-        add_key("~\n`", 1.5, 4.2, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key("Caps", 2.5, 4.0, 1.0, 1.0, KeySection::LeftHand, None);
-        add_key(
-            "←",
-            3.5,
-            4.0,
-            1.0,
-            1.0,
-            KeySection::LeftHand,
-            Some(constants::LEFT_ICON),
-        );
-        add_key(
-            "→",
-            4.5,
-            4.0,
-            1.0,
-            1.0,
-            KeySection::LeftHand,
-            Some(constants::RIGHT_ICON),
-        );
+        add_key(KeyToken::LFn, 0.0, 4.2, 1.5, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::Backtick, 1.5, 4.2, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::Caps, 2.5, 4.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::Left, 3.5, 4.0, 1.0, 1.0, KeySection::LeftHand);
+        add_key(KeyToken::Right, 4.5, 4.0, 1.0, 1.0, KeySection::LeftHand);
 
         // Right hand - Row 0
         add_key(
-            "Ss",
+            KeyToken::SmartSet,
             0.0,
             0.0,
             1.0,
             1.0,
             KeySection::RightHand,
-            Some(constants::SMARTSET_ICON),
         );
-        add_key("^\n6", 1.0, 0.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("&\n7", 2.0, 0.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("*\n8", 3.0, 0.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("(\n9", 4.0, 0.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key(")\n0", 5.0, 0.2, 1.0, 1.0, KeySection::RightHand, None); // Offset down
-        add_key("_\n-", 6.0, 0.2, 1.5, 1.0, KeySection::RightHand, None); // Wide + offset down
+        add_key(KeyToken::Digit6, 1.0, 0.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::Digit7, 2.0, 0.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::Digit8, 3.0, 0.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::Digit9, 4.0, 0.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::Digit0, 5.0, 0.2, 1.0, 1.0, KeySection::RightHand); // Offset down
+        add_key(KeyToken::Hyphen, 6.0, 0.2, 1.5, 1.0, KeySection::RightHand); // Wide + offset down
 
         // Right hand - Row 1
+        add_key(KeyToken::Hotkey3, 0.0, 1.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::Y, 1.0, 1.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::U, 2.0, 1.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::I, 3.0, 1.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::O, 4.0, 1.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::P, 5.0, 1.2, 1.0, 1.0, KeySection::RightHand);
         add_key(
-            "Hk3",
-            0.0,
-            1.0,
-            1.0,
+            KeyToken::Backslash,
+            6.0,
+            1.2,
+            1.5,
             1.0,
             KeySection::RightHand,
-            Some(constants::HK3_ICON),
         );
-        add_key("Y", 1.0, 1.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("U", 2.0, 1.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("I", 3.0, 1.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("O", 4.0, 1.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("P", 5.0, 1.2, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("|\n\\", 6.0, 1.2, 1.5, 1.0, KeySection::RightHand, None);
 
         // Right hand - Row 2
+        add_key(KeyToken::Hotkey4, 0.0, 2.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::H, 1.0, 2.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::J, 2.0, 2.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::K, 3.0, 2.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::L, 4.0, 2.0, 1.0, 1.0, KeySection::RightHand);
         add_key(
-            "Hk4",
-            0.0,
-            2.0,
+            KeyToken::Semicolon,
+            5.0,
+            2.2,
             1.0,
             1.0,
             KeySection::RightHand,
-            Some(constants::HK4_ICON),
         );
-        add_key("H", 1.0, 2.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("J", 2.0, 2.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("K", 3.0, 2.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("L", 4.0, 2.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key(":\n;", 5.0, 2.2, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("\"\n'", 6.0, 2.2, 1.5, 1.0, KeySection::RightHand, None);
+        add_key(KeyToken::Quote, 6.0, 2.2, 1.5, 1.0, KeySection::RightHand);
 
         // Right hand - Row 3
-        add_key("N", 1.0, 3.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("M", 2.0, 3.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("<\n,", 3.0, 3.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key(">\n.", 4.0, 3.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("?\n/", 5.0, 3.2, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("RShift", 6.0, 3.2, 1.5, 1.0, KeySection::RightHand, None);
+        add_key(KeyToken::N, 1.0, 3.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::M, 2.0, 3.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::Comma, 3.0, 3.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::Period, 4.0, 3.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::Slash, 5.0, 3.2, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::RShift, 6.0, 3.2, 1.5, 1.0, KeySection::RightHand);
 
         // Right hand - Row 4 (bottom, KeySection::RightHand)
+        add_key(KeyToken::Up, 2.0, 4.0, 1.0, 1.0, KeySection::RightHand);
+        add_key(KeyToken::Down, 3.0, 4.0, 1.0, 1.0, KeySection::RightHand);
         add_key(
-            "↑",
-            2.0,
+            KeyToken::OpenBracket,
             4.0,
-            1.0,
-            1.0,
-            KeySection::RightHand,
-            Some(constants::UP_ICON),
-        );
-        add_key(
-            "↓",
-            3.0,
             4.0,
             1.0,
             1.0,
             KeySection::RightHand,
-            Some(constants::DOWN_ICON),
         );
-        add_key("{\n[", 4.0, 4.0, 1.0, 1.0, KeySection::RightHand, None);
-        add_key("}\n]", 5.0, 4.2, 1.0, 1.0, KeySection::RightHand, None);
         add_key(
-            "RFn",
-            6.0,
+            KeyToken::CloseBracket,
+            5.0,
             4.2,
-            1.5,
+            1.0,
             1.0,
             KeySection::RightHand,
-            Some(constants::FN_ICON),
         );
+        add_key(KeyToken::RFn, 6.0, 4.2, 1.5, 1.0, KeySection::RightHand);
 
         // Left thumb cluster - reorganized with consistent rows
-        add_key("LCtrl", 1.0, 0.0, 1.0, 1.0, KeySection::LeftThumb, None);
-        add_key("Alt", 2.0, 0.0, 1.0, 1.0, KeySection::LeftThumb, None);
+        add_key(KeyToken::LCtrl, 1.0, 0.0, 1.0, 1.0, KeySection::LeftThumb);
+        add_key(KeyToken::LAlt, 2.0, 0.0, 1.0, 1.0, KeySection::LeftThumb);
         add_key(
-            "Back\nSpace",
+            KeyToken::Backspace,
             0.0,
             1.0,
             1.0,
             2.0,
             KeySection::LeftThumb,
-            None,
         ); // Double height
-        add_key("Delete", 1.0, 1.0, 1.0, 2.0, KeySection::LeftThumb, None); // Double height
-        add_key("Home", 2.0, 1.0, 1.0, 1.0, KeySection::LeftThumb, None);
-        add_key("End", 2.0, 2.0, 1.0, 1.0, KeySection::LeftThumb, None);
+        add_key(KeyToken::Delete, 1.0, 1.0, 1.0, 2.0, KeySection::LeftThumb); // Double height
+        add_key(KeyToken::Home, 2.0, 1.0, 1.0, 1.0, KeySection::LeftThumb);
+        add_key(KeyToken::End, 2.0, 2.0, 1.0, 1.0, KeySection::LeftThumb);
 
         // Right thumb cluster - reorganized with consistent rows
+        add_key(KeyToken::RWin, 0.0, 0.0, 1.0, 1.0, KeySection::RightThumb);
+        add_key(KeyToken::RCtrl, 1.0, 0.0, 1.0, 1.0, KeySection::RightThumb);
+        add_key(KeyToken::PageUp, 0.0, 1.0, 1.0, 1.0, KeySection::RightThumb);
         add_key(
-            "Win",
-            0.0,
+            KeyToken::PageDown,
             0.0,
+            2.0,
             1.0,
             1.0,
             KeySection::RightThumb,
-            Some(constants::WIN_ICON),
         );
-        add_key("RCtrl", 1.0, 0.0, 1.0, 1.0, KeySection::RightThumb, None);
-        add_key("Pg\nUp", 0.0, 1.0, 1.0, 1.0, KeySection::RightThumb, None);
-        add_key("Pg\nDown", 0.0, 2.0, 1.0, 1.0, KeySection::RightThumb, None);
-        add_key("Enter", 1.0, 1.0, 1.0, 2.0, KeySection::RightThumb, None); // Double height
-        add_key("Space", 2.0, 1.0, 1.0, 2.0, KeySection::RightThumb, None); // Double height
+        add_key(KeyToken::Enter, 1.0, 1.0, 1.0, 2.0, KeySection::RightThumb); // Double height
+        add_key(KeyToken::Space, 2.0, 1.0, 1.0, 2.0, KeySection::RightThumb); // Double height
     }
 
     fn draw(
@@ -471,7 +969,9 @@ impl KeyboardView {
         width: i32,
         height: i32,
         keys: &[Key],
-        hovered_label: Option<&str>,
+        highlight: Highlight,
+        layer: Layer,
+        diff: &HashMap<KeyToken, DiffKind>,
     ) {
         // Clear background
         Self::set_color(cr, constants::BACKGROUND);
@@ -498,24 +998,61 @@ impl KeyboardView {
             KeySection::LeftThumb,
             KeySection::RightThumb,
         ] {
-            Self::draw_section(cr, keys, section, hovered_label);
+            Self::draw_section(cr, keys, section, highlight, layer, diff);
         }
 
         cr.restore().unwrap();
+
+        if let Some(band) = highlight.band {
+            let (x, y, width, height) = band.rectangle();
+            cr.rectangle(x, y, width, height);
+            let (red, green, blue) = constants::SELECTION_BAND;
+            cr.set_source_rgba(red, green, blue, constants::SELECTION_BAND_ALPHA);
+            let _ = cr.fill_preserve();
+            Self::set_color(cr, constants::SELECTION_BAND);
+            cr.set_line_width(1.0);
+            let _ = cr.stroke();
+        }
     }
 
-    fn draw_key(cr: &cairo::Context, key: &Key, hovered_label: Option<&str>) {
+    fn draw_key(
+        cr: &cairo::Context,
+        key: &Key,
+        highlight: Highlight,
+        layer: Layer,
+        diff: Option<DiffKind>,
+    ) {
         // Determine if this is a home row key
         let is_home_row = matches!(
-            key.label.as_str(),
-            "A" | "S" | "D" | "F" | "G" | "H" | "J" | "K" | "L" | ":" | ";\n:"
+            key.token,
+            KeyToken::A
+                | KeyToken::S
+                | KeyToken::D
+                | KeyToken::F
+                | KeyToken::G
+                | KeyToken::H
+                | KeyToken::J
+                | KeyToken::K
+                | KeyToken::L
+                | KeyToken::Semicolon
         );
 
-        let is_hovered = hovered_label == Some(&key.label);
+        let is_hovered = highlight.hovered == Some(key.token);
 
         // Set key background color - dark slate gray
-        if is_hovered {
+        if highlight.drop_target == Some(key.token) {
+            Self::set_color(cr, constants::KEY_BACKGROUND_DROP_TARGET);
+        } else if is_hovered {
             Self::set_color(cr, constants::KEY_BACKGROUND_HOVER);
+        } else if let Some(kind) = diff {
+            Self::set_color(
+                cr,
+                match kind {
+                    DiffKind::Added => constants::KEY_BACKGROUND_DIFF_ADDED,
+                    DiffKind::Removed => constants::KEY_BACKGROUND_DIFF_REMOVED,
+                    DiffKind::Changed => constants::KEY_BACKGROUND_DIFF_CHANGED,
+                },
+            );
         } else if key.remapped_label.is_some() {
             Self::set_color(cr, constants::KEY_BACKGROUND_REMAPPED);
         } else if is_home_row {
@@ -535,15 +1072,39 @@ impl KeyboardView {
         );
         let _ = cr.fill_preserve();
 
-        // Draw key border with slightly lighter gray
-        Self::set_color(cr, constants::KEY_BORDER);
-        cr.set_line_width(constants::KEY_BORDER_WIDTH);
+        // Draw key border with slightly lighter gray, or thicker in the
+        // selection colour
+        if highlight.selected.contains(&key.token) {
+            Self::set_color(cr, constants::KEY_SELECTED_BORDER);
+            cr.set_line_width(constants::KEY_SELECTED_BORDER_WIDTH);
+        } else {
+            Self::set_color(cr, constants::KEY_BORDER);
+            cr.set_line_width(constants::KEY_BORDER_WIDTH);
+        }
         let _ = cr.stroke();
 
-        if key.remapped_label.is_none() && key.svg_data.is_some() {
+        // Keyboard cursor
+        if highlight.focused == Some(key.token) {
+            let inset = constants::KEY_FOCUS_RING_WIDTH / 2.0;
+            Self::draw_rounded_rectangle(
+                cr,
+                key.x + inset,
+                key.y + inset,
+                key.width - 2.0 * inset,
+                key.height - 2.0 * inset,
+                constants::KEY_CORNER_RADIUS,
+            );
+            Self::set_color(cr, constants::KEY_FOCUS_RING);
+            cr.set_line_width(constants::KEY_FOCUS_RING_WIDTH);
+            let _ = cr.stroke();
+        }
+
+        // Icons only stand for the key's base action
+        let action = layer.default_action(key.token);
+        if key.remapped_label.is_none() && key.svg_data.is_some() && action == key.token {
             Self::draw_svg_on_key(cr, key);
         } else {
-            Self::draw_text_on_key(cr, key);
+            Self::draw_text_on_key(cr, key, action);
         }
     }
 
@@ -551,7 +1112,9 @@ impl KeyboardView {
         cr: &cairo::Context,
         keys: &[Key],
         section: KeySection,
-        hovered_label: Option<&str>,
+        highlight: Highlight,
+        layer: Layer,
+        diff: &HashMap<KeyToken, DiffKind>,
     ) {
         let (offset_x, offset_y, rotation) = Self::get_section_transform(section);
 
@@ -569,7 +1132,8 @@ impl KeyboardView {
 
         // Draw all keys in this section
         for key in keys.iter().filter(|k| k.section == section) {
-            Self::draw_key(cr, key, hovered_label);
+            let kind = diff.get(&layer.position(key.token)).copied();
+            Self::draw_key(cr, key, highlight, layer, kind);
         }
 
         cr.restore().unwrap();
@@ -644,7 +1208,7 @@ impl KeyboardView {
                     cr.restore().unwrap();
                 }
                 Err(e) => {
-                    eprintln!("Failed to load SVG for key {}: {:?}", key.label, e);
+                    eprintln!("Failed to load SVG for key {}: {:?}", key.token, e);
                 }
             }
         }
@@ -654,19 +1218,11 @@ impl KeyboardView {
         cr.set_source_rgb(color.0, color.1, color.2);
     }
 
-    fn draw_text_on_key(cr: &cairo::Context, key: &Key) {
+    fn draw_text_on_key(cr: &cairo::Context, key: &Key, action: KeyToken) {
         let display_text = if let Some(ref remapped) = key.remapped_label {
             remapped.as_str()
         } else {
-            key.label.as_str()
-        };
-
-        let display_text = match display_text {
-            "LShift" => "Shift",
-            "RShift" => "Shift",
-            "LCtrl" => "Ctrl",
-            "RCtrl" => "Ctrl",
-            _ => display_text,
+            action.legend()
         };
 
         if display_text.contains('\n') {
@@ -769,4 +1325,53 @@ impl KeyboardView {
     pub fn widget(&self) -> &DrawingArea {
         &self.drawing_area
     }
+
+    /// A single key cap drawn as on the keyboard, for lists of keys
+    pub fn key_cap(token: KeyToken) -> DrawingArea {
+        let area = DrawingArea::new();
+        let size = constants::KEY_SIZE + 2.0 * constants::KEY_BORDER_WIDTH;
+        area.set_content_width(size.ceil() as i32);
+        area.set_content_height(size.ceil() as i32);
+
+        let key = Key {
+            token,
+            remapped_label: None,
+            x: constants::KEY_BORDER_WIDTH,
+            y: constants::KEY_BORDER_WIDTH,
+            width: constants::KEY_SIZE,
+            height: constants::KEY_SIZE,
+            section: KeySection::LeftHand,
+            svg_data: Self::icon(token),
+        };
+        area.set_draw_func(move |_, cr, _, _| {
+            let highlight = Highlight {
+                hovered: None,
+                focused: None,
+                drop_target: None,
+                selected: &BTreeSet::new(),
+                band: None,
+            };
+            Self::draw_key(cr, &key, highlight, Layer::Base, None);
+        });
+        area
+    }
+
+    /// Icon drawn instead of the legend, for keys that have one
+    fn icon(token: KeyToken) -> Option<&'static str> {
+        match token {
+            KeyToken::KeypadToggle => Some(constants::KP_ICON),
+            KeyToken::LFn | KeyToken::RFn => Some(constants::FN_ICON),
+            KeyToken::SmartSet => Some(constants::SMARTSET_ICON),
+            KeyToken::LWin | KeyToken::RWin => Some(constants::WIN_ICON),
+            KeyToken::Hotkey1 => Some(constants::HK1_ICON),
+            KeyToken::Hotkey2 => Some(constants::HK2_ICON),
+            KeyToken::Hotkey3 => Some(constants::HK3_ICON),
+            KeyToken::Hotkey4 => Some(constants::HK4_ICON),
+            KeyToken::Up => Some(constants::UP_ICON),
+            KeyToken::Down => Some(constants::DOWN_ICON),
+            KeyToken::Left => Some(constants::LEFT_ICON),
+            KeyToken::Right => Some(constants::RIGHT_ICON),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,89 @@
+use gtk4::prelude::*;
+use relm4::Sender;
+
+use crate::app::AppMsg;
+use crate::models::{Lint, Severity};
+
+/// Side panel listing the current layout's lints, each with a fix button
+#[derive(Debug)]
+pub struct LintPanel {
+    root: gtk4::Box,
+    list: gtk4::ListBox,
+    sender: Sender<AppMsg>,
+}
+
+impl LintPanel {
+    pub fn new(sender: Sender<AppMsg>) -> Self {
+        let root = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+        root.set_width_request(280);
+
+        let title = gtk4::Label::new(Some("Problems"));
+        title.set_halign(gtk4::Align::Start);
+        title.add_css_class("heading");
+        root.append(&title);
+
+        let list = gtk4::ListBox::new();
+        list.set_selection_mode(gtk4::SelectionMode::None);
+        list.add_css_class("boxed-list");
+        list.set_placeholder(Some(&gtk4::Label::new(Some("No problems found"))));
+
+        let scrolled = gtk4::ScrolledWindow::new();
+        scrolled.set_hscrollbar_policy(gtk4::PolicyType::Never);
+        scrolled.set_vexpand(true);
+        scrolled.set_child(Some(&list));
+        root.append(&scrolled);
+
+        Self { root, list, sender }
+    }
+
+    /// Replace the listed lints
+    pub fn set_lints(&self, lints: &[Lint]) {
+        while let Some(row) = self.list.row_at_index(0) {
+            self.list.remove(&row);
+        }
+        for lint in lints {
+            self.list.append(&self.lint_row(*lint));
+        }
+    }
+
+    fn lint_row(&self, lint: Lint) -> gtk4::Box {
+        let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+        row.set_margin_start(6);
+        row.set_margin_end(6);
+        row.set_margin_top(6);
+        row.set_margin_bottom(6);
+
+        let (icon, css_class) = match lint.severity() {
+            Severity::Error => ("dialog-error-symbolic", "error"),
+            Severity::Warning => ("dialog-warning-symbolic", "warning"),
+            Severity::Info => ("dialog-information-symbolic", "accent"),
+        };
+        let image = gtk4::Image::from_icon_name(icon);
+        image.add_css_class(css_class);
+        image.set_tooltip_text(Some(&lint.severity().to_string()));
+        image.set_valign(gtk4::Align::Start);
+        row.append(&image);
+
+        let label = gtk4::Label::new(Some(&lint.to_string()));
+        label.set_halign(gtk4::Align::Start);
+        label.set_xalign(0.0);
+        label.set_hexpand(true);
+        label.set_wrap(true);
+        row.append(&label);
+
+        let fix = gtk4::Button::with_label(lint.fix_label());
+        fix.set_valign(gtk4::Align::Center);
+        fix.add_css_class("flat");
+        let sender = self.sender.clone();
+        fix.connect_clicked(move |_| {
+            let _ = sender.send(AppMsg::FixLint(lint));
+        });
+        row.append(&fix);
+
+        row
+    }
+
+    pub fn widget(&self) -> &gtk4::Box {
+        &self.root
+    }
+}
@@ -0,0 +1,320 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use adw::prelude::*;
+use gtk4::glib;
+use gtk4::prelude::*;
+use libadwaita as adw;
+
+use super::key_capture::token_from_keyval;
+use crate::models::{KeyToken, MacroStep};
+
+/// Step kinds offered by the "add step" drop-down, in display order
+const STEP_KINDS: &[&str] = &[
+    "Tap",
+    "Press",
+    "Release",
+    "Delay (ms)",
+    "Speed (1-9)",
+    "Text",
+];
+
+/// Dialog for building a macro as a list of steps, either by hand or by
+/// recording key presses
+pub struct MacroEditor {
+    dialog: adw::AlertDialog,
+    state: Rc<EditorState>,
+}
+
+struct EditorState {
+    steps: RefCell<Vec<MacroStep>>,
+    /// Keys pressed while recording and not yet released
+    held: RefCell<Vec<KeyToken>>,
+    recording: gtk4::ToggleButton,
+    list: gtk4::ListBox,
+    preview: gtk4::Label,
+    message: gtk4::Label,
+}
+
+impl MacroEditor {
+    pub fn new(trigger: KeyToken, steps: Vec<MacroStep>) -> Self {
+        let dialog = adw::AlertDialog::builder()
+            .heading(format!("Edit Macro: {}", trigger))
+            .body("Add steps by hand, or press Record and type the sequence")
+            .prefer_wide_layout(true)
+            .build();
+
+        let content_box = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+        content_box.set_margin_top(12);
+        content_box.set_margin_bottom(12);
+
+        let list = gtk4::ListBox::new();
+        list.set_selection_mode(gtk4::SelectionMode::None);
+        list.add_css_class("boxed-list");
+        list.set_placeholder(Some(&gtk4::Label::new(Some("No steps yet"))));
+
+        let scrolled = gtk4::ScrolledWindow::new();
+        scrolled.set_hscrollbar_policy(gtk4::PolicyType::Never);
+        scrolled.set_min_content_height(200);
+        scrolled.set_max_content_height(320);
+        scrolled.set_propagate_natural_height(true);
+        scrolled.set_child(Some(&list));
+        content_box.append(&scrolled);
+
+        // Add step row: kind, value, add button
+        let add_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+        let kind_dropdown = gtk4::DropDown::from_strings(STEP_KINDS);
+        let value_entry = gtk4::Entry::new();
+        value_entry.set_hexpand(true);
+        value_entry.set_placeholder_text(Some("Key token, milliseconds, speed or text"));
+        let add_button = gtk4::Button::from_icon_name("list-add-symbolic");
+        add_button.set_tooltip_text(Some("Add Step"));
+        add_box.append(&kind_dropdown);
+        add_box.append(&value_entry);
+        add_box.append(&add_button);
+        content_box.append(&add_box);
+
+        let recording = gtk4::ToggleButton::with_label("Record");
+        recording.set_tooltip_text(Some("Record key presses and releases; click again to stop"));
+        recording.set_halign(gtk4::Align::Start);
+        content_box.append(&recording);
+
+        let message = gtk4::Label::new(None);
+        message.set_halign(gtk4::Align::Start);
+        message.add_css_class("error");
+        content_box.append(&message);
+
+        content_box.append(&gtk4::Separator::new(gtk4::Orientation::Horizontal));
+
+        let preview_title = gtk4::Label::new(Some("Preview:"));
+        preview_title.set_halign(gtk4::Align::Start);
+        content_box.append(&preview_title);
+
+        let preview = gtk4::Label::new(None);
+        preview.set_halign(gtk4::Align::Start);
+        preview.set_xalign(0.0);
+        preview.set_wrap(true);
+        preview.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+        preview.set_selectable(true);
+        preview.add_css_class("monospace");
+        content_box.append(&preview);
+
+        let state = Rc::new(EditorState {
+            steps: RefCell::new(steps),
+            held: RefCell::new(Vec::new()),
+            recording: recording.clone(),
+            list,
+            preview,
+            message,
+        });
+
+        let add_state = state.clone();
+        let value_entry_activate = value_entry.clone();
+        let add = move || match step_from_input(kind_dropdown.selected(), value_entry.text().trim())
+        {
+            Ok(step) => {
+                add_state.steps.borrow_mut().push(step);
+                value_entry.set_text("");
+                add_state.message.set_text("");
+                add_state.refresh();
+            }
+            Err(e) => add_state.message.set_text(&e),
+        };
+        let add = Rc::new(add);
+        let add_clone = add.clone();
+        add_button.connect_clicked(move |_| add_clone());
+        value_entry_activate.connect_activate(move |_| add());
+
+        let toggle_state = state.clone();
+        recording.connect_toggled(move |button| {
+            toggle_state.held.borrow_mut().clear();
+            button.set_label(if button.is_active() {
+                "Stop Recording"
+            } else {
+                "Record"
+            });
+        });
+
+        // Capture keys before the dialog sees them, so Enter and Escape are
+        // recorded instead of closing it
+        let recorder = gtk4::EventControllerKey::new();
+        recorder.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        let press_state = state.clone();
+        recorder.connect_key_pressed(move |_, keyval, _, _| {
+            if !press_state.recording.is_active() {
+                return glib::Propagation::Proceed;
+            }
+            if let Some(token) = token_from_keyval(keyval) {
+                press_state.record_press(token);
+            }
+            glib::Propagation::Stop
+        });
+        let release_state = state.clone();
+        recorder.connect_key_released(move |_, keyval, _, _| {
+            if release_state.recording.is_active()
+                && let Some(token) = token_from_keyval(keyval)
+            {
+                release_state.record_release(token);
+            }
+        });
+        dialog.add_controller(recorder);
+
+        dialog.set_extra_child(Some(&content_box));
+
+        dialog.add_response("cancel", "Cancel");
+        dialog.add_response("apply", "Apply");
+        dialog.set_response_appearance("apply", adw::ResponseAppearance::Suggested);
+        dialog.set_close_response("cancel");
+
+        state.refresh();
+
+        Self { dialog, state }
+    }
+
+    /// Show the editor and return the edited steps, or `None` if cancelled
+    pub async fn run(self, parent: &impl IsA<gtk4::Widget>) -> Option<Vec<MacroStep>> {
+        let response = self.dialog.choose_future(parent).await;
+        self.state.recording.set_active(false);
+
+        match response.as_str() {
+            "apply" => Some(self.state.steps.take()),
+            _ => None,
+        }
+    }
+}
+
+impl EditorState {
+    /// Rebuild the step list and preview from `steps`
+    fn refresh(self: &Rc<Self>) {
+        while let Some(row) = self.list.row_at_index(0) {
+            self.list.remove(&row);
+        }
+
+        let steps = self.steps.borrow();
+        for (idx, step) in steps.iter().enumerate() {
+            self.list.append(&self.step_row(idx, step, steps.len()));
+        }
+
+        let mut preview = MacroStep::format_sequence(&steps);
+        if preview.is_empty() {
+            preview.push_str("(empty)");
+        }
+        let duration = MacroStep::estimated_duration(&steps);
+        preview.push_str(&format!("\n≈ {:.1} s", duration.as_secs_f64()));
+        for issue in MacroStep::validate(&steps) {
+            preview.push_str(&format!("\n⚠ {}", issue));
+        }
+        self.preview.set_text(&preview);
+    }
+
+    fn step_row(self: &Rc<Self>, idx: usize, step: &MacroStep, count: usize) -> gtk4::Box {
+        let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+        row.set_margin_start(6);
+        row.set_margin_end(6);
+        row.set_margin_top(3);
+        row.set_margin_bottom(3);
+
+        let label = gtk4::Label::new(Some(&format!("{}. {}", idx + 1, describe_step(step))));
+        label.set_halign(gtk4::Align::Start);
+        label.set_hexpand(true);
+        row.append(&label);
+
+        type Edit = fn(&mut Vec<MacroStep>, usize);
+        let buttons: [(&str, &str, bool, Edit); 3] = [
+            ("go-up-symbolic", "Move Up", idx > 0, |steps, i| {
+                steps.swap(i - 1, i)
+            }),
+            (
+                "go-down-symbolic",
+                "Move Down",
+                idx + 1 < count,
+                |steps, i| steps.swap(i, i + 1),
+            ),
+            ("list-remove-symbolic", "Remove Step", true, |steps, i| {
+                steps.remove(i);
+            }),
+        ];
+        for (icon, tooltip, sensitive, edit) in buttons {
+            let button = gtk4::Button::from_icon_name(icon);
+            button.set_tooltip_text(Some(tooltip));
+            button.set_sensitive(sensitive);
+            button.add_css_class("flat");
+
+            let state = self.clone();
+            button.connect_clicked(move |_| {
+                edit(&mut state.steps.borrow_mut(), idx);
+                state.refresh();
+            });
+            row.append(&button);
+        }
+
+        row
+    }
+
+    fn record_press(self: &Rc<Self>, token: KeyToken) {
+        // Ignore key repeat while a key is held
+        if self.held.borrow().contains(&token) {
+            return;
+        }
+        self.held.borrow_mut().push(token);
+        self.steps.borrow_mut().push(MacroStep::Press(token));
+        self.refresh();
+    }
+
+    fn record_release(self: &Rc<Self>, token: KeyToken) {
+        let was_held = {
+            let mut held = self.held.borrow_mut();
+            let idx = held.iter().position(|k| *k == token);
+            idx.map(|idx| held.remove(idx)).is_some()
+        };
+        // Keys already down when recording started have no press to match
+        if !was_held {
+            return;
+        }
+
+        {
+            let mut steps = self.steps.borrow_mut();
+            // A press immediately followed by its release is a tap
+            if steps.last() == Some(&MacroStep::Press(token)) {
+                steps.pop();
+                steps.push(MacroStep::Tap(token));
+            } else {
+                steps.push(MacroStep::Release(token));
+            }
+        }
+        self.refresh();
+    }
+}
+
+/// Build a step from the drop-down index and the value entry
+fn step_from_input(kind: u32, value: &str) -> Result<MacroStep, String> {
+    let token = || value.parse::<KeyToken>().map_err(|e| e.to_string());
+
+    match kind {
+        0 => token().map(MacroStep::Tap),
+        1 => token().map(MacroStep::Press),
+        2 => token().map(MacroStep::Release),
+        3 => value
+            .parse()
+            .map(MacroStep::Delay)
+            .map_err(|_| format!("Invalid delay '{}', expected milliseconds", value)),
+        4 => match value.parse::<u8>() {
+            Ok(speed @ 1..=9) => Ok(MacroStep::Speed(speed)),
+            _ => Err(format!("Invalid speed '{}', expected 1 to 9", value)),
+        },
+        _ if value.is_empty() => Err("Text step is empty".to_string()),
+        _ if value.contains(['{', '}']) => Err("Text may not contain '{' or '}'".to_string()),
+        _ => Ok(MacroStep::Text(value.to_string())),
+    }
+}
+
+fn describe_step(step: &MacroStep) -> String {
+    match step {
+        MacroStep::Tap(key) => format!("Tap {}", key),
+        MacroStep::Press(key) => format!("Press {}", key),
+        MacroStep::Release(key) => format!("Release {}", key),
+        MacroStep::Speed(level) => format!("Set speed to {}", level),
+        MacroStep::Delay(ms) => format!("Wait {} ms", ms),
+        MacroStep::Text(text) => format!("Type \"{}\"", text),
+    }
+}
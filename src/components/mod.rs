@@ -1,4 +1,10 @@
+pub mod key_capture;
+pub mod key_picker;
 pub mod keyboard_view;
+pub mod lint_panel;
+pub mod macro_editor;
 pub mod remap_dialog;
+pub mod settings_dialog;
 
 pub use keyboard_view::KeyboardView;
+pub use lint_panel::LintPanel;
@@ -1,34 +1,53 @@
 use adw::prelude::*;
+use gtk4::glib;
 use gtk4::prelude::*;
 use libadwaita as adw;
 
+use super::key_capture::token_from_key_event;
+use super::key_picker::KeyPicker;
+use super::macro_editor::MacroEditor;
+use crate::models::{KeyAction, KeyToken, MacroStep};
+
 #[derive(Debug, Clone)]
 pub struct RemapDialogResult {
-    pub source_key: String,
-    pub target_key: Option<String>,
-    pub remap_type: RemapType,
+    pub source_key: KeyToken,
+    pub target: RemapTarget,
+}
+
+/// What a key is given by the remap dialog
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemapTarget {
+    Key(KeyToken),
+    Macro(Vec<MacroStep>),
+    /// Remove the key's mapping
+    Clear,
 }
 
 #[derive(Debug, Clone)]
 pub struct RemapDialog {
     dialog: adw::AlertDialog,
+    /// Target key of a simple remap
+    picker: KeyPicker,
+    /// Macro text
     entry: gtk4::Entry,
-    source_key: String,
+    source_key: KeyToken,
     simple_radio: gtk4::CheckButton,
     macro_radio: gtk4::CheckButton,
 }
 
-#[derive(Debug, Clone)]
-pub enum RemapType {
-    Simple,
-    Macro,
-}
-
 impl RemapDialog {
-    pub fn new(source_key: &str, current_mapping: Option<&str>) -> Self {
+    pub fn new(source_key: KeyToken, current_action: Option<&KeyAction>) -> Self {
+        let (current_mapping, is_macro) = match current_action {
+            Some(KeyAction::SimpleRemap { target, .. }) => (Some(target.to_string()), false),
+            Some(KeyAction::Macro { steps, .. }) => (Some(MacroStep::format_sequence(steps)), true),
+            None => (None, false),
+        };
+        let current_mapping = current_mapping.as_deref();
+
         let dialog = adw::AlertDialog::builder()
             .heading(format!("Remap Key: {}", source_key))
-            .body("Enter the target key (e.g., 'A', 'Enter', 'LShift')\nor leave empty to clear the mapping")
+            .body("Pick the key to send, or use Press Key and press it.\nWith no key picked the mapping is cleared.")
+            .prefer_wide_layout(true)
             .build();
 
         let content_box = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
@@ -59,28 +78,94 @@ impl RemapDialog {
 
         content_box.append(&gtk4::Separator::new(gtk4::Orientation::Horizontal));
 
+        let capture = gtk4::ToggleButton::with_label("Press Key");
+        capture.set_tooltip_text(Some("Press the key to map to instead of picking it"));
+        capture.set_halign(gtk4::Align::Start);
+        content_box.append(&capture);
+
+        let picker = KeyPicker::new();
+        content_box.append(picker.widget());
+
         let entry = gtk4::Entry::new();
+        entry.set_placeholder_text(Some(
+            "Macro text or key sequence (e.g., 'hello' or '{ctrl}{c}')",
+        ));
+        entry.set_visible(false);
+        content_box.append(&entry);
+
         let entry_clone = entry.clone();
         let content_box_clone = content_box.clone();
+        let capture_clone = capture.clone();
+        let picker_clone = picker.clone();
+
+        let edit_steps_button = gtk4::Button::with_label("Edit Steps…");
+        edit_steps_button.set_halign(gtk4::Align::Start);
+        edit_steps_button.set_visible(false);
+        let edit_steps_clone = edit_steps_button.clone();
 
         simple_radio.connect_toggled(move |radio| {
-            if radio.is_active() {
-                entry_clone.set_placeholder_text(Some("Target key (e.g., 'A', 'Enter', 'Shift')"));
-            } else {
-                entry_clone.set_placeholder_text(Some(
-                    "Macro text or key sequence (e.g., 'hello' or '{ctrl}{c}')",
-                ));
-            }
+            let simple = radio.is_active();
+            picker_clone.widget().set_visible(simple);
+            capture_clone.set_visible(simple);
+            capture_clone.set_active(false);
+            entry_clone.set_visible(!simple);
+            edit_steps_clone.set_visible(!simple);
             // Force the content box to repaint
             content_box_clone.queue_draw();
         });
-        entry.set_placeholder_text(Some("Target key (e.g., 'A', 'Enter', 'Shift')"));
 
-        if let Some(mapping) = current_mapping {
-            entry.set_text(mapping);
+        match current_action {
+            Some(KeyAction::SimpleRemap { target, .. }) => picker.select(Some(*target)),
+            Some(KeyAction::Macro { .. }) => entry.set_text(current_mapping.unwrap_or_default()),
+            None => {}
         }
 
-        content_box.append(&entry);
+        capture.connect_toggled(|button| {
+            button.set_label(if button.is_active() {
+                "Press a Key…"
+            } else {
+                "Press Key"
+            });
+        });
+
+        // Capture keys before the dialog sees them, so Enter and Escape can
+        // be chosen instead of closing it
+        let listener = gtk4::EventControllerKey::new();
+        listener.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        let capture_clone = capture.clone();
+        let picker_clone = picker.clone();
+        listener.connect_key_pressed(move |_, keyval, keycode, _| {
+            if !capture_clone.is_active() {
+                return glib::Propagation::Proceed;
+            }
+            // Keys with no SmartSet token leave capture running
+            if let Some(token) = token_from_key_event(keyval, keycode) {
+                picker_clone.select(Some(token));
+                capture_clone.set_active(false);
+            }
+            glib::Propagation::Stop
+        });
+        dialog.add_controller(listener);
+
+        let entry_clone = entry.clone();
+        edit_steps_button.connect_clicked(move |button| {
+            // Start from the steps in the entry; text that does not parse
+            // is left alone unless the editor is applied
+            let steps = MacroStep::parse_sequence(entry_clone.text().as_str()).unwrap_or_default();
+            let editor = MacroEditor::new(source_key, steps);
+            let entry = entry_clone.clone();
+            let button = button.clone();
+            relm4::spawn_local(async move {
+                if let Some(steps) = editor.run(&button).await {
+                    entry.set_text(&MacroStep::format_sequence(&steps));
+                }
+            });
+        });
+        content_box.append(&edit_steps_button);
+
+        if is_macro {
+            macro_radio.set_active(true);
+        }
 
         if let Some(mapping) = current_mapping {
             let current_label =
@@ -105,49 +190,67 @@ impl RemapDialog {
 
         Self {
             dialog,
+            picker,
             entry,
-            source_key: source_key.to_string(),
+            source_key,
             simple_radio,
             macro_radio,
         }
     }
 
+    /// Show the dialog and return the new mapping, or `None` if it was
+    /// cancelled or the macro text is not a valid macro
     pub async fn run(self, parent: &impl IsA<gtk4::Widget>) -> Option<RemapDialogResult> {
-        let entry = self.entry.clone();
-        let source_key = self.source_key.clone();
-        let simple_radio = self.simple_radio.clone();
-
+        let source_key = self.source_key;
         let response = self.dialog.choose_future(parent).await;
 
-        match response.as_str() {
+        // Nothing picked or typed clears the mapping
+        let target = match response.as_str() {
+            "apply" if self.simple_radio.is_active() => self
+                .picker
+                .selected()
+                .map_or(RemapTarget::Clear, RemapTarget::Key),
             "apply" => {
-                let target = entry.text().to_string();
-                if target.is_empty() {
-                    Some(RemapDialogResult {
-                        source_key,
-                        target_key: None,
-                        remap_type: RemapType::Simple,
-                    })
+                let text = self.entry.text();
+                if text.is_empty() {
+                    RemapTarget::Clear
                 } else {
-                    let remap_type = if simple_radio.is_active() {
-                        RemapType::Simple
-                    } else {
-                        RemapType::Macro
-                    };
-
-                    Some(RemapDialogResult {
-                        source_key,
-                        target_key: Some(target),
-                        remap_type,
-                    })
+                    match macro_steps(&text) {
+                        Ok(steps) => RemapTarget::Macro(steps),
+                        Err((heading, details)) => {
+                            let alert = adw::AlertDialog::new(Some(heading), Some(&details));
+                            alert.add_response("ok", "OK");
+                            alert.set_default_response(Some("ok"));
+                            alert.choose_future(parent).await;
+                            return None;
+                        }
+                    }
                 }
             }
-            "clear" => Some(RemapDialogResult {
-                source_key,
-                target_key: None,
-                remap_type: RemapType::Simple,
-            }),
-            _ => None,
-        }
+            "clear" => RemapTarget::Clear,
+            _ => return None,
+        };
+
+        Some(RemapDialogResult { source_key, target })
+    }
+}
+
+/// Parse and check macro text, or give an alert heading and details
+fn macro_steps(text: &str) -> Result<Vec<MacroStep>, (&'static str, String)> {
+    let steps = MacroStep::parse_sequence(text).map_err(|e| {
+        (
+            "Invalid Macro",
+            format!("'{}' is not a valid macro: {}", text, e),
+        )
+    })?;
+    let issues = MacroStep::validate(&steps);
+    if !issues.is_empty() {
+        let details = issues
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        return Err(("Unbalanced Macro", details));
     }
+    Ok(steps)
 }
@@ -0,0 +1,251 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use adw::prelude::*;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use relm4::Sender;
+
+use crate::app::AppMsg;
+use crate::models::{
+    IndicatorMode, KeyboardSettings, Layer, LedSettings, MAX_BRIGHTNESS, MAX_PLAYBACK_SPEED, Rgb,
+    ThumbMode,
+};
+use crate::vdrive::LAYOUT_COUNT;
+
+/// Preferences for the keyboard-wide settings and LED files. Changes are
+/// sent as one [`AppMsg::SaveAllSettings`] when the dialog closes.
+pub struct SettingsDialog {
+    dialog: adw::PreferencesDialog,
+}
+
+impl SettingsDialog {
+    pub fn new(settings: KeyboardSettings, leds: LedSettings, sender: Sender<AppMsg>) -> Self {
+        let original_settings = settings.clone();
+        let settings = Rc::new(RefCell::new(settings));
+        let original_leds = leds.clone();
+        let leds = Rc::new(RefCell::new(leds));
+
+        let dialog = adw::PreferencesDialog::new();
+        dialog.set_title("Keyboard Settings");
+        dialog.add(&keyboard_page(&settings));
+        dialog.add(&lighting_page(&leds));
+
+        dialog.connect_closed(move |_| {
+            let settings = Some(settings.borrow().clone()).filter(|s| *s != original_settings);
+            let leds = Some(leds.borrow().clone()).filter(|l| *l != original_leds);
+            if settings.is_some() || leds.is_some() {
+                let _ = sender.send(AppMsg::SaveAllSettings { settings, leds });
+            }
+        });
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: &impl IsA<gtk4::Widget>) {
+        self.dialog.present(Some(parent));
+    }
+}
+
+fn keyboard_page(settings: &Rc<RefCell<KeyboardSettings>>) -> adw::PreferencesPage {
+    let page = adw::PreferencesPage::new();
+    page.set_title("Keyboard");
+    page.set_icon_name(Some("input-keyboard-symbolic"));
+
+    // Startup and thumb keys
+    let general = adw::PreferencesGroup::new();
+    general.set_title("General");
+    page.add(&general);
+
+    let layout_names: Vec<String> = (1..=LAYOUT_COUNT)
+        .map(|n| format!("Layout {}", n))
+        .collect();
+    let startup = adw::ComboRow::new();
+    startup.set_title("Startup Layout");
+    startup.set_subtitle("Layout active when the keyboard is plugged in");
+    startup.set_model(Some(&gtk4::StringList::new(
+        &layout_names.iter().map(String::as_str).collect::<Vec<_>>(),
+    )));
+    startup.set_selected(settings.borrow().startup_layout as u32);
+    let state = settings.clone();
+    startup.connect_selected_notify(move |row| {
+        state.borrow_mut().startup_layout = row.selected() as usize;
+    });
+    general.add(&startup);
+
+    let mode_names: Vec<String> = ThumbMode::ALL.iter().map(|m| m.to_string()).collect();
+    let thumb_mode = adw::ComboRow::new();
+    thumb_mode.set_title("Thumb Keys");
+    thumb_mode.set_subtitle("Operating system the thumb clusters are arranged for");
+    thumb_mode.set_model(Some(&gtk4::StringList::new(
+        &mode_names.iter().map(String::as_str).collect::<Vec<_>>(),
+    )));
+    let current_mode = settings.borrow().thumb_mode;
+    let mode_index = ThumbMode::ALL.iter().position(|m| *m == current_mode);
+    thumb_mode.set_selected(mode_index.unwrap_or(0) as u32);
+    let state = settings.clone();
+    thumb_mode.connect_selected_notify(move |row| {
+        if let Some(mode) = ThumbMode::ALL.get(row.selected() as usize) {
+            state.borrow_mut().thumb_mode = *mode;
+        }
+    });
+    general.add(&thumb_mode);
+
+    // Sounds
+    let sounds = adw::PreferencesGroup::new();
+    sounds.set_title("Sounds");
+    page.add(&sounds);
+
+    type Toggle = fn(&mut KeyboardSettings) -> &mut bool;
+    let toggles: [(&str, &str, Toggle); 2] = [
+        ("Key Click", "Click on every key press", |s| {
+            &mut s.key_click
+        }),
+        (
+            "Toggle Tone",
+            "Sound when Caps Lock, the keypad or another toggle changes",
+            |s| &mut s.toggle_tone,
+        ),
+    ];
+    for (title, subtitle, value) in toggles {
+        let row = adw::SwitchRow::new();
+        row.set_title(title);
+        row.set_subtitle(subtitle);
+        row.set_active(*value(&mut settings.borrow_mut()));
+        let state = settings.clone();
+        row.connect_active_notify(move |row| {
+            *value(&mut state.borrow_mut()) = row.is_active();
+        });
+        sounds.add(&row);
+    }
+
+    // Playback speeds
+    let playback = adw::PreferencesGroup::new();
+    playback.set_title("Playback");
+    playback.set_description(Some("0 is slowest, 9 is fastest"));
+    page.add(&playback);
+
+    type Speed = fn(&mut KeyboardSettings) -> &mut u8;
+    let speeds: [(&str, &str, Speed); 2] = [
+        (
+            "Macro Speed",
+            "Default speed for macros without a speed step",
+            |s| &mut s.macro_speed,
+        ),
+        (
+            "Status Report Speed",
+            "How fast the SmartSet status report is typed",
+            |s| &mut s.status_report_speed,
+        ),
+    ];
+    for (title, subtitle, value) in speeds {
+        let row = adw::SpinRow::with_range(0.0, MAX_PLAYBACK_SPEED as f64, 1.0);
+        row.set_title(title);
+        row.set_subtitle(subtitle);
+        row.set_value(*value(&mut settings.borrow_mut()) as f64);
+        let state = settings.clone();
+        row.connect_value_notify(move |row| {
+            *value(&mut state.borrow_mut()) = row.value() as u8;
+        });
+        playback.add(&row);
+    }
+
+    page
+}
+
+fn lighting_page(leds: &Rc<RefCell<LedSettings>>) -> adw::PreferencesPage {
+    let page = adw::PreferencesPage::new();
+    page.set_title("Lighting");
+    page.set_icon_name(Some("display-brightness-symbolic"));
+
+    let indicators = adw::PreferencesGroup::new();
+    indicators.set_title("Indicator LEDs");
+    page.add(&indicators);
+
+    let brightness = adw::SpinRow::with_range(0.0, MAX_BRIGHTNESS as f64, 1.0);
+    brightness.set_title("Brightness");
+    brightness.set_subtitle("0 turns the indicator LEDs off");
+    brightness.set_value(leds.borrow().brightness as f64);
+    let state = leds.clone();
+    brightness.connect_value_notify(move |row| {
+        state.borrow_mut().brightness = row.value() as u8;
+    });
+    indicators.add(&brightness);
+
+    let mode_names: Vec<String> = IndicatorMode::ALL.iter().map(|m| m.to_string()).collect();
+    let mode = adw::ComboRow::new();
+    mode.set_title("Show");
+    mode.set_subtitle("Which colour the indicators light up in");
+    mode.set_model(Some(&gtk4::StringList::new(
+        &mode_names.iter().map(String::as_str).collect::<Vec<_>>(),
+    )));
+    let current_mode = leds.borrow().indicator_mode;
+    let mode_index = IndicatorMode::ALL.iter().position(|m| *m == current_mode);
+    mode.set_selected(mode_index.unwrap_or(0) as u32);
+    let state = leds.clone();
+    mode.connect_selected_notify(move |row| {
+        if let Some(mode) = IndicatorMode::ALL.get(row.selected() as usize) {
+            state.borrow_mut().indicator_mode = *mode;
+        }
+    });
+    indicators.add(&mode);
+
+    let layers = adw::PreferencesGroup::new();
+    layers.set_title("Layer Colours");
+    page.add(&layers);
+    for layer in Layer::ALL {
+        let color = leds.borrow().layer_color(layer);
+        let state = leds.clone();
+        layers.add(&color_row(&format!("{} Layer", layer), color, move |rgb| {
+            *state.borrow_mut().layer_color_mut(layer) = rgb;
+        }));
+    }
+
+    let layouts = adw::PreferencesGroup::new();
+    layouts.set_title("Layout Colours");
+    page.add(&layouts);
+    for slot in 0..LAYOUT_COUNT {
+        let color = leds.borrow().layout_colors[slot];
+        let state = leds.clone();
+        layouts.add(&color_row(
+            &format!("Layout {}", slot + 1),
+            color,
+            move |rgb| {
+                state.borrow_mut().layout_colors[slot] = rgb;
+            },
+        ));
+    }
+
+    page
+}
+
+/// A row with a colour picker that calls `on_change` with each new colour
+fn color_row(title: &str, color: Rgb, on_change: impl Fn(Rgb) + 'static) -> adw::ActionRow {
+    let row = adw::ActionRow::new();
+    row.set_title(title);
+
+    let picker = gtk4::ColorDialog::new();
+    picker.set_title(title);
+    picker.set_with_alpha(false);
+    let button = gtk4::ColorDialogButton::new(Some(picker));
+    button.set_valign(gtk4::Align::Center);
+    button.set_rgba(&gtk4::gdk::RGBA::new(
+        color.red as f32 / 255.0,
+        color.green as f32 / 255.0,
+        color.blue as f32 / 255.0,
+        1.0,
+    ));
+    button.connect_rgba_notify(move |button| {
+        let rgba = button.rgba();
+        let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        on_change(Rgb::new(
+            channel(rgba.red()),
+            channel(rgba.green()),
+            channel(rgba.blue()),
+        ));
+    });
+
+    row.add_suffix(&button);
+    row.set_activatable_widget(Some(&button));
+    row
+}
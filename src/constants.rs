@@ -5,8 +5,18 @@ pub const KEY_BACKGROUND_DEFAULT: Color = (0.25, 0.27, 0.29);
 pub const KEY_BACKGROUND_HOVER: Color = (0.4, 0.4, 0.4);
 pub const KEY_BACKGROUND_HOME_ROW: Color = (0.30, 0.35, 0.60);
 pub const KEY_BACKGROUND_REMAPPED: Color = (0.20, 0.30, 0.40);
+pub const KEY_BACKGROUND_DIFF_ADDED: Color = (0.20, 0.45, 0.25);
+pub const KEY_BACKGROUND_DIFF_REMOVED: Color = (0.55, 0.20, 0.20);
+pub const KEY_BACKGROUND_DIFF_CHANGED: Color = (0.60, 0.45, 0.15);
+pub const KEY_BACKGROUND_DROP_TARGET: Color = (0.25, 0.50, 0.75);
 pub const KEY_BORDER: Color = (0.5, 0.5, 0.5);
 pub const KEY_BORDER_WIDTH: f64 = 1.3;
+pub const KEY_FOCUS_RING: Color = (0.45, 0.65, 0.95);
+pub const KEY_FOCUS_RING_WIDTH: f64 = 3.0;
+pub const KEY_SELECTED_BORDER: Color = (0.95, 0.75, 0.30);
+pub const KEY_SELECTED_BORDER_WIDTH: f64 = 3.0;
+pub const SELECTION_BAND: Color = (0.95, 0.75, 0.30);
+pub const SELECTION_BAND_ALPHA: f64 = 0.2;
 
 // Text Colors
 pub const TEXT_PRIMARY: Color = (0.9, 0.9, 0.9); // White
@@ -0,0 +1,47 @@
+use std::fs;
+
+use crate::fs_util::write_atomic;
+
+#[test]
+fn test_write_atomic_creates_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("layout1.txt");
+
+    write_atomic(&path, b"[a]>[b]\n").unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[a]>[b]\n");
+}
+
+#[test]
+fn test_write_atomic_replaces_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("layout1.txt");
+    fs::write(&path, "[a]>[b]\n[c]>[d]\n[e]>[f]\n").unwrap();
+
+    write_atomic(&path, b"[x]>[y]\n").unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[x]>[y]\n");
+}
+
+#[test]
+fn test_write_atomic_leaves_no_temp_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("layout1.txt");
+
+    write_atomic(&path, b"[a]>[b]\n").unwrap();
+
+    let names: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    assert_eq!(names, vec!["layout1.txt"]);
+}
+
+#[test]
+fn test_write_atomic_missing_dir_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing/layout1.txt");
+
+    assert!(write_atomic(&path, b"[a]>[b]\n").is_err());
+    assert!(!path.exists());
+}
@@ -1,6 +1,12 @@
 pub mod app;
+pub mod cli;
 pub mod components;
 pub mod constants;
+pub mod fs_util;
 pub mod models;
+pub mod vdrive;
+
+#[cfg(test)]
+mod fs_util_test;
 
 pub use app::App;
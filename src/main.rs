@@ -1,5 +1,23 @@
-use kinesis_configurator::App;
+use std::process::ExitCode;
 
-fn main() {
-    App::run();
+use kinesis_configurator::{App, cli};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        App::run();
+        return ExitCode::SUCCESS;
+    }
+
+    match cli::run(&args, &mut std::io::stdout()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if e.is::<cli::UsageError>() => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            ExitCode::from(2)
+        }
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,111 @@
+use super::kinesis_layout::{KinesisLayout, LineChange};
+
+/// Edits kept per layout before the oldest are forgotten
+pub const HISTORY_LIMIT: usize = 100;
+
+/// One reversible change to a layout. Only the replaced lines are kept, with
+/// their original text, so undo restores the file exactly without storing a
+/// copy of the layout for every step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+    /// Short description such as "Remap caps"
+    pub label: String,
+    change: LineChange,
+}
+
+impl Edit {
+    pub fn undo(&self, layout: &mut KinesisLayout) {
+        self.change.revert(layout);
+    }
+
+    pub fn redo(&self, layout: &mut KinesisLayout) {
+        self.change.apply(layout);
+    }
+}
+
+/// Undo and redo stacks for a single layout slot
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `edit` on the layout and record it. Returns false, recording
+    /// nothing, if the layout did not change.
+    pub fn apply(
+        &mut self,
+        layout: &mut KinesisLayout,
+        label: impl Into<String>,
+        edit: impl FnOnce(&mut KinesisLayout),
+    ) -> bool {
+        let before = layout.clone();
+        edit(layout);
+        self.record(label, before, layout.clone())
+    }
+
+    /// Record a change made elsewhere. Returns false if no line changed.
+    pub fn record(
+        &mut self,
+        label: impl Into<String>,
+        before: KinesisLayout,
+        after: KinesisLayout,
+    ) -> bool {
+        if before.same_lines(&after) {
+            return false;
+        }
+
+        self.undo.push(Edit {
+            label: label.into(),
+            change: before.change_to(&after),
+        });
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        true
+    }
+
+    /// Revert the latest edit, returning its label
+    pub fn undo(&mut self, layout: &mut KinesisLayout) -> Option<String> {
+        let edit = self.undo.pop()?;
+        edit.undo(layout);
+        let label = edit.label.clone();
+        self.redo.push(edit);
+        Some(label)
+    }
+
+    /// Reapply the latest undone edit, returning its label
+    pub fn redo(&mut self, layout: &mut KinesisLayout) -> Option<String> {
+        let edit = self.redo.pop()?;
+        edit.redo(layout);
+        let label = edit.label.clone();
+        self.undo.push(edit);
+        Some(label)
+    }
+
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo.last().map(|edit| edit.label.as_str())
+    }
+
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo.last().map(|edit| edit.label.as_str())
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
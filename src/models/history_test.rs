@@ -0,0 +1,117 @@
+use super::history::{HISTORY_LIMIT, History};
+use super::key_token::KeyToken;
+use super::kinesis_layout::KinesisLayout;
+
+#[test]
+fn test_undo_redo() {
+    let mut layout: KinesisLayout = "# mine\n[a]>[b]\n".parse().unwrap();
+    let original = layout.clone();
+    let mut history = History::new();
+
+    assert!(history.apply(&mut layout, "Remap caps", |l| {
+        l.add_remap(KeyToken::Caps, KeyToken::Escape)
+    }));
+    assert!(history.apply(&mut layout, "Clear a", |l| l.remove_by_source(KeyToken::A)));
+    let edited = layout.clone();
+
+    assert_eq!(history.undo_label(), Some("Clear a"));
+    assert_eq!(history.undo(&mut layout).as_deref(), Some("Clear a"));
+    assert_eq!(history.undo(&mut layout).as_deref(), Some("Remap caps"));
+    assert_eq!(layout, original);
+    assert_eq!(layout.to_string(), "# mine\n[a]>[b]\n");
+    assert!(!history.can_undo());
+    assert_eq!(history.undo(&mut layout), None);
+
+    assert_eq!(history.redo(&mut layout).as_deref(), Some("Remap caps"));
+    assert_eq!(history.redo(&mut layout).as_deref(), Some("Clear a"));
+    assert_eq!(layout, edited);
+    assert!(!history.can_redo());
+}
+
+#[test]
+fn test_new_edit_clears_redo() {
+    let mut layout = KinesisLayout::new();
+    let mut history = History::new();
+
+    history.apply(&mut layout, "one", |l| {
+        l.add_remap(KeyToken::A, KeyToken::B)
+    });
+    history.undo(&mut layout);
+    assert!(history.can_redo());
+
+    history.apply(&mut layout, "two", |l| {
+        l.add_remap(KeyToken::C, KeyToken::D)
+    });
+    assert!(!history.can_redo());
+    assert_eq!(history.redo_label(), None);
+}
+
+#[test]
+fn test_unchanged_layout_is_not_recorded() {
+    let mut layout = KinesisLayout::new();
+    let mut history = History::new();
+
+    assert!(!history.apply(&mut layout, "nothing", |l| l.remove_by_source(KeyToken::A)));
+    assert!(!history.can_undo());
+}
+
+#[test]
+fn test_edit_back_to_same_lines_is_not_recorded() {
+    let mut layout: KinesisLayout = "[a] > [b]\n".parse().unwrap();
+    let mut history = History::new();
+
+    history.apply(&mut layout, "one", |l| {
+        l.add_remap(KeyToken::C, KeyToken::D)
+    });
+    history.undo(&mut layout);
+
+    // Remapping a key to what it already sends changes no line, so it
+    // neither adds an undo step nor drops the redo step
+    assert!(!history.apply(&mut layout, "same", |l| {
+        l.remap_to_key(KeyToken::A, KeyToken::C);
+        l.remap_to_key(KeyToken::A, KeyToken::B);
+    }));
+    assert!(!history.can_undo());
+    assert_eq!(history.redo_label(), Some("one"));
+}
+
+#[test]
+fn test_undo_restores_original_text() {
+    let content = "# top\r\n[a] > [b]\r\n{c}>{x}{y}\r\n# bottom";
+    let mut layout: KinesisLayout = content.parse().unwrap();
+    let mut history = History::new();
+
+    history.apply(&mut layout, "clear c", |l| l.remove_by_source(KeyToken::C));
+    history.apply(&mut layout, "remap a", |l| {
+        l.remap_to_key(KeyToken::A, KeyToken::Z)
+    });
+    history.apply(&mut layout, "replace", |l| {
+        *l = "[q]>[w]\n[e]>[r]\n[t]>[y]\n".parse().unwrap()
+    });
+    let replaced = layout.to_string();
+
+    while history.undo(&mut layout).is_some() {}
+    assert_eq!(layout.to_string(), content);
+
+    while history.redo(&mut layout).is_some() {}
+    assert_eq!(layout.to_string(), replaced);
+}
+
+#[test]
+fn test_history_is_bounded() {
+    let mut layout = KinesisLayout::new();
+    let mut history = History::new();
+
+    for _ in 0..HISTORY_LIMIT + 5 {
+        history.apply(&mut layout, "add", |l| {
+            l.add_remap(KeyToken::A, KeyToken::B)
+        });
+    }
+
+    let mut undone = 0;
+    while history.undo(&mut layout).is_some() {
+        undone += 1;
+    }
+    assert_eq!(undone, HISTORY_LIMIT);
+    assert_eq!(layout.mapping_count(), 5);
+}
@@ -0,0 +1,562 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+/// A single SmartSet key token, as written inside `[...]` or `{...}` in a
+/// layout file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum KeyToken {
+    // Letters
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+
+    // Number row
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    Digit0,
+
+    // Punctuation
+    Hyphen,
+    Equals,
+    OpenBracket,
+    CloseBracket,
+    Backslash,
+    Semicolon,
+    Quote,
+    Backtick,
+    Comma,
+    Period,
+    Slash,
+
+    // Modifiers
+    LShift,
+    RShift,
+    LCtrl,
+    RCtrl,
+    LAlt,
+    RAlt,
+    LWin,
+    RWin,
+
+    // Editing and navigation
+    Tab,
+    Caps,
+    Escape,
+    Enter,
+    Space,
+    Backspace,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Up,
+    Down,
+    Left,
+    Right,
+    Menu,
+    PrintScreen,
+    ScrollLock,
+    Pause,
+
+    // Function keys
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+
+    // Numeric keypad actions
+    NumLock,
+    Kp0,
+    Kp1,
+    Kp2,
+    Kp3,
+    Kp4,
+    Kp5,
+    Kp6,
+    Kp7,
+    Kp8,
+    Kp9,
+    KpPeriod,
+    KpEnter,
+    KpPlus,
+    KpMinus,
+    KpMultiply,
+    KpDivide,
+    KpEquals,
+
+    // Media
+    Mute,
+    VolumeDown,
+    VolumeUp,
+    PrevTrack,
+    PlayPause,
+    NextTrack,
+    Calculator,
+
+    // Mouse buttons
+    LeftClick,
+    MiddleClick,
+    RightClick,
+
+    // International
+    IntlBackslash,
+    IntlRo,
+    IntlYen,
+
+    // Keyboard specific
+    KeypadToggle,
+    KeypadShift,
+    LFn,
+    RFn,
+    Hotkey1,
+    Hotkey2,
+    Hotkey3,
+    Hotkey4,
+    SmartSet,
+    Null,
+
+    /// The keypad-layer position of a base key, written with a `kp-` prefix
+    /// (e.g. `kp-j`).
+    Keypad(&'static KeyToken),
+}
+
+/// Canonical token name and on-screen legend for every base token.
+/// Legends use `\n` to split shifted and unshifted symbols.
+const TOKENS: &[(KeyToken, &str, &str)] = &[
+    (KeyToken::A, "a", "A"),
+    (KeyToken::B, "b", "B"),
+    (KeyToken::C, "c", "C"),
+    (KeyToken::D, "d", "D"),
+    (KeyToken::E, "e", "E"),
+    (KeyToken::F, "f", "F"),
+    (KeyToken::G, "g", "G"),
+    (KeyToken::H, "h", "H"),
+    (KeyToken::I, "i", "I"),
+    (KeyToken::J, "j", "J"),
+    (KeyToken::K, "k", "K"),
+    (KeyToken::L, "l", "L"),
+    (KeyToken::M, "m", "M"),
+    (KeyToken::N, "n", "N"),
+    (KeyToken::O, "o", "O"),
+    (KeyToken::P, "p", "P"),
+    (KeyToken::Q, "q", "Q"),
+    (KeyToken::R, "r", "R"),
+    (KeyToken::S, "s", "S"),
+    (KeyToken::T, "t", "T"),
+    (KeyToken::U, "u", "U"),
+    (KeyToken::V, "v", "V"),
+    (KeyToken::W, "w", "W"),
+    (KeyToken::X, "x", "X"),
+    (KeyToken::Y, "y", "Y"),
+    (KeyToken::Z, "z", "Z"),
+    (KeyToken::Digit1, "1", "!\n1"),
+    (KeyToken::Digit2, "2", "@\n2"),
+    (KeyToken::Digit3, "3", "#\n3"),
+    (KeyToken::Digit4, "4", "$\n4"),
+    (KeyToken::Digit5, "5", "%\n5"),
+    (KeyToken::Digit6, "6", "^\n6"),
+    (KeyToken::Digit7, "7", "&\n7"),
+    (KeyToken::Digit8, "8", "*\n8"),
+    (KeyToken::Digit9, "9", "(\n9"),
+    (KeyToken::Digit0, "0", ")\n0"),
+    (KeyToken::Hyphen, "hyphen", "_\n-"),
+    (KeyToken::Equals, "=", "+\n="),
+    (KeyToken::OpenBracket, "obrack", "{\n["),
+    (KeyToken::CloseBracket, "cbrack", "}\n]"),
+    (KeyToken::Backslash, "\\", "|\n\\"),
+    (KeyToken::Semicolon, ";", ":\n;"),
+    (KeyToken::Quote, "'", "\"\n'"),
+    (KeyToken::Backtick, "`", "~\n`"),
+    (KeyToken::Comma, ",", "<\n,"),
+    (KeyToken::Period, ".", ">\n."),
+    (KeyToken::Slash, "/", "?\n/"),
+    (KeyToken::LShift, "lshift", "Shift"),
+    (KeyToken::RShift, "rshift", "Shift"),
+    (KeyToken::LCtrl, "lctrl", "Ctrl"),
+    (KeyToken::RCtrl, "rctrl", "Ctrl"),
+    (KeyToken::LAlt, "lalt", "Alt"),
+    (KeyToken::RAlt, "ralt", "Alt"),
+    (KeyToken::LWin, "lwin", "Win"),
+    (KeyToken::RWin, "rwin", "Win"),
+    (KeyToken::Tab, "tab", "Tab"),
+    (KeyToken::Caps, "caps", "Caps"),
+    (KeyToken::Escape, "esc", "Esc"),
+    (KeyToken::Enter, "enter", "Enter"),
+    (KeyToken::Space, "space", "Space"),
+    (KeyToken::Backspace, "bspace", "Back\nSpace"),
+    (KeyToken::Delete, "delete", "Delete"),
+    (KeyToken::Insert, "insert", "Insert"),
+    (KeyToken::Home, "home", "Home"),
+    (KeyToken::End, "end", "End"),
+    (KeyToken::PageUp, "pup", "Pg\nUp"),
+    (KeyToken::PageDown, "pdown", "Pg\nDown"),
+    (KeyToken::Up, "up", "↑"),
+    (KeyToken::Down, "down", "↓"),
+    (KeyToken::Left, "left", "←"),
+    (KeyToken::Right, "right", "→"),
+    (KeyToken::Menu, "menu", "Menu"),
+    (KeyToken::PrintScreen, "prtscr", "Prt\nScr"),
+    (KeyToken::ScrollLock, "scroll", "Scroll\nLock"),
+    (KeyToken::Pause, "pause", "Pause"),
+    (KeyToken::F1, "f1", "F1"),
+    (KeyToken::F2, "f2", "F2"),
+    (KeyToken::F3, "f3", "F3"),
+    (KeyToken::F4, "f4", "F4"),
+    (KeyToken::F5, "f5", "F5"),
+    (KeyToken::F6, "f6", "F6"),
+    (KeyToken::F7, "f7", "F7"),
+    (KeyToken::F8, "f8", "F8"),
+    (KeyToken::F9, "f9", "F9"),
+    (KeyToken::F10, "f10", "F10"),
+    (KeyToken::F11, "f11", "F11"),
+    (KeyToken::F12, "f12", "F12"),
+    (KeyToken::F13, "f13", "F13"),
+    (KeyToken::F14, "f14", "F14"),
+    (KeyToken::F15, "f15", "F15"),
+    (KeyToken::F16, "f16", "F16"),
+    (KeyToken::F17, "f17", "F17"),
+    (KeyToken::F18, "f18", "F18"),
+    (KeyToken::F19, "f19", "F19"),
+    (KeyToken::F20, "f20", "F20"),
+    (KeyToken::F21, "f21", "F21"),
+    (KeyToken::F22, "f22", "F22"),
+    (KeyToken::F23, "f23", "F23"),
+    (KeyToken::F24, "f24", "F24"),
+    (KeyToken::NumLock, "numlk", "Num\nLock"),
+    (KeyToken::Kp0, "kp0", "Kp\n0"),
+    (KeyToken::Kp1, "kp1", "Kp\n1"),
+    (KeyToken::Kp2, "kp2", "Kp\n2"),
+    (KeyToken::Kp3, "kp3", "Kp\n3"),
+    (KeyToken::Kp4, "kp4", "Kp\n4"),
+    (KeyToken::Kp5, "kp5", "Kp\n5"),
+    (KeyToken::Kp6, "kp6", "Kp\n6"),
+    (KeyToken::Kp7, "kp7", "Kp\n7"),
+    (KeyToken::Kp8, "kp8", "Kp\n8"),
+    (KeyToken::Kp9, "kp9", "Kp\n9"),
+    (KeyToken::KpPeriod, "kp.", "Kp\n."),
+    (KeyToken::KpEnter, "kpenter", "Kp\nEnter"),
+    (KeyToken::KpPlus, "kpplus", "Kp\n+"),
+    (KeyToken::KpMinus, "kpmin", "Kp\n-"),
+    (KeyToken::KpMultiply, "kpmult", "Kp\n*"),
+    (KeyToken::KpDivide, "kpdiv", "Kp\n/"),
+    (KeyToken::KpEquals, "kp=", "Kp\n="),
+    (KeyToken::Mute, "mute", "Mute"),
+    (KeyToken::VolumeDown, "vol-", "Vol\n-"),
+    (KeyToken::VolumeUp, "vol+", "Vol\n+"),
+    (KeyToken::PrevTrack, "prev", "Prev"),
+    (KeyToken::PlayPause, "play", "Play"),
+    (KeyToken::NextTrack, "next", "Next"),
+    (KeyToken::Calculator, "calc", "Calc"),
+    (KeyToken::LeftClick, "lmouse", "L\nClick"),
+    (KeyToken::MiddleClick, "mmouse", "M\nClick"),
+    (KeyToken::RightClick, "rmouse", "R\nClick"),
+    (KeyToken::IntlBackslash, "intl-\\", "Intl\n\\"),
+    (KeyToken::IntlRo, "intl-ro", "Intl\nRo"),
+    (KeyToken::IntlYen, "intl-yen", "Intl\n¥"),
+    (KeyToken::KeypadToggle, "kptoggle", "Kp"),
+    (KeyToken::KeypadShift, "kpshift", "Kp\nShift"),
+    (KeyToken::LFn, "lfn", "Fn"),
+    (KeyToken::RFn, "rfn", "Fn"),
+    (KeyToken::Hotkey1, "hk1", "Hk1"),
+    (KeyToken::Hotkey2, "hk2", "Hk2"),
+    (KeyToken::Hotkey3, "hk3", "Hk3"),
+    (KeyToken::Hotkey4, "hk4", "Hk4"),
+    (KeyToken::SmartSet, "smartset", "Ss"),
+    (KeyToken::Null, "null", "None"),
+];
+
+/// Friendly spellings accepted when parsing, in addition to the canonical
+/// token names.
+const ALIASES: &[(&str, KeyToken)] = &[
+    ("-", KeyToken::Hyphen),
+    ("[", KeyToken::OpenBracket),
+    ("]", KeyToken::CloseBracket),
+    ("shift", KeyToken::LShift),
+    ("ctrl", KeyToken::LCtrl),
+    ("control", KeyToken::LCtrl),
+    ("alt", KeyToken::LAlt),
+    ("win", KeyToken::LWin),
+    ("windows", KeyToken::LWin),
+    ("super", KeyToken::LWin),
+    ("meta", KeyToken::LWin),
+    ("capslock", KeyToken::Caps),
+    ("escape", KeyToken::Escape),
+    ("return", KeyToken::Enter),
+    ("spc", KeyToken::Space),
+    ("backspace", KeyToken::Backspace),
+    ("bksp", KeyToken::Backspace),
+    ("del", KeyToken::Delete),
+    ("ins", KeyToken::Insert),
+    ("pgup", KeyToken::PageUp),
+    ("pageup", KeyToken::PageUp),
+    ("pgdn", KeyToken::PageDown),
+    ("pgdown", KeyToken::PageDown),
+    ("pagedown", KeyToken::PageDown),
+    ("fn", KeyToken::LFn),
+    ("kp", KeyToken::KeypadToggle),
+    ("keypad", KeyToken::KeypadToggle),
+    ("ss", KeyToken::SmartSet),
+];
+
+const KEYPAD_PREFIX: &str = "kp-";
+
+/// Groups of tokens for choosing a remap target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum KeyCategory {
+    Letters,
+    Numbers,
+    Symbols,
+    Modifiers,
+    /// Editing and navigation keys
+    Navigation,
+    Function,
+    Media,
+    Keypad,
+    Mouse,
+    /// International and keyboard-specific keys
+    Special,
+}
+
+impl KeyCategory {
+    pub const ALL: [KeyCategory; 10] = [
+        KeyCategory::Letters,
+        KeyCategory::Numbers,
+        KeyCategory::Symbols,
+        KeyCategory::Modifiers,
+        KeyCategory::Navigation,
+        KeyCategory::Function,
+        KeyCategory::Media,
+        KeyCategory::Keypad,
+        KeyCategory::Mouse,
+        KeyCategory::Special,
+    ];
+}
+
+impl fmt::Display for KeyCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyCategory::Letters => write!(f, "Letters"),
+            KeyCategory::Numbers => write!(f, "Numbers"),
+            KeyCategory::Symbols => write!(f, "Symbols"),
+            KeyCategory::Modifiers => write!(f, "Modifiers"),
+            KeyCategory::Navigation => write!(f, "Editing and Navigation"),
+            KeyCategory::Function => write!(f, "Function Keys"),
+            KeyCategory::Media => write!(f, "Media"),
+            KeyCategory::Keypad => write!(f, "Keypad"),
+            KeyCategory::Mouse => write!(f, "Mouse"),
+            KeyCategory::Special => write!(f, "Special"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKeyToken(pub String);
+
+impl fmt::Display for UnknownKeyToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown key token '{}'", self.0)
+    }
+}
+
+impl std::error::Error for UnknownKeyToken {}
+
+impl KeyToken {
+    /// Every base token in table order (keypad-layer positions excluded)
+    pub fn all() -> impl Iterator<Item = KeyToken> {
+        TOKENS.iter().map(|(token, _, _)| *token)
+    }
+
+    fn entry(&self) -> &'static (KeyToken, &'static str, &'static str) {
+        let base = self.base();
+        TOKENS
+            .iter()
+            .find(|(token, _, _)| *token == base)
+            .expect("every base token has a table entry")
+    }
+
+    /// The token with any keypad-layer prefix removed
+    pub fn base(&self) -> KeyToken {
+        match self {
+            KeyToken::Keypad(inner) => **inner,
+            other => *other,
+        }
+    }
+
+    /// The keypad-layer position of this key (`j` becomes `kp-j`)
+    pub fn on_keypad(&self) -> KeyToken {
+        KeyToken::Keypad(&self.entry().0)
+    }
+
+    pub fn is_keypad_position(&self) -> bool {
+        matches!(self, KeyToken::Keypad(_))
+    }
+
+    /// Canonical name of the base token, without any `kp-` prefix
+    pub fn name(&self) -> &'static str {
+        self.entry().1
+    }
+
+    /// Text drawn on the key cap; a `\n` separates shifted and unshifted legends
+    pub fn legend(&self) -> &'static str {
+        self.entry().2
+    }
+
+    /// The group the base token belongs to
+    pub fn category(&self) -> KeyCategory {
+        use KeyToken::*;
+
+        match self.base() {
+            A | B | C | D | E | F | G | H | I | J | K | L | M | N | O | P | Q | R | S | T | U
+            | V | W | X | Y | Z => KeyCategory::Letters,
+            Digit1 | Digit2 | Digit3 | Digit4 | Digit5 | Digit6 | Digit7 | Digit8 | Digit9
+            | Digit0 => KeyCategory::Numbers,
+            Hyphen | Equals | OpenBracket | CloseBracket | Backslash | Semicolon | Quote
+            | Backtick | Comma | Period | Slash => KeyCategory::Symbols,
+            LShift | RShift | LCtrl | RCtrl | LAlt | RAlt | LWin | RWin => KeyCategory::Modifiers,
+            Tab | Caps | Escape | Enter | Space | Backspace | Delete | Insert | Home | End
+            | PageUp | PageDown | Up | Down | Left | Right | Menu | PrintScreen | ScrollLock
+            | Pause => KeyCategory::Navigation,
+            F1 | F2 | F3 | F4 | F5 | F6 | F7 | F8 | F9 | F10 | F11 | F12 | F13 | F14 | F15
+            | F16 | F17 | F18 | F19 | F20 | F21 | F22 | F23 | F24 => KeyCategory::Function,
+            Mute | VolumeDown | VolumeUp | PrevTrack | PlayPause | NextTrack | Calculator => {
+                KeyCategory::Media
+            }
+            NumLock | Kp0 | Kp1 | Kp2 | Kp3 | Kp4 | Kp5 | Kp6 | Kp7 | Kp8 | Kp9 | KpPeriod
+            | KpEnter | KpPlus | KpMinus | KpMultiply | KpDivide | KpEquals => KeyCategory::Keypad,
+            LeftClick | MiddleClick | RightClick => KeyCategory::Mouse,
+            IntlBackslash | IntlRo | IntlYen | KeypadToggle | KeypadShift | LFn | RFn | Hotkey1
+            | Hotkey2 | Hotkey3 | Hotkey4 | SmartSet | Null | Keypad(_) => KeyCategory::Special,
+        }
+    }
+
+    /// Whether a search matches the token's name, legend, aliases or
+    /// descriptive name (such as `VolumeUp`), ignoring case
+    pub fn matches_search(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return true;
+        }
+
+        let base = self.base();
+        let descriptive = format!("{:?}", base);
+        let aliases = ALIASES
+            .iter()
+            .filter(|(_, token)| *token == base)
+            .map(|(alias, _)| *alias);
+        [base.name(), base.legend(), descriptive.as_str()]
+            .into_iter()
+            .chain(aliases)
+            .any(|term| term.to_lowercase().contains(&query))
+    }
+
+    fn lookup(name: &str) -> Option<KeyToken> {
+        TOKENS
+            .iter()
+            .find(|(_, n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(token, _, _)| *token)
+            .or_else(|| {
+                ALIASES
+                    .iter()
+                    .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+                    .map(|(_, token)| *token)
+            })
+    }
+}
+
+impl FromStr for KeyToken {
+    type Err = UnknownKeyToken;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Some(token) = Self::lookup(s) {
+            return Ok(token);
+        }
+
+        let has_prefix = s
+            .get(..KEYPAD_PREFIX.len())
+            .is_some_and(|p| p.eq_ignore_ascii_case(KEYPAD_PREFIX));
+        if has_prefix && let Some(base) = Self::lookup(&s[KEYPAD_PREFIX.len()..]) {
+            return Ok(base.on_keypad());
+        }
+
+        Err(UnknownKeyToken(s.to_string()))
+    }
+}
+
+impl fmt::Display for KeyToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_keypad_position() {
+            write!(f, "{}", KEYPAD_PREFIX)?;
+        }
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Serialised as the name used in layout files, such as `"kp-j"`
+impl Serialize for KeyToken {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyToken {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,113 @@
+use super::key_token::{KeyCategory, KeyToken};
+
+#[test]
+fn test_parse_canonical_names() {
+    assert_eq!("hyphen".parse(), Ok(KeyToken::Hyphen));
+    assert_eq!("lalt".parse(), Ok(KeyToken::LAlt));
+    assert_eq!("bspace".parse(), Ok(KeyToken::Backspace));
+    assert_eq!("f12".parse(), Ok(KeyToken::F12));
+    assert_eq!("kp.".parse(), Ok(KeyToken::KpPeriod));
+}
+
+#[test]
+fn test_parse_keypad_position() {
+    let token: KeyToken = "kp-j".parse().unwrap();
+    assert_eq!(token, KeyToken::Keypad(&KeyToken::J));
+    assert_eq!(token.base(), KeyToken::J);
+    assert!(token.is_keypad_position());
+    assert_eq!(token.to_string(), "kp-j");
+}
+
+#[test]
+fn test_parse_is_case_insensitive() {
+    assert_eq!("LShift".parse(), Ok(KeyToken::LShift));
+    assert_eq!("Q".parse(), Ok(KeyToken::Q));
+    assert_eq!("KP-Hyphen".parse(), Ok(KeyToken::Hyphen.on_keypad()));
+}
+
+#[test]
+fn test_parse_aliases() {
+    assert_eq!("Backspace".parse(), Ok(KeyToken::Backspace));
+    assert_eq!("Escape".parse(), Ok(KeyToken::Escape));
+    assert_eq!("PgDn".parse(), Ok(KeyToken::PageDown));
+    assert_eq!("-".parse(), Ok(KeyToken::Hyphen));
+}
+
+#[test]
+fn test_parse_unknown_token() {
+    assert!("notakey".parse::<KeyToken>().is_err());
+    assert!("kp-notakey".parse::<KeyToken>().is_err());
+    assert!("".parse::<KeyToken>().is_err());
+}
+
+#[test]
+fn test_display_round_trip() {
+    for token in KeyToken::all() {
+        let name = token.to_string();
+        assert_eq!(name.parse::<KeyToken>(), Ok(token), "base token {}", name);
+
+        let keypad = token.on_keypad();
+        let name = keypad.to_string();
+        assert_eq!(
+            name.parse::<KeyToken>(),
+            Ok(keypad),
+            "keypad token {}",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_canonical_names_are_unique() {
+    let mut names: Vec<&str> = KeyToken::all().map(|t| t.name()).collect();
+    let count = names.len();
+    names.sort();
+    names.dedup();
+    assert_eq!(names.len(), count);
+}
+
+#[test]
+fn test_legends() {
+    assert_eq!(KeyToken::Digit1.legend(), "!\n1");
+    assert_eq!(KeyToken::Backspace.legend(), "Back\nSpace");
+    assert_eq!(KeyToken::J.on_keypad().legend(), "J");
+}
+
+#[test]
+fn test_categories() {
+    assert_eq!(KeyToken::Q.category(), KeyCategory::Letters);
+    assert_eq!(KeyToken::Digit0.category(), KeyCategory::Numbers);
+    assert_eq!(KeyToken::Slash.category(), KeyCategory::Symbols);
+    assert_eq!(KeyToken::RAlt.category(), KeyCategory::Modifiers);
+    assert_eq!(KeyToken::PageDown.category(), KeyCategory::Navigation);
+    assert_eq!(KeyToken::F24.category(), KeyCategory::Function);
+    assert_eq!(KeyToken::PlayPause.category(), KeyCategory::Media);
+    assert_eq!(KeyToken::KpEnter.category(), KeyCategory::Keypad);
+    assert_eq!(KeyToken::MiddleClick.category(), KeyCategory::Mouse);
+    assert_eq!(KeyToken::SmartSet.category(), KeyCategory::Special);
+    assert_eq!(KeyToken::J.on_keypad().category(), KeyCategory::Letters);
+}
+
+#[test]
+fn test_every_category_has_tokens() {
+    for category in KeyCategory::ALL {
+        assert!(
+            KeyToken::all().any(|t| t.category() == category),
+            "{} is empty",
+            category
+        );
+    }
+}
+
+#[test]
+fn test_matches_search() {
+    // Name, legend, alias and descriptive name
+    assert!(KeyToken::VolumeUp.matches_search("vol+"));
+    assert!(KeyToken::Digit1.matches_search("!"));
+    assert!(KeyToken::Escape.matches_search("ESCAPE"));
+    assert!(KeyToken::VolumeUp.matches_search("volume"));
+    assert!(KeyToken::PageUp.matches_search(" page "));
+
+    assert!(KeyToken::A.matches_search(""));
+    assert!(!KeyToken::A.matches_search("volume"));
+}
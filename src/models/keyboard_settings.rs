@@ -0,0 +1,193 @@
+use std::{fmt, fs, io, path::Path};
+
+use super::kinesis_layout::LAYOUT_COUNT;
+use super::settings_file::{KeyValueSettings, SettingsError, SettingsLines, on_off, parse_on_off};
+use crate::fs_util::write_atomic;
+
+/// Highest macro and status report playback speed
+pub const MAX_PLAYBACK_SPEED: u8 = 9;
+
+/// What the thumb clusters send, matching the host operating system
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThumbMode {
+    #[default]
+    Pc,
+    Mac,
+}
+
+impl ThumbMode {
+    pub const ALL: [ThumbMode; 2] = [ThumbMode::Pc, ThumbMode::Mac];
+
+    fn name(self) -> &'static str {
+        match self {
+            ThumbMode::Pc => "pc",
+            ThumbMode::Mac => "mac",
+        }
+    }
+}
+
+impl fmt::Display for ThumbMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThumbMode::Pc => write!(f, "Windows and Linux"),
+            ThumbMode::Mac => write!(f, "macOS"),
+        }
+    }
+}
+
+/// Keyboard-wide options from `settings/kbd_settings.txt`
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyboardSettings {
+    /// Zero-based layout slot loaded when the keyboard starts
+    pub startup_layout: usize,
+    /// Click on every key press
+    pub key_click: bool,
+    /// Tone when a toggle such as Caps Lock changes
+    pub toggle_tone: bool,
+    /// Macro playback speed, 0 to 9
+    pub macro_speed: u8,
+    pub thumb_mode: ThumbMode,
+    /// Playback speed of the SmartSet status report, 0 to 9
+    pub status_report_speed: u8,
+    lines: SettingsLines,
+}
+
+impl Default for KeyboardSettings {
+    fn default() -> Self {
+        Self {
+            startup_layout: 0,
+            key_click: false,
+            toggle_tone: true,
+            macro_speed: 3,
+            thumb_mode: ThumbMode::Pc,
+            status_report_speed: 3,
+            lines: SettingsLines::default(),
+        }
+    }
+}
+
+type Getter = fn(&KeyboardSettings) -> String;
+type Setter = fn(&mut KeyboardSettings, &str) -> Option<()>;
+
+/// Known keys with how to write and read each one
+const FIELDS: &[(&str, Getter, Setter)] = &[
+    (
+        "startup_file",
+        |s| format!("layout{}.txt", s.startup_layout + 1),
+        |s, v| {
+            let n: usize = v
+                .strip_prefix("layout")?
+                .strip_suffix(".txt")?
+                .parse()
+                .ok()?;
+            s.startup_layout = (1..=LAYOUT_COUNT).contains(&n).then(|| n - 1)?;
+            Some(())
+        },
+    ),
+    (
+        "keyclick_status",
+        |s| on_off(s.key_click),
+        |s, v| {
+            s.key_click = parse_on_off(v)?;
+            Some(())
+        },
+    ),
+    (
+        "toggle_tone",
+        |s| on_off(s.toggle_tone),
+        |s, v| {
+            s.toggle_tone = parse_on_off(v)?;
+            Some(())
+        },
+    ),
+    (
+        "macro_speed",
+        |s| s.macro_speed.to_string(),
+        |s, v| {
+            s.macro_speed = parse_speed(v)?;
+            Some(())
+        },
+    ),
+    (
+        "thumb_mode",
+        |s| s.thumb_mode.name().to_string(),
+        |s, v| {
+            s.thumb_mode = ThumbMode::ALL
+                .into_iter()
+                .find(|mode| mode.name().eq_ignore_ascii_case(v))?;
+            Some(())
+        },
+    ),
+    (
+        "status_play_speed",
+        |s| s.status_report_speed.to_string(),
+        |s, v| {
+            s.status_report_speed = parse_speed(v)?;
+            Some(())
+        },
+    ),
+];
+
+impl KeyboardSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read settings from a file, using the defaults if it does not exist
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match fs::read_to_string(&path) {
+            Ok(content) => content
+                .parse()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write settings to a file, atomically replacing any existing one
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        write_atomic(path, self.to_string().as_bytes())
+    }
+}
+
+impl KeyValueSettings for KeyboardSettings {
+    fn keys() -> Vec<String> {
+        FIELDS.iter().map(|(key, _, _)| key.to_string()).collect()
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        field(key).map(|(_, get, _)| get(self))
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Option<()> {
+        field(key).and_then(|(_, _, set)| set(self, value))
+    }
+}
+
+impl std::str::FromStr for KeyboardSettings {
+    type Err = SettingsError;
+
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        let (settings, lines) = SettingsLines::parse::<Self>(content)?;
+        Ok(Self { lines, ..settings })
+    }
+}
+
+impl fmt::Display for KeyboardSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.lines.write(f, self)
+    }
+}
+
+fn field(key: &str) -> Option<&'static (&'static str, Getter, Setter)> {
+    FIELDS
+        .iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(key))
+}
+
+fn parse_speed(value: &str) -> Option<u8> {
+    value
+        .parse()
+        .ok()
+        .filter(|speed| *speed <= MAX_PLAYBACK_SPEED)
+}
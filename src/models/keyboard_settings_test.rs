@@ -0,0 +1,101 @@
+use super::keyboard_settings::{KeyboardSettings, ThumbMode};
+use super::settings_file::SettingsError;
+
+const SETTINGS: &str = "\
+startup_file=layout3.txt\r
+keyclick_status=OFF\r
+toggle_tone = on\r
+macro_speed=5\r
+v_drive=off\r
+thumb_mode=mac\r
+status_play_speed=2\r
+";
+
+#[test]
+fn test_parse_settings() {
+    let settings: KeyboardSettings = SETTINGS.parse().unwrap();
+
+    assert_eq!(settings.startup_layout, 2);
+    assert!(!settings.key_click);
+    assert!(settings.toggle_tone);
+    assert_eq!(settings.macro_speed, 5);
+    assert_eq!(settings.thumb_mode, ThumbMode::Mac);
+    assert_eq!(settings.status_report_speed, 2);
+}
+
+#[test]
+fn test_untouched_settings_round_trip() {
+    let settings: KeyboardSettings = SETTINGS.parse().unwrap();
+    assert_eq!(settings.to_string(), SETTINGS);
+
+    let empty: KeyboardSettings = "".parse().unwrap();
+    assert_eq!(empty, KeyboardSettings::default());
+    assert_eq!(empty.to_string(), "");
+}
+
+#[test]
+fn test_edit_keeps_unknown_keys() {
+    let mut settings: KeyboardSettings = SETTINGS.parse().unwrap();
+    settings.key_click = true;
+    settings.startup_layout = 0;
+
+    assert_eq!(
+        settings.to_string(),
+        "startup_file=layout1.txt\r\nkeyclick_status=on\r\ntoggle_tone = on\r\nmacro_speed=5\r\nv_drive=off\r\nthumb_mode=mac\r\nstatus_play_speed=2\r\n"
+    );
+}
+
+#[test]
+fn test_missing_settings_added_when_changed() {
+    let mut settings: KeyboardSettings = "# comment\nv_drive=off".parse().unwrap();
+    assert_eq!(settings.to_string(), "# comment\nv_drive=off");
+
+    settings.macro_speed = 9;
+    assert_eq!(
+        settings.to_string(),
+        "# comment\nv_drive=off\nmacro_speed=9\n"
+    );
+}
+
+#[test]
+fn test_invalid_settings() {
+    let cases = [
+        ("startup_file=layout10.txt\n", "startup_file"),
+        ("macro_speed=12\n", "macro_speed"),
+        ("keyclick_status=maybe\n", "keyclick_status"),
+        ("\nthumb_mode=left\n", "thumb_mode"),
+    ];
+    for (content, key) in cases {
+        let err = content.parse::<KeyboardSettings>().unwrap_err();
+        assert_eq!(err.key, key);
+    }
+
+    assert_eq!(
+        "\n\nstatus_play_speed=fast".parse::<KeyboardSettings>(),
+        Err(SettingsError {
+            line: 3,
+            key: "status_play_speed".to_string(),
+            value: "fast".to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_settings_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("kbd_settings.txt");
+
+    assert_eq!(
+        KeyboardSettings::from_file(&path).unwrap(),
+        KeyboardSettings::default()
+    );
+
+    let mut settings = KeyboardSettings::new();
+    settings.thumb_mode = ThumbMode::Mac;
+    settings.to_file(&path).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "thumb_mode=mac\n");
+    assert_eq!(
+        KeyboardSettings::from_file(&path).unwrap().thumb_mode,
+        ThumbMode::Mac
+    );
+}
@@ -1,24 +1,160 @@
-use std::io::Write;
 use std::{fs, io, path::Path};
 
-#[derive(Debug, Clone, PartialEq)]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::key_token::KeyToken;
+use super::layer::Layer;
+use super::macro_step::MacroStep;
+use super::parse_error::{ParseError, ParseErrorKind};
+use crate::fs_util::write_atomic;
+
+/// Number of layout slots on the keyboard, `layout1.txt` to `layout9.txt`
+pub const LAYOUT_COUNT: usize = 9;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum KeyAction {
     /// simple key remapping: [source]>[target]
-    SimpleRemap { source: String, target: String },
+    #[serde(rename = "remap")]
+    SimpleRemap { source: KeyToken, target: KeyToken },
 
     /// Macro: {trigger}>{actions}
-    Macro { trigger: String, actions: String },
+    Macro {
+        trigger: KeyToken,
+        #[serde(rename = "macro", with = "macro_text")]
+        steps: Vec<MacroStep>,
+    },
+}
+
+/// Macro steps serialised as their SmartSet text, such as `"{-lalt}{tab}{+lalt}"`
+mod macro_text {
+    use serde::{Deserialize, Deserializer, Serializer, de};
+
+    use crate::models::MacroStep;
+
+    pub fn serialize<S: Serializer>(steps: &[MacroStep], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&MacroStep::format_sequence(steps))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<MacroStep>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        MacroStep::parse_sequence(&text).map_err(de::Error::custom)
+    }
+}
+
+impl KeyAction {
+    /// The key that triggers this action
+    pub fn source(&self) -> KeyToken {
+        match self {
+            KeyAction::SimpleRemap { source, .. } => *source,
+            KeyAction::Macro { trigger, .. } => *trigger,
+        }
+    }
+}
+
+/// One line of a layout file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutLine {
+    Mapping(KeyAction),
+    /// A `#` comment, including any leading whitespace
+    Comment(String),
+    /// An empty or whitespace-only line
+    Blank(String),
+    /// A line that could not be parsed, kept verbatim
+    Unknown(String),
+}
+
+impl LayoutLine {
+    fn canonical(&self) -> String {
+        match self {
+            LayoutLine::Mapping(KeyAction::SimpleRemap { source, target }) => {
+                format!("[{}]>[{}]", source, target)
+            }
+            LayoutLine::Mapping(KeyAction::Macro { trigger, steps }) => {
+                format!("{{{}}}>{}", trigger, MacroStep::format_sequence(steps))
+            }
+            LayoutLine::Comment(text) | LayoutLine::Blank(text) | LayoutLine::Unknown(text) => {
+                text.clone()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    line: LayoutLine,
+    /// Original text including its line terminator. `None` once the line has
+    /// been added or edited, in which case it is written in canonical form.
+    raw: Option<String>,
+}
+
+impl Entry {
+    fn new(line: LayoutLine) -> Self {
+        Self { line, raw: None }
+    }
+}
+
+/// The lines an edit replaced, enough to make or revert the edit without
+/// keeping a copy of the whole layout
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LineChange {
+    /// Index of the first replaced line
+    start: usize,
+    before: Vec<Entry>,
+    after: Vec<Entry>,
+    crlf: (bool, bool),
+}
+
+impl LineChange {
+    /// Turn `layout` from the before state into the after state
+    pub(crate) fn apply(&self, layout: &mut KinesisLayout) {
+        Self::splice(layout, self.start, self.before.len(), &self.after);
+        layout.crlf = self.crlf.1;
+    }
+
+    /// Turn `layout` from the after state back into the before state
+    pub(crate) fn revert(&self, layout: &mut KinesisLayout) {
+        Self::splice(layout, self.start, self.after.len(), &self.before);
+        layout.crlf = self.crlf.0;
+    }
+
+    fn splice(layout: &mut KinesisLayout, start: usize, len: usize, entries: &[Entry]) {
+        layout
+            .entries
+            .splice(start..start + len, entries.iter().cloned());
+    }
 }
 
+/// Header comment keys holding the layout's name and description
+const NAME_FIELD: &str = "name";
+const DESCRIPTION_FIELD: &str = "description";
+
+/// A parsed layout file. Comments, blank lines, unknown lines and the
+/// original formatting are kept so that untouched files are written back
+/// byte for byte.
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct KinesisLayout {
-    pub mappings: Vec<KeyAction>,
+    entries: Vec<Entry>,
+    /// Use `\r\n` for newly written lines, matching the original file
+    crlf: bool,
 }
 
 impl KinesisLayout {
     pub fn new() -> Self {
         Self {
-            mappings: Vec::new(),
+            entries: Vec::new(),
+            crlf: false,
+        }
+    }
+
+    /// Build a layout from lines, written in canonical form
+    pub fn from_lines(lines: impl IntoIterator<Item = LayoutLine>) -> Self {
+        Self {
+            entries: lines.into_iter().map(Entry::new).collect(),
+            crlf: false,
         }
     }
 
@@ -33,110 +169,391 @@ impl KinesisLayout {
         }
     }
 
-    /// Write layout to a file
+    /// Parse a layout, keeping every valid line and collecting an error for
+    /// each line that could not be understood. Invalid lines are preserved
+    /// as [`LayoutLine::Unknown`].
+    pub fn parse_lenient(content: &str) -> (Self, Vec<ParseError>) {
+        let mut errors = Vec::new();
+        let layout = Self::parse_with(content, |text, e| {
+            errors.push(e);
+            Ok(LayoutLine::Unknown(text.to_string()))
+        })
+        .expect("lenient parsing never fails");
+
+        (layout, errors)
+    }
+
+    fn parse_with(
+        content: &str,
+        mut on_error: impl FnMut(&str, ParseError) -> Result<LayoutLine, ParseError>,
+    ) -> Result<Self, ParseError> {
+        let mut layout = Self::new();
+        layout.crlf = content.contains("\r\n");
+
+        for (line_num, raw) in content.split_inclusive('\n').enumerate() {
+            let text = raw.strip_suffix('\n').unwrap_or(raw);
+            let text = text.strip_suffix('\r').unwrap_or(text);
+
+            let line = match parse_line(text, line_num + 1) {
+                Ok(line) => line,
+                Err(e) => on_error(text, e)?,
+            };
+            layout.entries.push(Entry {
+                line,
+                raw: Some(raw.to_string()),
+            });
+        }
+
+        Ok(layout)
+    }
+
+    /// Read layout from a file, keeping invalid lines verbatim and returning
+    /// an error for each one
+    pub fn from_file_lenient<P: AsRef<Path>>(path: P) -> io::Result<(Self, Vec<ParseError>)> {
+        match fs::read_to_string(&path) {
+            Ok(content) => Ok(Self::parse_lenient(&content)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok((Self::new(), Vec::new())),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write layout to a file, atomically replacing any existing one
     pub fn to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        let mut file = fs::File::create(path)?;
-        write!(file, "{}", self)
+        write_atomic(path, self.to_string().as_bytes())
+    }
+
+    /// Every line in file order
+    pub fn lines(&self) -> impl Iterator<Item = &LayoutLine> {
+        self.entries.iter().map(|e| &e.line)
+    }
+
+    /// Whether both layouts have the same lines, ignoring how unchanged
+    /// lines were originally formatted. Use this rather than `==`, which
+    /// also compares the original text, to decide whether a layout changed.
+    pub fn same_lines(&self, other: &Self) -> bool {
+        self.lines().eq(other.lines())
+    }
+
+    /// The lines that differ between this layout and `after`, from the
+    /// first differing line to the last
+    pub(crate) fn change_to(&self, after: &Self) -> LineChange {
+        let (old, new) = (&self.entries, &after.entries);
+        let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        LineChange {
+            start: prefix,
+            before: old[prefix..old.len() - suffix].to_vec(),
+            after: new[prefix..new.len() - suffix].to_vec(),
+            crlf: (self.crlf, after.crlf),
+        }
+    }
+
+    /// All remaps and macros in file order
+    pub fn mappings(&self) -> impl Iterator<Item = &KeyAction> {
+        self.lines().filter_map(|line| match line {
+            LayoutLine::Mapping(action) => Some(action),
+            _ => None,
+        })
+    }
+
+    pub fn mapping_count(&self) -> usize {
+        self.mappings().count()
+    }
+
+    /// Remaps and macros whose source key is on the given layer
+    pub fn mappings_on(&self, layer: Layer) -> impl Iterator<Item = &KeyAction> {
+        self.mappings()
+            .filter(move |m| Layer::of(m.source()) == layer)
+    }
+
+    pub fn mapping_count_on(&self, layer: Layer) -> usize {
+        self.mappings_on(layer).count()
     }
 
     /// Add a simple key remap
-    pub fn add_remap(&mut self, source: String, target: String) {
-        self.mappings
-            .push(KeyAction::SimpleRemap { source, target });
+    pub fn add_remap(&mut self, source: KeyToken, target: KeyToken) {
+        self.push_mapping(KeyAction::SimpleRemap { source, target });
     }
 
     /// Add a macro
-    pub fn add_macro(&mut self, trigger: String, actions: String) {
-        self.mappings.push(KeyAction::Macro { trigger, actions });
+    pub fn add_macro(&mut self, trigger: KeyToken, steps: Vec<MacroStep>) {
+        self.push_mapping(KeyAction::Macro { trigger, steps });
+    }
+
+    fn push_mapping(&mut self, action: KeyAction) {
+        self.entries.push(Entry::new(LayoutLine::Mapping(action)));
+    }
+
+    /// Replace the mapping for the action's source key, keeping its position
+    /// in the file. Any further mappings for the same key are removed. The
+    /// mapping is appended if the key had none, and an identical mapping
+    /// keeps its original text.
+    pub fn set_mapping(&mut self, action: KeyAction) {
+        let source = action.source();
+        let mut pending = Some(action);
+
+        self.entries.retain_mut(|entry| match &entry.line {
+            LayoutLine::Mapping(existing) if existing.source() == source => match pending.take() {
+                Some(action) if *existing == action => true,
+                Some(action) => {
+                    *entry = Entry::new(LayoutLine::Mapping(action));
+                    true
+                }
+                None => false,
+            },
+            _ => true,
+        });
+
+        if let Some(action) = pending {
+            self.push_mapping(action);
+        }
+    }
+
+    /// Make `source` send what `other` sends when not remapped, replacing
+    /// any mapping `source` had. Both are positions on the same layer.
+    pub fn remap_to_key(&mut self, source: KeyToken, other: KeyToken) {
+        let target = Layer::of(other).default_action(other);
+        self.set_mapping(KeyAction::SimpleRemap { source, target });
+    }
+
+    /// Swap two keys with `[a]>[b]` and `[b]>[a]`, replacing any mappings
+    /// they had
+    pub fn swap_keys(&mut self, a: KeyToken, b: KeyToken) {
+        if a != b {
+            self.remap_to_key(a, b);
+            self.remap_to_key(b, a);
+        }
     }
 
     /// Find all remaps for a specific source key
-    pub fn find_by_source(&self, source: &str) -> Vec<&KeyAction> {
-        self.mappings
+    pub fn find_by_source(&self, source: KeyToken) -> Vec<&KeyAction> {
+        self.mappings().filter(|m| m.source() == source).collect()
+    }
+
+    /// Remove every remap and macro, keeping comments and other lines
+    pub fn clear_mappings(&mut self) {
+        self.entries
+            .retain(|entry| !matches!(entry.line, LayoutLine::Mapping(_)));
+    }
+
+    /// Name from a `# name: ...` line in the leading comments
+    pub fn name(&self) -> Option<&str> {
+        self.header_field(NAME_FIELD)
+    }
+
+    /// Description from a `# description: ...` line in the leading comments
+    pub fn description(&self) -> Option<&str> {
+        self.header_field(DESCRIPTION_FIELD)
+    }
+
+    /// Set the name kept in the header comments; an empty name removes it
+    pub fn set_name(&mut self, name: &str) {
+        self.set_header_field(NAME_FIELD, name);
+    }
+
+    /// Set the description kept in the header comments; an empty
+    /// description removes it
+    pub fn set_description(&mut self, description: &str) {
+        self.set_header_field(DESCRIPTION_FIELD, description);
+    }
+
+    /// Indices of the comment lines at the top of the file
+    fn header(&self) -> std::ops::Range<usize> {
+        let len = self
+            .entries
             .iter()
-            .filter(|m| match m {
-                KeyAction::SimpleRemap { source: s, .. } => s == source,
-                KeyAction::Macro { trigger, .. } => trigger == source,
-            })
-            .collect()
+            .take_while(|entry| matches!(entry.line, LayoutLine::Comment(_)))
+            .count();
+        0..len
+    }
+
+    /// Index and value of a `# key: value` line in the header
+    fn find_header_field(&self, key: &str) -> Option<(usize, &str)> {
+        self.header().find_map(|idx| match &self.entries[idx].line {
+            LayoutLine::Comment(text) => header_value(text, key).map(|value| (idx, value)),
+            _ => None,
+        })
+    }
+
+    fn header_field(&self, key: &str) -> Option<&str> {
+        self.find_header_field(key)
+            .map(|(_, value)| value)
+            .filter(|value| !value.is_empty())
+    }
+
+    fn set_header_field(&mut self, key: &str, value: &str) {
+        // Keep the field on one line so it stays a comment
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        let existing = self.find_header_field(key).map(|(idx, _)| idx);
+
+        if value.is_empty() {
+            if let Some(idx) = existing {
+                self.entries.remove(idx);
+            }
+            return;
+        }
+
+        let line = Entry::new(LayoutLine::Comment(format!("# {}: {}", key, value)));
+        match existing {
+            Some(idx) => self.entries[idx] = line,
+            None => {
+                // After any other header fields, so name and description
+                // stay together at the top
+                let idx = self
+                    .header()
+                    .rfind(|&idx| match &self.entries[idx].line {
+                        LayoutLine::Comment(text) => [NAME_FIELD, DESCRIPTION_FIELD]
+                            .iter()
+                            .any(|field| header_value(text, field).is_some()),
+                        _ => false,
+                    })
+                    .map_or(0, |idx| idx + 1);
+                self.entries.insert(idx, line);
+            }
+        }
     }
 
     /// Remove all mappings for a specific source key
-    pub fn remove_by_source(&mut self, source: &str) {
-        self.mappings.retain(|m| match m {
-            KeyAction::SimpleRemap { source: s, .. } => s != source,
-            KeyAction::Macro { trigger, .. } => trigger != source,
+    pub fn remove_by_source(&mut self, source: KeyToken) {
+        self.entries.retain(|entry| match &entry.line {
+            LayoutLine::Mapping(m) => m.source() != source,
+            _ => true,
         });
     }
 }
 
-impl std::str::FromStr for KinesisLayout {
-    type Err = String;
+/// The value of a `# key: value` comment, matching the key case-insensitively
+fn header_value<'a>(comment: &'a str, key: &str) -> Option<&'a str> {
+    let (name, value) = comment.trim().strip_prefix('#')?.split_once(':')?;
+    name.trim()
+        .eq_ignore_ascii_case(key)
+        .then_some(value.trim())
+}
 
-    fn from_str(content: &str) -> Result<Self, Self::Err> {
-        let mut layout = Self::new();
+/// Parse a single line, without its terminator
+fn parse_line(line: &str, line_num: usize) -> Result<LayoutLine, ParseError> {
+    let start = line.len() - line.trim_start().len();
+    let trimmed = line.trim();
 
-        for (line_num, line) in content.lines().enumerate() {
-            let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return Ok(LayoutLine::Blank(line.to_string()));
+    }
+    if trimmed.starts_with('#') {
+        return Ok(LayoutLine::Comment(line.to_string()));
+    }
 
-            if trimmed.is_empty() || trimmed.starts_with('#') {
-                continue;
-            }
+    let error = |offset: usize, text: &str, kind: ParseErrorKind| ParseError {
+        line: line_num,
+        span: offset..offset + text.len(),
+        text: text.to_string(),
+        kind,
+    };
 
-            if !trimmed.contains('>') {
-                return Err(format!("Line {}: Missing '>' operator", line_num + 1));
-            }
+    let Some(op) = trimmed.find('>') else {
+        return Err(error(start, trimmed, ParseErrorKind::MissingOperator));
+    };
 
-            let parts: Vec<&str> = trimmed.splitn(2, '>').collect();
-            if parts.len() != 2 {
-                return Err(format!("Line {}: Invalid format", line_num + 1));
-            }
+    // Locate both halves, trimmed, along with their offsets within the line
+    let (source_raw, target_raw) = (&trimmed[..op], &trimmed[op + 1..]);
+    let source = source_raw.trim();
+    let source_start = start + (source_raw.len() - source_raw.trim_start().len());
+    let target = target_raw.trim();
+    let target_start = start + op + 1 + (target_raw.len() - target_raw.trim_start().len());
 
-            let source = parts[0].trim();
-            let target = parts[1].trim();
+    let parse_token = |token: &str, offset: usize| {
+        token
+            .parse::<KeyToken>()
+            .map_err(|e| error(offset, token, ParseErrorKind::UnknownToken(e.0)))
+    };
 
-            if source.starts_with('[') && source.ends_with(']') {
-                // Simple remap
-                if !target.starts_with('[') || !target.ends_with(']') {
-                    return Err(format!(
-                        "Line {}: Simple remap target must be in square brackets",
-                        line_num + 1
-                    ));
-                }
-                layout.mappings.push(KeyAction::SimpleRemap {
-                    source: source[1..source.len() - 1].to_string(),
-                    target: target[1..target.len() - 1].to_string(),
-                });
-            } else if source.starts_with('{') && source.ends_with('}') {
-                // Macro: {key}>{actions}
-                // Target should NOT be a simple remap (no square brackets)
-                if target.starts_with('[') && target.ends_with(']') {
-                    return Err(format!(
-                        "Line {}: Macro target cannot be a simple remap in square brackets",
-                        line_num + 1
-                    ));
-                }
-                layout.mappings.push(KeyAction::Macro {
-                    trigger: source[1..source.len() - 1].to_string(),
-                    actions: target.to_string(),
-                });
-            } else {
-                return Err(format!("Line {}: Invalid key notation", line_num + 1));
-            }
+    let is_wrapped = |s: &str, open: char, close: char| {
+        s.len() >= 2 && s.starts_with(open) && s.ends_with(close)
+    };
+
+    if is_wrapped(source, '[', ']') {
+        // Simple remap
+        if !is_wrapped(target, '[', ']') {
+            return Err(error(
+                target_start,
+                target,
+                ParseErrorKind::RemapTargetNotBracketed,
+            ));
         }
-        Ok(layout)
+        Ok(LayoutLine::Mapping(KeyAction::SimpleRemap {
+            source: parse_token(&source[1..source.len() - 1], source_start + 1)?,
+            target: parse_token(&target[1..target.len() - 1], target_start + 1)?,
+        }))
+    } else if is_wrapped(source, '{', '}') {
+        // Macro: {key}>{actions}
+        // Target should NOT be a simple remap (no square brackets)
+        if is_wrapped(target, '[', ']') {
+            return Err(error(
+                target_start,
+                target,
+                ParseErrorKind::MacroTargetBracketed,
+            ));
+        }
+        let trigger = parse_token(&source[1..source.len() - 1], source_start + 1)?;
+        let steps = MacroStep::parse_sequence(target).map_err(|e| {
+            let offset = target_start + e.offset;
+            error(
+                offset,
+                &target[e.offset..e.offset + e.len],
+                ParseErrorKind::InvalidMacro(e.kind),
+            )
+        })?;
+        Ok(LayoutLine::Mapping(KeyAction::Macro { trigger, steps }))
+    } else {
+        Err(error(source_start, source, ParseErrorKind::InvalidNotation))
+    }
+}
+
+impl std::str::FromStr for KinesisLayout {
+    type Err = ParseError;
+
+    /// Strict parse that fails on the first invalid line
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        Self::parse_with(content, |_, e| Err(e))
+    }
+}
+
+/// Serialised as its list of lines; original formatting is not kept
+impl Serialize for KinesisLayout {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.lines())
+    }
+}
+
+impl<'de> Deserialize<'de> for KinesisLayout {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<LayoutLine>::deserialize(deserializer).map(Self::from_lines)
     }
 }
 
 impl std::fmt::Display for KinesisLayout {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for mapping in &self.mappings {
-            match mapping {
-                KeyAction::SimpleRemap { source, target } => {
-                    writeln!(f, "[{}]>[{}]", source, target)?;
+        let eol = if self.crlf { "\r\n" } else { "\n" };
+        let mut at_line_start = true;
+
+        for entry in &self.entries {
+            match &entry.raw {
+                Some(raw) => {
+                    write!(f, "{}", raw)?;
+                    at_line_start = raw.ends_with('\n');
                 }
-                KeyAction::Macro { trigger, actions } => {
-                    writeln!(f, "{{{}}}>{}", trigger, actions)?;
+                None => {
+                    // The original last line may have had no terminator
+                    if !at_line_start {
+                        write!(f, "{}", eol)?;
+                    }
+                    write!(f, "{}{}", entry.line.canonical(), eol)?;
+                    at_line_start = true;
                 }
             }
         }
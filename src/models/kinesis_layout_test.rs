@@ -1,16 +1,22 @@
-use super::kinesis_layout::{KeyAction, KinesisLayout};
+use proptest::prelude::*;
+
+use super::key_token::KeyToken;
+use super::kinesis_layout::{KeyAction, KinesisLayout, LayoutLine};
+use super::layer::Layer;
+use super::macro_step::{MacroErrorKind, MacroStep};
+use super::parse_error::{ParseError, ParseErrorKind};
 
 #[test]
 fn test_parse_simple_remap() {
     let content = "[2]>[7]\n[1]>[8]";
     let layout: KinesisLayout = content.parse().unwrap();
 
-    assert_eq!(layout.mappings.len(), 2);
+    assert_eq!(layout.mapping_count(), 2);
     assert_eq!(
-        layout.mappings[0],
+        *layout.mappings().next().unwrap(),
         KeyAction::SimpleRemap {
-            source: "2".to_string(),
-            target: "7".to_string()
+            source: KeyToken::Digit2,
+            target: KeyToken::Digit7
         }
     );
 }
@@ -20,11 +26,19 @@ fn test_parse_macro() {
     let content = "{hyphen}>{speed5}{-lalt}{tab}{+lalt}";
     let layout: KinesisLayout = content.parse().unwrap();
 
-    assert_eq!(layout.mappings.len(), 1);
-    match &layout.mappings[0] {
-        KeyAction::Macro { trigger, actions } => {
-            assert_eq!(trigger, "hyphen");
-            assert_eq!(actions, "{speed5}{-lalt}{tab}{+lalt}");
+    assert_eq!(layout.mapping_count(), 1);
+    match layout.mappings().next().unwrap() {
+        KeyAction::Macro { trigger, steps } => {
+            assert_eq!(*trigger, KeyToken::Hyphen);
+            assert_eq!(
+                *steps,
+                vec![
+                    MacroStep::Speed(5),
+                    MacroStep::Press(KeyToken::LAlt),
+                    MacroStep::Tap(KeyToken::Tab),
+                    MacroStep::Release(KeyToken::LAlt),
+                ]
+            );
         }
         _ => panic!("Expected macro"),
     }
@@ -33,27 +47,27 @@ fn test_parse_macro() {
 #[test]
 fn test_display_trait() {
     let mut layout = KinesisLayout::new();
-    layout.add_remap("a".to_string(), "b".to_string());
-    layout.add_macro("test".to_string(), "{speed5}".to_string());
+    layout.add_remap(KeyToken::A, KeyToken::B);
+    layout.add_macro(KeyToken::T, vec![MacroStep::Speed(5)]);
 
     let output = format!("{}", layout);
 
     assert!(output.contains("[a]>[b]"));
-    assert!(output.contains("{test}>{speed5}"));
+    assert!(output.contains("{t}>{speed5}"));
 }
 
 #[test]
 fn test_empty_layout() {
     let content = "";
     let layout: KinesisLayout = content.parse().unwrap();
-    assert_eq!(layout.mappings.len(), 0);
+    assert_eq!(layout.mapping_count(), 0);
 }
 
 #[test]
 fn test_comments() {
     let content = "# This is a comment\n[a]>[b]\n# Another comment";
     let layout: KinesisLayout = content.parse().unwrap();
-    assert_eq!(layout.mappings.len(), 1);
+    assert_eq!(layout.mapping_count(), 1);
 }
 
 #[test]
@@ -66,22 +80,22 @@ fn test_error_missing_operator() {
 #[test]
 fn test_find_by_source() {
     let mut layout = KinesisLayout::new();
-    layout.add_remap("a".to_string(), "b".to_string());
-    layout.add_remap("c".to_string(), "d".to_string());
+    layout.add_remap(KeyToken::A, KeyToken::B);
+    layout.add_remap(KeyToken::C, KeyToken::D);
 
-    let found = layout.find_by_source("a");
+    let found = layout.find_by_source(KeyToken::A);
     assert_eq!(found.len(), 1);
 }
 
 #[test]
 fn test_remove_by_source() {
     let mut layout = KinesisLayout::new();
-    layout.add_remap("a".to_string(), "b".to_string());
-    layout.add_remap("c".to_string(), "d".to_string());
+    layout.add_remap(KeyToken::A, KeyToken::B);
+    layout.add_remap(KeyToken::C, KeyToken::D);
 
-    layout.remove_by_source("a");
-    assert_eq!(layout.mappings.len(), 1);
-    assert_eq!(layout.find_by_source("a").len(), 0);
+    layout.remove_by_source(KeyToken::A);
+    assert_eq!(layout.mapping_count(), 1);
+    assert_eq!(layout.find_by_source(KeyToken::A).len(), 0);
 }
 
 #[test]
@@ -128,3 +142,357 @@ fn test_mixed_bracket_types() {
     let content2 = "{key}>[target]";
     assert!(content2.parse::<KinesisLayout>().is_err());
 }
+
+#[test]
+fn test_parse_keypad_layer_remap() {
+    let content = "[kp-j]>[kp4]";
+    let layout: KinesisLayout = content.parse().unwrap();
+
+    assert_eq!(
+        *layout.mappings().next().unwrap(),
+        KeyAction::SimpleRemap {
+            source: KeyToken::J.on_keypad(),
+            target: KeyToken::Kp4
+        }
+    );
+    assert_eq!(layout.to_string(), "[kp-j]>[kp4]");
+}
+
+#[test]
+fn test_error_unknown_token() {
+    let content = "[a]>[notakey]";
+    let result = content.parse::<KinesisLayout>();
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_error_location() {
+    let content = "[a]>[b]\n  [c]>[notakey]\n";
+    let err = content.parse::<KinesisLayout>().unwrap_err();
+
+    assert_eq!(
+        err,
+        ParseError {
+            line: 2,
+            span: 7..14,
+            text: "notakey".to_string(),
+            kind: ParseErrorKind::UnknownToken("notakey".to_string()),
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "line 2, column 8: unknown key token 'notakey'"
+    );
+}
+
+#[test]
+fn test_error_kinds() {
+    let kind = |content: &str| content.parse::<KinesisLayout>().unwrap_err().kind;
+
+    assert_eq!(kind("[a][b]"), ParseErrorKind::MissingOperator);
+    assert_eq!(kind("a>[b]"), ParseErrorKind::InvalidNotation);
+    assert_eq!(kind("[a]>b"), ParseErrorKind::RemapTargetNotBracketed);
+    assert_eq!(kind("{a}>[b]"), ParseErrorKind::MacroTargetBracketed);
+}
+
+#[test]
+fn test_error_span_covers_target() {
+    let err = "[a] > {b}".parse::<KinesisLayout>().unwrap_err();
+    assert_eq!(err.span, 6..9);
+    assert_eq!(err.text, "{b}");
+}
+
+#[test]
+fn test_parse_lenient_keeps_valid_lines() {
+    let content = "[a]>[b]\nnot a mapping\n[c]>[bogus]\n{d}>{speed5}\n";
+    let (layout, errors) = KinesisLayout::parse_lenient(content);
+
+    assert_eq!(layout.mapping_count(), 2);
+    assert_eq!(layout.mappings().nth(1).unwrap().source(), KeyToken::D);
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].line, 2);
+    assert_eq!(errors[0].kind, ParseErrorKind::MissingOperator);
+    assert_eq!(errors[1].line, 3);
+    assert_eq!(
+        errors[1].kind,
+        ParseErrorKind::UnknownToken("bogus".to_string())
+    );
+}
+
+#[test]
+fn test_round_trip_preserves_formatting() {
+    let content = "# Layout for work\n\n  [caps]>[esc]   \n[A] > [B]\n{hyphen}>{speed5}{-lalt}{tab}{+lalt}\n   \n# trailing note";
+    let layout: KinesisLayout = content.parse().unwrap();
+
+    assert_eq!(layout.to_string(), content);
+    assert_eq!(layout.mapping_count(), 3);
+}
+
+#[test]
+fn test_round_trip_preserves_crlf() {
+    let content = "# note\r\n[a]>[b]\r\n";
+    let mut layout: KinesisLayout = content.parse().unwrap();
+    assert_eq!(layout.to_string(), content);
+
+    layout.add_remap(KeyToken::C, KeyToken::D);
+    assert_eq!(layout.to_string(), "# note\r\n[a]>[b]\r\n[c]>[d]\r\n");
+}
+
+#[test]
+fn test_lenient_keeps_unknown_lines() {
+    let content = "[a]>[b]\nsomething new\n[c]>[futurekey]\n";
+    let (layout, errors) = KinesisLayout::parse_lenient(content);
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!(layout.to_string(), content);
+    assert_eq!(
+        layout.lines().nth(1),
+        Some(&LayoutLine::Unknown("something new".to_string()))
+    );
+}
+
+#[test]
+fn test_append_after_unterminated_line() {
+    let mut layout: KinesisLayout = "[a]>[b]".parse().unwrap();
+    layout.add_remap(KeyToken::C, KeyToken::D);
+
+    assert_eq!(layout.to_string(), "[a]>[b]\n[c]>[d]\n");
+}
+
+#[test]
+fn test_edits_keep_surrounding_lines() {
+    let content = "# swap\n[a]>[b]\n# escape\n[caps]>[esc]\n";
+    let mut layout: KinesisLayout = content.parse().unwrap();
+
+    layout.set_mapping(KeyAction::SimpleRemap {
+        source: KeyToken::A,
+        target: KeyToken::Z,
+    });
+    layout.remove_by_source(KeyToken::Caps);
+
+    assert_eq!(layout.to_string(), "# swap\n[a]>[z]\n# escape\n");
+}
+
+#[test]
+fn test_set_mapping_removes_duplicates() {
+    let mut layout: KinesisLayout = "[a]>[b]\n[c]>[d]\n[a]>[e]\n".parse().unwrap();
+
+    layout.set_mapping(KeyAction::SimpleRemap {
+        source: KeyToken::A,
+        target: KeyToken::F,
+    });
+
+    assert_eq!(layout.to_string(), "[a]>[f]\n[c]>[d]\n");
+}
+
+#[test]
+fn test_set_mapping_appends_new_source() {
+    let mut layout: KinesisLayout = "[a]>[b]\n".parse().unwrap();
+
+    layout.set_mapping(KeyAction::Macro {
+        trigger: KeyToken::C,
+        steps: vec![MacroStep::Speed(5)],
+    });
+
+    assert_eq!(layout.to_string(), "[a]>[b]\n{c}>{speed5}\n");
+}
+
+#[test]
+fn test_set_mapping_keeps_identical_mapping() {
+    let content = "[a] > [b]\r\n";
+    let mut layout: KinesisLayout = content.parse().unwrap();
+
+    layout.set_mapping(KeyAction::SimpleRemap {
+        source: KeyToken::A,
+        target: KeyToken::B,
+    });
+
+    assert_eq!(layout, content.parse().unwrap());
+    assert_eq!(layout.to_string(), content);
+}
+
+#[test]
+fn test_same_lines_ignores_formatting() {
+    let saved: KinesisLayout = "[a] > [b]\n".parse().unwrap();
+    let mut layout = saved.clone();
+
+    layout.remap_to_key(KeyToken::A, KeyToken::C);
+    assert!(!layout.same_lines(&saved));
+
+    // Changing the remap back by hand loses the original spacing, but the
+    // layout has not changed
+    layout.remap_to_key(KeyToken::A, KeyToken::B);
+    assert_ne!(layout, saved);
+    assert!(layout.same_lines(&saved));
+}
+
+#[test]
+fn test_swap_keys() {
+    let mut layout = KinesisLayout::new();
+    layout.swap_keys(KeyToken::A, KeyToken::B);
+    assert_eq!(layout.to_string(), "[a]>[b]\n[b]>[a]\n");
+
+    // Same key is left alone
+    let mut layout = KinesisLayout::new();
+    layout.swap_keys(KeyToken::A, KeyToken::A);
+    assert_eq!(layout.mapping_count(), 0);
+}
+
+#[test]
+fn test_swap_keys_replaces_existing_mappings() {
+    let mut layout: KinesisLayout = "[b]>[c]\n{a}>{x}\n[x]>[y]\n".parse().unwrap();
+    layout.swap_keys(KeyToken::A, KeyToken::B);
+    assert_eq!(layout.to_string(), "[b]>[a]\n[a]>[b]\n[x]>[y]\n");
+}
+
+#[test]
+fn test_swap_keys_on_keypad_layer() {
+    let mut layout = KinesisLayout::new();
+    layout.swap_keys(KeyToken::J.on_keypad(), KeyToken::K.on_keypad());
+    assert_eq!(layout.to_string(), "[kp-j]>[kp5]\n[kp-k]>[kp4]\n");
+}
+
+#[test]
+fn test_remap_to_key() {
+    let mut layout: KinesisLayout = "[caps]>[lctrl]\n".parse().unwrap();
+    layout.remap_to_key(KeyToken::Caps, KeyToken::Escape);
+    assert_eq!(layout.to_string(), "[caps]>[esc]\n");
+}
+
+/// A line that may or may not be valid layout syntax
+fn arbitrary_line() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just(String::new()),
+        "[ \t]{1,4}",
+        "[ \t]{0,2}#[ -~]{0,20}",
+        "[ ]{0,2}\\[(a|b|lalt|kp-j|hyphen|f12)\\][ ]{0,2}>[ ]{0,2}\\[(c|tab|bspace|kp4)\\][ ]{0,2}",
+        "\\{(hyphen|t|f1)\\}>(\\{(speed5|-lalt|tab|\\+lalt|d125)\\}){0,4}",
+        "[ -~]{0,30}",
+    ]
+}
+
+/// A whole file built from arbitrary lines and line endings, with or
+/// without a final terminator
+fn arbitrary_file() -> impl Strategy<Value = String> {
+    (
+        prop::collection::vec(
+            (arbitrary_line(), prop_oneof![Just("\n"), Just("\r\n")]),
+            0..12,
+        ),
+        any::<bool>(),
+    )
+        .prop_map(|(lines, trailing)| {
+            let mut content: String = lines
+                .into_iter()
+                .map(|(line, eol)| format!("{}{}", line, eol))
+                .collect();
+            if !trailing && content.ends_with('\n') {
+                content.pop();
+                if content.ends_with('\r') {
+                    content.pop();
+                }
+            }
+            content
+        })
+}
+
+proptest! {
+    #[test]
+    fn prop_lenient_round_trip_is_byte_identical(content in arbitrary_file()) {
+        let (layout, _) = KinesisLayout::parse_lenient(&content);
+        prop_assert_eq!(layout.to_string(), content);
+    }
+
+    #[test]
+    fn prop_strict_round_trip_is_byte_identical(content in arbitrary_file()) {
+        if let Ok(layout) = content.parse::<KinesisLayout>() {
+            prop_assert_eq!(layout.to_string(), content);
+        }
+    }
+
+    #[test]
+    fn prop_reparse_after_edit_keeps_mappings(content in arbitrary_file()) {
+        let (mut layout, _) = KinesisLayout::parse_lenient(&content);
+        layout.add_remap(KeyToken::Q, KeyToken::W);
+
+        let (reparsed, _) = KinesisLayout::parse_lenient(&layout.to_string());
+        prop_assert_eq!(
+            reparsed.mappings().collect::<Vec<_>>(),
+            layout.mappings().collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn test_error_invalid_macro() {
+    let err = "{a}>{speed5}{tab".parse::<KinesisLayout>().unwrap_err();
+
+    assert_eq!(
+        err.kind,
+        ParseErrorKind::InvalidMacro(MacroErrorKind::UnclosedBrace)
+    );
+    assert_eq!(err.span, 12..16);
+    assert_eq!(err.text, "{tab");
+}
+
+#[test]
+fn test_mappings_by_layer() {
+    let content = "[a]>[b]\n[kp-j]>[kp4]\n{kp-k}>hi\n[c]>[d]\n";
+    let layout: KinesisLayout = content.parse().unwrap();
+
+    assert_eq!(layout.mapping_count_on(Layer::Base), 2);
+    assert_eq!(layout.mapping_count_on(Layer::Keypad), 2);
+    let keypad_sources: Vec<KeyToken> = layout
+        .mappings_on(Layer::Keypad)
+        .map(|m| m.source())
+        .collect();
+    assert_eq!(
+        keypad_sources,
+        vec![KeyToken::J.on_keypad(), KeyToken::K.on_keypad()]
+    );
+}
+
+#[test]
+fn test_clear_mappings_keeps_other_lines() {
+    let mut layout: KinesisLayout = "# top\n[a]>[b]\n\n{c}>hi\n".parse().unwrap();
+    layout.clear_mappings();
+
+    assert_eq!(layout.mapping_count(), 0);
+    assert_eq!(layout.to_string(), "# top\n\n");
+}
+
+#[test]
+fn test_name_and_description_header() {
+    let mut layout: KinesisLayout = "# Name: Gaming\n# notes\n# description:  WASD on the keypad \n[a]>[b]\n# name: not a header\n"
+        .parse()
+        .unwrap();
+
+    assert_eq!(layout.name(), Some("Gaming"));
+    assert_eq!(layout.description(), Some("WASD on the keypad"));
+
+    layout.set_name("Gaming\nv2");
+    assert_eq!(layout.name(), Some("Gaming v2"));
+    layout.set_description("");
+    assert_eq!(layout.description(), None);
+    assert_eq!(
+        layout.to_string(),
+        "# name: Gaming v2\n# notes\n[a]>[b]\n# name: not a header\n"
+    );
+}
+
+#[test]
+fn test_set_name_on_new_layout() {
+    let mut layout: KinesisLayout = "# keep me\n\n[a]>[b]\n".parse().unwrap();
+    layout.set_description("Writing");
+    layout.set_name("Prose");
+
+    assert_eq!(
+        layout.to_string(),
+        "# description: Writing\n# name: Prose\n# keep me\n\n[a]>[b]\n"
+    );
+    assert_eq!(layout.mapping_count(), 1);
+
+    layout.set_name("");
+    assert_eq!(layout.name(), None);
+    assert_eq!(layout.description(), Some("Writing"));
+}
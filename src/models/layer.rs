@@ -0,0 +1,85 @@
+use std::fmt;
+
+use super::key_token::KeyToken;
+
+/// Keys the keypad layer produces out of the box, by physical key
+const KEYPAD_DEFAULTS: &[(KeyToken, KeyToken)] = &[
+    (KeyToken::Digit7, KeyToken::NumLock),
+    (KeyToken::Digit8, KeyToken::KpEquals),
+    (KeyToken::Digit9, KeyToken::KpDivide),
+    (KeyToken::Digit0, KeyToken::KpMultiply),
+    (KeyToken::U, KeyToken::Kp7),
+    (KeyToken::I, KeyToken::Kp8),
+    (KeyToken::O, KeyToken::Kp9),
+    (KeyToken::P, KeyToken::KpMinus),
+    (KeyToken::J, KeyToken::Kp4),
+    (KeyToken::K, KeyToken::Kp5),
+    (KeyToken::L, KeyToken::Kp6),
+    (KeyToken::Semicolon, KeyToken::KpPlus),
+    (KeyToken::M, KeyToken::Kp1),
+    (KeyToken::Comma, KeyToken::Kp2),
+    (KeyToken::Period, KeyToken::Kp3),
+    (KeyToken::Slash, KeyToken::KpEnter),
+    (KeyToken::OpenBracket, KeyToken::KpPeriod),
+    (KeyToken::CloseBracket, KeyToken::KpEnter),
+    (KeyToken::Space, KeyToken::Kp0),
+];
+
+/// The two layers of a layout. Keypad-layer remaps use `kp-` prefixed
+/// source tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Layer {
+    #[default]
+    Base,
+    Keypad,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 2] = [Layer::Base, Layer::Keypad];
+
+    /// The layer a remap source belongs to
+    pub fn of(token: KeyToken) -> Layer {
+        if token.is_keypad_position() {
+            Layer::Keypad
+        } else {
+            Layer::Base
+        }
+    }
+
+    /// The source token for a physical key on this layer (`j` becomes
+    /// `kp-j` on the keypad layer)
+    pub fn position(self, key: KeyToken) -> KeyToken {
+        match self {
+            Layer::Base => key.base(),
+            Layer::Keypad => key.on_keypad(),
+        }
+    }
+
+    /// What a physical key produces on this layer when it is not remapped
+    pub fn default_action(self, key: KeyToken) -> KeyToken {
+        let key = key.base();
+        match self {
+            Layer::Base => key,
+            Layer::Keypad => KEYPAD_DEFAULTS
+                .iter()
+                .find(|(physical, _)| *physical == key)
+                .map_or(key, |(_, action)| *action),
+        }
+    }
+
+    pub fn toggled(self) -> Layer {
+        match self {
+            Layer::Base => Layer::Keypad,
+            Layer::Keypad => Layer::Base,
+        }
+    }
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layer::Base => write!(f, "Base"),
+            Layer::Keypad => write!(f, "Keypad"),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use super::key_token::KeyToken;
+use super::layer::Layer;
+
+#[test]
+fn test_layer_of_source() {
+    assert_eq!(Layer::of(KeyToken::J), Layer::Base);
+    assert_eq!(Layer::of(KeyToken::J.on_keypad()), Layer::Keypad);
+}
+
+#[test]
+fn test_position_on_layer() {
+    assert_eq!(Layer::Base.position(KeyToken::J), KeyToken::J);
+    assert_eq!(Layer::Keypad.position(KeyToken::J), KeyToken::J.on_keypad());
+    assert_eq!(Layer::Base.position(KeyToken::J.on_keypad()), KeyToken::J);
+    assert_eq!(
+        Layer::Keypad.position(KeyToken::J.on_keypad()),
+        KeyToken::J.on_keypad()
+    );
+}
+
+#[test]
+fn test_default_action() {
+    assert_eq!(Layer::Base.default_action(KeyToken::J), KeyToken::J);
+    assert_eq!(Layer::Keypad.default_action(KeyToken::J), KeyToken::Kp4);
+    assert_eq!(Layer::Keypad.default_action(KeyToken::Space), KeyToken::Kp0);
+    // Keys without a keypad function keep their base action
+    assert_eq!(Layer::Keypad.default_action(KeyToken::Q), KeyToken::Q);
+}
+
+#[test]
+fn test_toggled() {
+    assert_eq!(Layer::Base.toggled(), Layer::Keypad);
+    assert_eq!(Layer::Keypad.toggled(), Layer::Base);
+}
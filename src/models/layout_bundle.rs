@@ -0,0 +1,107 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::kinesis_layout::{KinesisLayout, LAYOUT_COUNT};
+use crate::fs_util::write_atomic;
+
+/// Version of the bundle file format written by this build
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// File extension for bundles
+pub const BUNDLE_EXTENSION: &str = "json";
+
+/// Who made a bundle and what it is for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleMetadata {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: String,
+    pub keyboard_model: String,
+    /// Version of the configurator that wrote the bundle
+    pub app_version: String,
+}
+
+impl Default for BundleMetadata {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            description: String::new(),
+            author: String::new(),
+            keyboard_model: "Kinesis Advantage 360".to_string(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// All nine layouts of a keyboard in one portable file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutBundle {
+    pub format_version: u32,
+    pub metadata: BundleMetadata,
+    layouts: Vec<KinesisLayout>,
+}
+
+#[derive(Debug, Error)]
+pub enum BundleError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("invalid bundle: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("bundle has {0} layouts, expected {LAYOUT_COUNT}")]
+    WrongLayoutCount(usize),
+
+    #[error("bundle format version {0} is newer than this app supports")]
+    UnsupportedVersion(u32),
+}
+
+impl LayoutBundle {
+    pub fn new(metadata: BundleMetadata, layouts: &[KinesisLayout; LAYOUT_COUNT]) -> Self {
+        Self {
+            format_version: BUNDLE_FORMAT_VERSION,
+            metadata,
+            layouts: layouts.to_vec(),
+        }
+    }
+
+    pub fn layouts(&self) -> &[KinesisLayout] {
+        &self.layouts
+    }
+
+    pub fn into_layouts(self) -> [KinesisLayout; LAYOUT_COUNT] {
+        self.layouts
+            .try_into()
+            .expect("layout count is checked when a bundle is read")
+    }
+
+    pub fn to_json(&self) -> Result<String, BundleError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, BundleError> {
+        let bundle: Self = serde_json::from_str(json)?;
+        if bundle.format_version > BUNDLE_FORMAT_VERSION {
+            return Err(BundleError::UnsupportedVersion(bundle.format_version));
+        }
+        if bundle.layouts.len() != LAYOUT_COUNT {
+            return Err(BundleError::WrongLayoutCount(bundle.layouts.len()));
+        }
+        Ok(bundle)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), BundleError> {
+        let mut json = self.to_json()?;
+        json.push('\n');
+        write_atomic(path, json.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, BundleError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
@@ -0,0 +1,97 @@
+use serde_json::json;
+
+use super::key_token::KeyToken;
+use super::kinesis_layout::{KeyAction, KinesisLayout};
+use super::layout_bundle::{BUNDLE_FORMAT_VERSION, BundleError, BundleMetadata, LayoutBundle};
+
+fn layouts() -> [KinesisLayout; 9] {
+    let mut layouts: [KinesisLayout; 9] = Default::default();
+    layouts[0] = "# caps to esc\n[caps]>[esc]\n\n{f1}>{-lalt}{tab}{+lalt}\n"
+        .parse()
+        .unwrap();
+    layouts[8] = "[kp-j]>[kp4]\n".parse().unwrap();
+    layouts
+}
+
+fn metadata() -> BundleMetadata {
+    BundleMetadata {
+        name: "Team default".to_string(),
+        description: "Caps is escape".to_string(),
+        author: "Platform team".to_string(),
+        ..BundleMetadata::default()
+    }
+}
+
+#[test]
+fn test_key_action_json() {
+    let remap = KeyAction::SimpleRemap {
+        source: KeyToken::J.on_keypad(),
+        target: KeyToken::Kp4,
+    };
+    assert_eq!(
+        serde_json::to_value(&remap).unwrap(),
+        json!({"type": "remap", "source": "kp-j", "target": "kp4"})
+    );
+
+    let layout: KinesisLayout = "{f1}>{speed5}hi\n".parse().unwrap();
+    assert_eq!(
+        serde_json::to_value(&layout).unwrap(),
+        json!([{"mapping": {"type": "macro", "trigger": "f1", "macro": "{speed5}hi"}}])
+    );
+}
+
+#[test]
+fn test_bundle_round_trip() {
+    let bundle = LayoutBundle::new(metadata(), &layouts());
+    let json = bundle.to_json().unwrap();
+    let read = LayoutBundle::from_json(&json).unwrap();
+
+    assert_eq!(read.format_version, BUNDLE_FORMAT_VERSION);
+    assert_eq!(read.metadata, metadata());
+    let read = read.into_layouts();
+    assert_eq!(
+        read[0].to_string(),
+        "# caps to esc\n[caps]>[esc]\n\n{f1}>{-lalt}{tab}{+lalt}\n"
+    );
+    assert_eq!(read[8].to_string(), "[kp-j]>[kp4]\n");
+    assert_eq!(read[1].mapping_count(), 0);
+}
+
+#[test]
+fn test_bundle_file_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("team.json");
+
+    LayoutBundle::new(metadata(), &layouts())
+        .save(&path)
+        .unwrap();
+    let read = LayoutBundle::load(&path).unwrap();
+
+    assert_eq!(read.metadata.name, "Team default");
+    assert_eq!(read.layouts()[0].mapping_count(), 2);
+}
+
+#[test]
+fn test_bundle_errors() {
+    let mut value = serde_json::to_value(LayoutBundle::new(metadata(), &layouts())).unwrap();
+
+    value["layouts"].as_array_mut().unwrap().pop();
+    assert!(matches!(
+        LayoutBundle::from_json(&value.to_string()),
+        Err(BundleError::WrongLayoutCount(8))
+    ));
+
+    value["format_version"] = json!(BUNDLE_FORMAT_VERSION + 1);
+    assert!(matches!(
+        LayoutBundle::from_json(&value.to_string()),
+        Err(BundleError::UnsupportedVersion(_))
+    ));
+
+    let bad_token = json!({
+        "format_version": 1,
+        "metadata": {"name": "x", "keyboard_model": "x", "app_version": "0"},
+        "layouts": [[{"mapping": {"type": "remap", "source": "nope", "target": "a"}}]]
+    });
+    let err = LayoutBundle::from_json(&bad_token.to_string()).unwrap_err();
+    assert!(err.to_string().contains("Unknown key token 'nope'"));
+}
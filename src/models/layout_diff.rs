@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::key_token::KeyToken;
+use super::kinesis_layout::{KeyAction, KinesisLayout};
+
+/// How a key's mapping differs between two layouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// The difference for one source key
+#[derive(Debug, Clone, PartialEq)]
+pub enum MappingChange {
+    Added(KeyAction),
+    Removed(KeyAction),
+    Changed { old: KeyAction, new: KeyAction },
+}
+
+impl MappingChange {
+    pub fn source(&self) -> KeyToken {
+        match self {
+            MappingChange::Added(action) | MappingChange::Removed(action) => action.source(),
+            MappingChange::Changed { new, .. } => new.source(),
+        }
+    }
+
+    pub fn kind(&self) -> DiffKind {
+        match self {
+            MappingChange::Added(_) => DiffKind::Added,
+            MappingChange::Removed(_) => DiffKind::Removed,
+            MappingChange::Changed { .. } => DiffKind::Changed,
+        }
+    }
+}
+
+/// Mapping differences between two layouts, one entry per source key in
+/// key order. Only the first mapping for each key counts, matching
+/// [`KinesisLayout::find_by_source`]; comments and formatting are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayoutDiff {
+    pub changes: Vec<MappingChange>,
+}
+
+impl LayoutDiff {
+    pub fn between(old: &KinesisLayout, new: &KinesisLayout) -> Self {
+        let old = first_by_source(old);
+        let mut new = first_by_source(new);
+
+        let mut changes = Vec::new();
+        for (source, old_action) in old {
+            match new.remove(&source) {
+                None => changes.push(MappingChange::Removed(old_action.clone())),
+                Some(new_action) if new_action != old_action => {
+                    changes.push(MappingChange::Changed {
+                        old: old_action.clone(),
+                        new: new_action.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        changes.extend(
+            new.into_values()
+                .map(|action| MappingChange::Added(action.clone())),
+        );
+        changes.sort_by_key(|change| change.source());
+
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The change for a source key, if it differs
+    pub fn kind_of(&self, source: KeyToken) -> Option<DiffKind> {
+        self.changes
+            .iter()
+            .find(|change| change.source() == source)
+            .map(|change| change.kind())
+    }
+
+    pub fn count(&self, kind: DiffKind) -> usize {
+        self.changes.iter().filter(|c| c.kind() == kind).count()
+    }
+}
+
+impl fmt::Display for LayoutDiff {
+    /// A summary such as "2 added, 1 removed, 0 changed"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} added, {} removed, {} changed",
+            self.count(DiffKind::Added),
+            self.count(DiffKind::Removed),
+            self.count(DiffKind::Changed)
+        )
+    }
+}
+
+fn first_by_source(layout: &KinesisLayout) -> BTreeMap<KeyToken, &KeyAction> {
+    let mut by_source = BTreeMap::new();
+    for action in layout.mappings() {
+        by_source.entry(action.source()).or_insert(action);
+    }
+    by_source
+}
@@ -0,0 +1,74 @@
+use super::key_token::KeyToken;
+use super::kinesis_layout::{KeyAction, KinesisLayout};
+use super::layout_diff::{DiffKind, LayoutDiff, MappingChange};
+use super::macro_step::MacroStep;
+
+fn layout(content: &str) -> KinesisLayout {
+    content.parse().unwrap()
+}
+
+#[test]
+fn test_identical_layouts() {
+    let a = layout("[a]>[b]\n{c}>hi\n");
+    let b = layout("# same mappings\n{c}>hi\n[a]>[b]\n");
+    assert!(LayoutDiff::between(&a, &b).is_empty());
+}
+
+#[test]
+fn test_added_removed_changed() {
+    let old = layout("[a]>[b]\n[c]>[d]\n{f1}>hi\n");
+    let new = layout("[a]>[b]\n[c]>[e]\n[kp-j]>[kp4]\n");
+
+    let diff = LayoutDiff::between(&old, &new);
+
+    assert_eq!(diff.kind_of(KeyToken::A), None);
+    assert_eq!(diff.kind_of(KeyToken::C), Some(DiffKind::Changed));
+    assert_eq!(diff.kind_of(KeyToken::F1), Some(DiffKind::Removed));
+    assert_eq!(diff.kind_of(KeyToken::J.on_keypad()), Some(DiffKind::Added));
+    assert_eq!(diff.kind_of(KeyToken::J), None);
+    assert_eq!(diff.to_string(), "1 added, 1 removed, 1 changed");
+
+    assert_eq!(
+        diff.changes[0],
+        MappingChange::Changed {
+            old: KeyAction::SimpleRemap {
+                source: KeyToken::C,
+                target: KeyToken::D
+            },
+            new: KeyAction::SimpleRemap {
+                source: KeyToken::C,
+                target: KeyToken::E
+            },
+        }
+    );
+}
+
+#[test]
+fn test_macro_changes() {
+    let old = layout("{f1}>{-lalt}{tab}{+lalt}\n[f2]>[a]\n");
+    let new = layout("{f1}>{-lalt}{tab}{tab}{+lalt}\n{f2}>a\n");
+
+    let diff = LayoutDiff::between(&old, &new);
+
+    assert_eq!(diff.count(DiffKind::Changed), 2);
+    assert_eq!(
+        diff.changes[1],
+        MappingChange::Changed {
+            old: KeyAction::SimpleRemap {
+                source: KeyToken::F2,
+                target: KeyToken::A
+            },
+            new: KeyAction::Macro {
+                trigger: KeyToken::F2,
+                steps: vec![MacroStep::Text("a".to_string())]
+            },
+        }
+    );
+}
+
+#[test]
+fn test_only_first_mapping_per_key_counts() {
+    let old = layout("[a]>[b]\n");
+    let new = layout("[a]>[b]\n[a]>[c]\n");
+    assert!(LayoutDiff::between(&old, &new).is_empty());
+}
@@ -0,0 +1,221 @@
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+use super::kinesis_layout::LAYOUT_COUNT;
+use super::layer::Layer;
+use super::settings_file::{KeyValueSettings, SettingsError, SettingsLines};
+use crate::fs_util::write_atomic;
+
+/// Highest indicator LED brightness; 0 turns the LEDs off
+pub const MAX_BRIGHTNESS: u8 = 5;
+
+/// An LED colour, written as six hex digits such as `ff8000`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Rgb {
+    pub const fn new(red: u8, green: u8, blue: u8) -> Self {
+        Self { red, green, blue }
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = ();
+
+    /// Parse `rrggbb`, with or without a leading `#`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(());
+        }
+        let channel = |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).map_err(|_| ());
+        Ok(Self::new(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
+
+/// What the indicator LEDs show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IndicatorMode {
+    /// The colour of the active layout
+    #[default]
+    Layout,
+    /// The colour of the active layer
+    Layer,
+    Off,
+}
+
+impl IndicatorMode {
+    pub const ALL: [IndicatorMode; 3] = [
+        IndicatorMode::Layout,
+        IndicatorMode::Layer,
+        IndicatorMode::Off,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            IndicatorMode::Layout => "layout",
+            IndicatorMode::Layer => "layer",
+            IndicatorMode::Off => "off",
+        }
+    }
+}
+
+impl fmt::Display for IndicatorMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndicatorMode::Layout => write!(f, "Active Layout"),
+            IndicatorMode::Layer => write!(f, "Active Layer"),
+            IndicatorMode::Off => write!(f, "Off"),
+        }
+    }
+}
+
+/// Colour each layout slot starts with
+const DEFAULT_LAYOUT_COLORS: [Rgb; LAYOUT_COUNT] = [
+    Rgb::new(0xff, 0xff, 0xff),
+    Rgb::new(0xff, 0x00, 0x00),
+    Rgb::new(0x00, 0xff, 0x00),
+    Rgb::new(0x00, 0x00, 0xff),
+    Rgb::new(0xff, 0xff, 0x00),
+    Rgb::new(0x00, 0xff, 0xff),
+    Rgb::new(0xff, 0x00, 0xff),
+    Rgb::new(0xff, 0x80, 0x00),
+    Rgb::new(0x80, 0x00, 0xff),
+];
+
+/// Indicator LED options from `settings/led.txt`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedSettings {
+    /// 0 (off) to [`MAX_BRIGHTNESS`]
+    pub brightness: u8,
+    pub indicator_mode: IndicatorMode,
+    pub base_color: Rgb,
+    pub keypad_color: Rgb,
+    /// Colour for each zero-based layout slot
+    pub layout_colors: [Rgb; LAYOUT_COUNT],
+    lines: SettingsLines,
+}
+
+impl Default for LedSettings {
+    fn default() -> Self {
+        Self {
+            brightness: 3,
+            indicator_mode: IndicatorMode::Layout,
+            base_color: Rgb::new(0xff, 0xff, 0xff),
+            keypad_color: Rgb::new(0x00, 0xff, 0x00),
+            layout_colors: DEFAULT_LAYOUT_COLORS,
+            lines: SettingsLines::default(),
+        }
+    }
+}
+
+impl LedSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn layer_color(&self, layer: Layer) -> Rgb {
+        match layer {
+            Layer::Base => self.base_color,
+            Layer::Keypad => self.keypad_color,
+        }
+    }
+
+    pub fn layer_color_mut(&mut self, layer: Layer) -> &mut Rgb {
+        match layer {
+            Layer::Base => &mut self.base_color,
+            Layer::Keypad => &mut self.keypad_color,
+        }
+    }
+
+    /// Read LED settings from a file, using the defaults if it does not exist
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match fs::read_to_string(&path) {
+            Ok(content) => content
+                .parse()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write LED settings to a file, atomically replacing any existing one
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        write_atomic(path, self.to_string().as_bytes())
+    }
+
+    /// Zero-based layout slot of a `layoutN_color` key
+    fn layout_slot(key: &str) -> Option<usize> {
+        let n: usize = key
+            .strip_prefix("layout")?
+            .strip_suffix("_color")?
+            .parse()
+            .ok()?;
+        (1..=LAYOUT_COUNT).contains(&n).then(|| n - 1)
+    }
+}
+
+impl KeyValueSettings for LedSettings {
+    fn keys() -> Vec<String> {
+        let fixed = ["brightness", "indicators", "base_color", "keypad_color"];
+        fixed
+            .into_iter()
+            .map(String::from)
+            .chain((1..=LAYOUT_COUNT).map(|n| format!("layout{}_color", n)))
+            .collect()
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "brightness" => Some(self.brightness.to_string()),
+            "indicators" => Some(self.indicator_mode.name().to_string()),
+            "base_color" => Some(self.base_color.to_string()),
+            "keypad_color" => Some(self.keypad_color.to_string()),
+            _ => Self::layout_slot(key).map(|slot| self.layout_colors[slot].to_string()),
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Option<()> {
+        match key {
+            "brightness" => {
+                self.brightness = value
+                    .parse()
+                    .ok()
+                    .filter(|level| *level <= MAX_BRIGHTNESS)?;
+            }
+            "indicators" => {
+                self.indicator_mode = IndicatorMode::ALL
+                    .into_iter()
+                    .find(|mode| mode.name().eq_ignore_ascii_case(value))?;
+            }
+            "base_color" => self.base_color = value.parse().ok()?,
+            "keypad_color" => self.keypad_color = value.parse().ok()?,
+            _ => self.layout_colors[Self::layout_slot(key)?] = value.parse().ok()?,
+        }
+        Some(())
+    }
+}
+
+impl FromStr for LedSettings {
+    type Err = SettingsError;
+
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        let (settings, lines) = SettingsLines::parse::<Self>(content)?;
+        Ok(Self { lines, ..settings })
+    }
+}
+
+impl fmt::Display for LedSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.lines.write(f, self)
+    }
+}
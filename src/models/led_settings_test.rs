@@ -0,0 +1,66 @@
+use super::layer::Layer;
+use super::led_settings::{IndicatorMode, LedSettings, Rgb};
+
+const LEDS: &str = "\
+# Indicator LEDs
+brightness=4
+indicators=layer
+base_color=#FF8000
+keypad_color=00ff00
+layout2_color=123456
+profile_led=on
+";
+
+#[test]
+fn test_rgb_parse_and_format() {
+    assert_eq!("#ff8000".parse(), Ok(Rgb::new(0xff, 0x80, 0x00)));
+    assert_eq!("0A0b0C".parse(), Ok(Rgb::new(10, 11, 12)));
+    assert_eq!(Rgb::new(10, 11, 12).to_string(), "0a0b0c");
+    for bad in ["", "fff", "#12345", "1234567", "gg0000", "ü12345"] {
+        assert!(bad.parse::<Rgb>().is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn test_parse_led_settings() {
+    let leds: LedSettings = LEDS.parse().unwrap();
+
+    assert_eq!(leds.brightness, 4);
+    assert_eq!(leds.indicator_mode, IndicatorMode::Layer);
+    assert_eq!(leds.layer_color(Layer::Base), Rgb::new(0xff, 0x80, 0x00));
+    assert_eq!(leds.layer_color(Layer::Keypad), Rgb::new(0, 0xff, 0));
+    assert_eq!(leds.layout_colors[1], Rgb::new(0x12, 0x34, 0x56));
+    assert_eq!(
+        leds.layout_colors[0],
+        LedSettings::default().layout_colors[0]
+    );
+    assert_eq!(leds.to_string(), LEDS);
+}
+
+#[test]
+fn test_edit_led_settings() {
+    let mut leds: LedSettings = LEDS.parse().unwrap();
+    *leds.layer_color_mut(Layer::Base) = Rgb::new(0, 0, 0);
+    leds.layout_colors[8] = Rgb::new(1, 2, 3);
+    leds.indicator_mode = IndicatorMode::Off;
+
+    assert_eq!(
+        leds.to_string(),
+        "# Indicator LEDs\nbrightness=4\nindicators=off\nbase_color=000000\nkeypad_color=00ff00\nlayout2_color=123456\nprofile_led=on\nlayout9_color=010203\n"
+    );
+}
+
+#[test]
+fn test_invalid_led_settings() {
+    for (content, key) in [
+        ("brightness=6", "brightness"),
+        ("indicators=blink", "indicators"),
+        ("layout3_color=red", "layout3_color"),
+    ] {
+        assert_eq!(content.parse::<LedSettings>().unwrap_err().key, key);
+    }
+
+    // Slots outside 1 to 9 are not LED settings and are kept as they are
+    let leds: LedSettings = "layout10_color=red\n".parse().unwrap();
+    assert_eq!(leds.to_string(), "layout10_color=red\n");
+}
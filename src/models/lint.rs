@@ -0,0 +1,217 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use super::key_token::KeyToken;
+use super::kinesis_layout::{KeyAction, KinesisLayout};
+use super::layer::Layer;
+use super::macro_step::MacroStep;
+
+/// Keys that drive the keyboard itself rather than sending a keycode
+const NON_KEYCODES: &[KeyToken] = &[
+    KeyToken::KeypadToggle,
+    KeyToken::KeypadShift,
+    KeyToken::LFn,
+    KeyToken::RFn,
+    KeyToken::Hotkey1,
+    KeyToken::Hotkey2,
+    KeyToken::Hotkey3,
+    KeyToken::Hotkey4,
+    KeyToken::SmartSet,
+    KeyToken::Null,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A likely mistake in a layout, each with a fix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lint {
+    /// More than one mapping for the same key; only the first is used
+    DuplicateSource { source: KeyToken, count: usize },
+    /// A remap to what the key already sends, such as `[a]>[a]`
+    IdentityRemap { source: KeyToken },
+    /// Two keys swapped that send the same keycode by default, such as the
+    /// keypad layer's two Enter keys, so the swap changes nothing
+    CancellingSwap { a: KeyToken, b: KeyToken },
+    /// A key was remapped away and nothing else sends its keycode
+    UnreachableKeycode { keycode: KeyToken },
+}
+
+impl Lint {
+    /// Every lint for a layout, most severe first and then by key
+    pub fn check(layout: &KinesisLayout) -> Vec<Lint> {
+        let mut lints = Vec::new();
+
+        let mut by_source: BTreeMap<KeyToken, Vec<&KeyAction>> = BTreeMap::new();
+        for action in layout.mappings() {
+            by_source.entry(action.source()).or_default().push(action);
+        }
+        // As elsewhere, only the first mapping for a key counts
+        let first: BTreeMap<KeyToken, &KeyAction> = by_source
+            .iter()
+            .map(|(source, actions)| (*source, actions[0]))
+            .collect();
+
+        for (&source, actions) in &by_source {
+            if actions.len() > 1 {
+                lints.push(Lint::DuplicateSource {
+                    source,
+                    count: actions.len(),
+                });
+            }
+        }
+
+        let identities: Vec<(KeyToken, KeyToken)> = first
+            .iter()
+            .filter_map(|(&source, action)| match action {
+                KeyAction::SimpleRemap { target, .. } if is_identity(source, *target) => {
+                    Some((source, *target))
+                }
+                _ => None,
+            })
+            .collect();
+        // Swapping two keys that send the same keycode writes a remap to
+        // that keycode for each, reported once as a swap
+        let mut swapped = BTreeSet::new();
+        for (idx, &(a, target)) in identities.iter().enumerate() {
+            if swapped.contains(&a) {
+                continue;
+            }
+            let other = identities[idx + 1..].iter().find(|&&(b, other)| {
+                other == target && Layer::of(b) == Layer::of(a) && !swapped.contains(&b)
+            });
+            if let Some(&(b, _)) = other {
+                swapped.extend([a, b]);
+                lints.push(Lint::CancellingSwap { a, b });
+            }
+        }
+        for &(source, _) in &identities {
+            if !swapped.contains(&source) {
+                lints.push(Lint::IdentityRemap { source });
+            }
+        }
+
+        let produced = produced_keycodes(first.values().copied());
+        for (&source, action) in &first {
+            let identity = matches!(action, KeyAction::SimpleRemap { target, .. } if is_identity(source, *target));
+            if Layer::of(source) == Layer::Base
+                && !identity
+                && !NON_KEYCODES.contains(&source)
+                && !produced.contains(&source)
+            {
+                lints.push(Lint::UnreachableKeycode { keycode: source });
+            }
+        }
+
+        lints.sort_by_key(|lint| (std::cmp::Reverse(lint.severity()), lint.key()));
+        lints
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            Lint::DuplicateSource { .. } => Severity::Error,
+            Lint::UnreachableKeycode { .. } => Severity::Warning,
+            Lint::IdentityRemap { .. } | Lint::CancellingSwap { .. } => Severity::Info,
+        }
+    }
+
+    /// The key the lint is about, for sorting and highlighting
+    pub fn key(&self) -> KeyToken {
+        match *self {
+            Lint::DuplicateSource { source, .. } | Lint::IdentityRemap { source } => source,
+            Lint::CancellingSwap { a, .. } => a,
+            Lint::UnreachableKeycode { keycode } => keycode,
+        }
+    }
+
+    /// Button label for the fix
+    pub fn fix_label(&self) -> &'static str {
+        match self {
+            Lint::DuplicateSource { .. } => "Keep First",
+            Lint::IdentityRemap { .. } => "Remove",
+            Lint::CancellingSwap { .. } => "Remove Both",
+            Lint::UnreachableKeycode { .. } => "Restore Key",
+        }
+    }
+
+    /// Change the layout so the lint no longer applies
+    pub fn fix(&self, layout: &mut KinesisLayout) {
+        match *self {
+            Lint::DuplicateSource { source, .. } => {
+                if let Some(first) = layout.find_by_source(source).first() {
+                    let first = (*first).clone();
+                    layout.set_mapping(first);
+                }
+            }
+            Lint::IdentityRemap { source } => layout.remove_by_source(source),
+            Lint::CancellingSwap { a, b } => {
+                layout.remove_by_source(a);
+                layout.remove_by_source(b);
+            }
+            Lint::UnreachableKeycode { keycode } => layout.remove_by_source(keycode),
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lint::DuplicateSource { source, count } => write!(
+                f,
+                "{} has {} mappings; only the first is used",
+                source, count
+            ),
+            Lint::IdentityRemap { source } => {
+                write!(f, "{} is remapped to what it already sends", source)
+            }
+            Lint::CancellingSwap { a, b } => write!(
+                f,
+                "{} and {} are swapped but both send {}, so nothing changes",
+                a,
+                b,
+                Layer::of(*a).default_action(*a)
+            ),
+            Lint::UnreachableKeycode { keycode } => {
+                write!(f, "{} is remapped and no other key sends it", keycode)
+            }
+        }
+    }
+}
+
+/// Whether a remap sends what the key sends when not remapped
+fn is_identity(source: KeyToken, target: KeyToken) -> bool {
+    Layer::of(source).default_action(source) == target
+}
+
+/// Keycodes sent by remap targets and by keys held or tapped in macros
+fn produced_keycodes<'a>(mappings: impl Iterator<Item = &'a KeyAction>) -> BTreeSet<KeyToken> {
+    let mut produced = BTreeSet::new();
+    for action in mappings {
+        match action {
+            KeyAction::SimpleRemap { target, .. } => {
+                produced.insert(*target);
+            }
+            KeyAction::Macro { steps, .. } => {
+                produced.extend(steps.iter().filter_map(|step| match step {
+                    MacroStep::Tap(key) | MacroStep::Press(key) => Some(*key),
+                    _ => None,
+                }));
+            }
+        }
+    }
+    produced
+}
@@ -0,0 +1,114 @@
+use super::key_token::KeyToken;
+use super::kinesis_layout::KinesisLayout;
+use super::lint::{Lint, Severity};
+
+fn lints(content: &str) -> Vec<Lint> {
+    Lint::check(&content.parse().unwrap())
+}
+
+#[test]
+fn test_clean_layout() {
+    assert!(lints("# nothing wrong\n[kp-j]>[j]\n{f2}>{speed5}{f2}hi\n").is_empty());
+    assert!(lints("").is_empty());
+}
+
+#[test]
+fn test_duplicate_source() {
+    let found = lints("[a]>[b]\n[a]>[c]\n[b]>[a]\n");
+    assert_eq!(
+        found[0],
+        Lint::DuplicateSource {
+            source: KeyToken::A,
+            count: 2
+        }
+    );
+    assert_eq!(found[0].severity(), Severity::Error);
+}
+
+#[test]
+fn test_identity_remap() {
+    let found = lints("[a]>[a]\n[kp-j]>[kp4]\n");
+    assert_eq!(
+        found,
+        vec![
+            Lint::IdentityRemap {
+                source: KeyToken::A
+            },
+            Lint::IdentityRemap {
+                source: KeyToken::J.on_keypad()
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_swap_is_not_a_problem() {
+    // A swap, as written by dragging one key onto another
+    let mut layout = KinesisLayout::new();
+    layout.swap_keys(KeyToken::Escape, KeyToken::Caps);
+    layout.swap_keys(KeyToken::J.on_keypad(), KeyToken::K.on_keypad());
+    assert!(Lint::check(&layout).is_empty());
+}
+
+#[test]
+fn test_cancelling_swap() {
+    // Both keys send Enter on the keypad layer, so swapping them, as
+    // dragging one onto the other does, changes nothing
+    let mut layout = KinesisLayout::new();
+    layout.swap_keys(
+        KeyToken::Slash.on_keypad(),
+        KeyToken::CloseBracket.on_keypad(),
+    );
+    layout.add_remap(KeyToken::A, KeyToken::A);
+
+    assert_eq!(
+        Lint::check(&layout),
+        vec![
+            Lint::IdentityRemap {
+                source: KeyToken::A
+            },
+            Lint::CancellingSwap {
+                a: KeyToken::CloseBracket.on_keypad(),
+                b: KeyToken::Slash.on_keypad(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_unreachable_keycode() {
+    let found = lints("[esc]>[caps]\n[hk1]>[a]\n{f1}>{-lalt}{tab}{+lalt}\n{tab}>hi\n");
+    assert_eq!(
+        found,
+        vec![
+            Lint::UnreachableKeycode {
+                keycode: KeyToken::Escape
+            },
+            Lint::UnreachableKeycode {
+                keycode: KeyToken::F1
+            },
+        ]
+    );
+    assert!(found.iter().all(|l| l.severity() == Severity::Warning));
+}
+
+#[test]
+fn test_fixes() {
+    let cases = [
+        ("[a]>[b]\n[a]>[c]\n[b]>[a]\n", "[a]>[b]\n[b]>[a]\n"),
+        ("[a]>[a]\n", ""),
+        (
+            "# enter\n[kp-/]>[kpenter]\n[kp-cbrack]>[kpenter]\n",
+            "# enter\n",
+        ),
+        ("[esc]>[caps]\n[kp-j]>[kp5]\n", "[kp-j]>[kp5]\n"),
+    ];
+
+    for (before, after) in cases {
+        let mut layout: KinesisLayout = before.parse().unwrap();
+        let lint = Lint::check(&layout)[0];
+        lint.fix(&mut layout);
+        assert_eq!(layout.to_string(), after, "fixing {}", lint);
+        assert!(!Lint::check(&layout).contains(&lint));
+    }
+}
@@ -0,0 +1,229 @@
+use std::fmt;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use super::key_token::KeyToken;
+
+/// Playback speed the keyboard uses until a macro sets its own
+pub const DEFAULT_MACRO_SPEED: u8 = 5;
+
+/// Estimated time between key events at speed 1; each speed step above
+/// that shortens the interval by a ninth
+const SLOWEST_EVENT_INTERVAL_MS: u64 = 90;
+
+/// One step of a macro, such as `{tab}`, `{-lalt}` or `{speed5}`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MacroStep {
+    /// Press and release a key: `{tab}`
+    Tap(KeyToken),
+    /// Press and hold a key: `{-lalt}`
+    Press(KeyToken),
+    /// Release a held key: `{+lalt}`
+    Release(KeyToken),
+    /// Change playback speed, 1 (slowest) to 9: `{speed5}`
+    Speed(u8),
+    /// Pause playback, in milliseconds: `{d125}`
+    Delay(u32),
+    /// Characters typed as written, outside any braces
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{kind} at offset {offset}")]
+pub struct MacroError {
+    /// Byte offset of the offending text within the macro
+    pub offset: usize,
+    pub len: usize,
+    pub kind: MacroErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MacroErrorKind {
+    #[error("'{{' is never closed")]
+    UnclosedBrace,
+
+    #[error("'}}' without a matching '{{'")]
+    UnexpectedCloseBrace,
+
+    #[error("empty braces")]
+    EmptyBraces,
+
+    #[error("unknown key token '{0}'")]
+    UnknownToken(String),
+
+    #[error("invalid speed '{0}', expected speed1 to speed9")]
+    InvalidSpeed(String),
+}
+
+/// Problems with a syntactically valid macro
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MacroIssue {
+    #[error("'{0}' is pressed but never released")]
+    NotReleased(KeyToken),
+
+    #[error("'{0}' is released without being pressed")]
+    ReleasedWithoutPress(KeyToken),
+
+    #[error("'{0}' is pressed again while already held")]
+    PressedTwice(KeyToken),
+}
+
+impl MacroStep {
+    /// Parse macro text such as `{speed5}{-lalt}{tab}{+lalt}`
+    pub fn parse_sequence(text: &str) -> Result<Vec<MacroStep>, MacroError> {
+        let mut steps = Vec::new();
+        let mut literal_start = 0;
+        let mut pos = 0;
+
+        let flush_literal = |steps: &mut Vec<MacroStep>, from: usize, to: usize| {
+            if from < to {
+                steps.push(MacroStep::Text(text[from..to].to_string()));
+            }
+        };
+
+        while let Some(offset) = text[pos..].find(['{', '}']) {
+            let open = pos + offset;
+            if text[open..].starts_with('}') {
+                return Err(MacroError {
+                    offset: open,
+                    len: 1,
+                    kind: MacroErrorKind::UnexpectedCloseBrace,
+                });
+            }
+
+            let Some(close) = text[open + 1..].find('}').map(|i| open + 1 + i) else {
+                return Err(MacroError {
+                    offset: open,
+                    len: text.len() - open,
+                    kind: MacroErrorKind::UnclosedBrace,
+                });
+            };
+
+            flush_literal(&mut steps, literal_start, open);
+            steps.push(
+                Self::parse_braced(&text[open + 1..close]).map_err(|kind| MacroError {
+                    offset: open,
+                    len: close + 1 - open,
+                    kind,
+                })?,
+            );
+
+            pos = close + 1;
+            literal_start = pos;
+        }
+
+        flush_literal(&mut steps, literal_start, text.len());
+        Ok(steps)
+    }
+
+    /// Parse the contents of one `{...}` group
+    fn parse_braced(inner: &str) -> Result<MacroStep, MacroErrorKind> {
+        let token = |name: &str| {
+            name.parse::<KeyToken>()
+                .map_err(|e| MacroErrorKind::UnknownToken(e.0))
+        };
+
+        if inner.is_empty() {
+            return Err(MacroErrorKind::EmptyBraces);
+        }
+
+        if let Some(level) = inner.strip_prefix("speed") {
+            return match level.parse::<u8>() {
+                Ok(speed @ 1..=9) => Ok(MacroStep::Speed(speed)),
+                _ => Err(MacroErrorKind::InvalidSpeed(inner.to_string())),
+            };
+        }
+
+        if let Some(ms) = inner.strip_prefix('d')
+            && !ms.is_empty()
+            && ms.bytes().all(|b| b.is_ascii_digit())
+            && let Ok(ms) = ms.parse()
+        {
+            return Ok(MacroStep::Delay(ms));
+        }
+
+        if let Some(name) = inner.strip_prefix('-').filter(|n| !n.is_empty()) {
+            token(name).map(MacroStep::Press)
+        } else if let Some(name) = inner.strip_prefix('+').filter(|n| !n.is_empty()) {
+            token(name).map(MacroStep::Release)
+        } else {
+            token(inner).map(MacroStep::Tap)
+        }
+    }
+
+    /// Write steps back to macro text
+    pub fn format_sequence(steps: &[MacroStep]) -> String {
+        steps.iter().map(|step| step.to_string()).collect()
+    }
+
+    /// Check that every pressed key is released exactly once
+    pub fn validate(steps: &[MacroStep]) -> Vec<MacroIssue> {
+        let mut held: Vec<KeyToken> = Vec::new();
+        let mut issues = Vec::new();
+
+        for step in steps {
+            match step {
+                MacroStep::Press(key) if held.contains(key) => {
+                    issues.push(MacroIssue::PressedTwice(*key));
+                }
+                MacroStep::Press(key) => held.push(*key),
+                MacroStep::Release(key) => match held.iter().position(|k| k == key) {
+                    Some(idx) => {
+                        held.remove(idx);
+                    }
+                    None => issues.push(MacroIssue::ReleasedWithoutPress(*key)),
+                },
+                _ => {}
+            }
+        }
+
+        issues.extend(held.into_iter().map(MacroIssue::NotReleased));
+        issues
+    }
+
+    /// Rough playback time, assuming playback starts at
+    /// [`DEFAULT_MACRO_SPEED`] and each tap or typed character is a press
+    /// and a release
+    pub fn estimated_duration(steps: &[MacroStep]) -> Duration {
+        let mut speed = DEFAULT_MACRO_SPEED;
+        let mut total_ms: u64 = 0;
+
+        for step in steps {
+            let events = match step {
+                MacroStep::Tap(_) => 2,
+                MacroStep::Press(_) | MacroStep::Release(_) => 1,
+                MacroStep::Text(text) => 2 * text.chars().count() as u64,
+                MacroStep::Speed(level) => {
+                    speed = *level;
+                    0
+                }
+                MacroStep::Delay(ms) => {
+                    total_ms += u64::from(*ms);
+                    0
+                }
+            };
+            total_ms += events * event_interval_ms(speed);
+        }
+
+        Duration::from_millis(total_ms)
+    }
+}
+
+fn event_interval_ms(speed: u8) -> u64 {
+    let speed = u64::from(speed.clamp(1, 9));
+    SLOWEST_EVENT_INTERVAL_MS * (10 - speed) / 9
+}
+
+impl fmt::Display for MacroStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacroStep::Tap(key) => write!(f, "{{{}}}", key),
+            MacroStep::Press(key) => write!(f, "{{-{}}}", key),
+            MacroStep::Release(key) => write!(f, "{{+{}}}", key),
+            MacroStep::Speed(level) => write!(f, "{{speed{}}}", level),
+            MacroStep::Delay(ms) => write!(f, "{{d{}}}", ms),
+            MacroStep::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use super::key_token::KeyToken;
+use super::macro_step::{MacroErrorKind, MacroIssue, MacroStep};
+
+#[test]
+fn test_parse_sequence() {
+    let steps = MacroStep::parse_sequence("{speed5}{-lalt}{tab}{+lalt}{d250}").unwrap();
+
+    assert_eq!(
+        steps,
+        vec![
+            MacroStep::Speed(5),
+            MacroStep::Press(KeyToken::LAlt),
+            MacroStep::Tap(KeyToken::Tab),
+            MacroStep::Release(KeyToken::LAlt),
+            MacroStep::Delay(250),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_literal_text() {
+    let steps = MacroStep::parse_sequence("hello {enter}world").unwrap();
+
+    assert_eq!(
+        steps,
+        vec![
+            MacroStep::Text("hello ".to_string()),
+            MacroStep::Tap(KeyToken::Enter),
+            MacroStep::Text("world".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_ambiguous_tokens() {
+    let steps = MacroStep::parse_sequence("{d}{-}{-hyphen}{kp-j}").unwrap();
+
+    assert_eq!(
+        steps,
+        vec![
+            MacroStep::Tap(KeyToken::D),
+            MacroStep::Tap(KeyToken::Hyphen),
+            MacroStep::Press(KeyToken::Hyphen),
+            MacroStep::Tap(KeyToken::J.on_keypad()),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    let err = |text: &str| MacroStep::parse_sequence(text).unwrap_err();
+
+    let e = err("{tab}{lalt");
+    assert_eq!(
+        (e.offset, e.len, e.kind),
+        (5, 5, MacroErrorKind::UnclosedBrace)
+    );
+
+    let e = err("abc}");
+    assert_eq!(
+        (e.offset, e.kind),
+        (3, MacroErrorKind::UnexpectedCloseBrace)
+    );
+
+    assert_eq!(err("{}").kind, MacroErrorKind::EmptyBraces);
+    assert_eq!(
+        err("{tab}{bogus}").kind,
+        MacroErrorKind::UnknownToken("bogus".to_string())
+    );
+    assert_eq!(
+        err("{speed0}").kind,
+        MacroErrorKind::InvalidSpeed("speed0".to_string())
+    );
+    assert_eq!(
+        err("{speed10}").kind,
+        MacroErrorKind::InvalidSpeed("speed10".to_string())
+    );
+}
+
+#[test]
+fn test_format_round_trip() {
+    let text = "{speed3}{-lctrl}{c}{+lctrl}{d125}abc{kp-j}";
+    let steps = MacroStep::parse_sequence(text).unwrap();
+
+    assert_eq!(MacroStep::format_sequence(&steps), text);
+}
+
+#[test]
+fn test_validate_balanced() {
+    let steps = MacroStep::parse_sequence("{-lalt}{-lshift}{tab}{+lshift}{+lalt}").unwrap();
+    assert!(MacroStep::validate(&steps).is_empty());
+}
+
+#[test]
+fn test_validate_unbalanced() {
+    let steps = MacroStep::parse_sequence("{+lctrl}{-lalt}{-lalt}{-lshift}{+lshift}").unwrap();
+
+    assert_eq!(
+        MacroStep::validate(&steps),
+        vec![
+            MacroIssue::ReleasedWithoutPress(KeyToken::LCtrl),
+            MacroIssue::PressedTwice(KeyToken::LAlt),
+            MacroIssue::NotReleased(KeyToken::LAlt),
+        ]
+    );
+}
+
+#[test]
+fn test_estimated_duration() {
+    // Speed 9 is 10ms per event, speed 1 is 90ms per event
+    let fast = MacroStep::parse_sequence("{speed9}{a}{b}").unwrap();
+    assert_eq!(
+        MacroStep::estimated_duration(&fast),
+        Duration::from_millis(40)
+    );
+
+    let slow = MacroStep::parse_sequence("{speed1}{-lalt}{+lalt}{d500}hi").unwrap();
+    assert_eq!(
+        MacroStep::estimated_duration(&slow),
+        Duration::from_millis(90 * 2 + 500 + 90 * 4)
+    );
+
+    assert_eq!(MacroStep::estimated_duration(&[]), Duration::ZERO);
+}
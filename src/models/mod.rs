@@ -1,6 +1,54 @@
+pub mod history;
+pub mod key_token;
+pub mod keyboard_settings;
 pub mod kinesis_layout;
+pub mod layer;
+pub mod layout_bundle;
+pub mod layout_diff;
+pub mod led_settings;
+pub mod lint;
+pub mod macro_step;
+pub mod parse_error;
+pub mod selection_operation;
+pub mod settings_file;
+pub mod slot_operation;
 
+#[cfg(test)]
+mod history_test;
+#[cfg(test)]
+mod key_token_test;
+#[cfg(test)]
+mod keyboard_settings_test;
 #[cfg(test)]
 mod kinesis_layout_test;
+#[cfg(test)]
+mod layer_test;
+#[cfg(test)]
+mod layout_bundle_test;
+#[cfg(test)]
+mod layout_diff_test;
+#[cfg(test)]
+mod led_settings_test;
+#[cfg(test)]
+mod lint_test;
+#[cfg(test)]
+mod macro_step_test;
+#[cfg(test)]
+mod selection_operation_test;
+#[cfg(test)]
+mod slot_operation_test;
 
-pub use kinesis_layout::{KeyAction, KinesisLayout};
+pub use history::{Edit, HISTORY_LIMIT, History};
+pub use key_token::{KeyCategory, KeyToken};
+pub use keyboard_settings::{KeyboardSettings, MAX_PLAYBACK_SPEED, ThumbMode};
+pub use kinesis_layout::{KeyAction, KinesisLayout, LAYOUT_COUNT, LayoutLine};
+pub use layer::Layer;
+pub use layout_bundle::{BUNDLE_EXTENSION, BundleError, BundleMetadata, LayoutBundle};
+pub use layout_diff::{DiffKind, LayoutDiff, MappingChange};
+pub use led_settings::{IndicatorMode, LedSettings, MAX_BRIGHTNESS, Rgb};
+pub use lint::{Lint, Severity};
+pub use macro_step::{MacroError, MacroErrorKind, MacroIssue, MacroStep};
+pub use parse_error::{ParseError, ParseErrorKind};
+pub use selection_operation::SelectionOperation;
+pub use settings_file::SettingsError;
+pub use slot_operation::SlotOperation;
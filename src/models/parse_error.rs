@@ -0,0 +1,39 @@
+use std::ops::Range;
+
+use thiserror::Error;
+
+use super::macro_step::MacroErrorKind;
+
+/// A problem found while parsing one line of a layout file
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("line {line}, column {}: {kind}", span.start + 1)]
+pub struct ParseError {
+    /// 1-based line number
+    pub line: usize,
+    /// 0-based byte range of the offending text within the line
+    pub span: Range<usize>,
+    /// The offending text
+    pub text: String,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseErrorKind {
+    #[error("missing '>' operator")]
+    MissingOperator,
+
+    #[error("source must be a [key] or {{key}}")]
+    InvalidNotation,
+
+    #[error("simple remap target must be in square brackets")]
+    RemapTargetNotBracketed,
+
+    #[error("macro target cannot be a simple remap in square brackets")]
+    MacroTargetBracketed,
+
+    #[error("unknown key token '{0}'")]
+    UnknownToken(String),
+
+    #[error("invalid macro: {0}")]
+    InvalidMacro(MacroErrorKind),
+}
@@ -0,0 +1,154 @@
+use super::key_token::KeyToken;
+use super::kinesis_layout::{KeyAction, KinesisLayout};
+use super::layer::Layer;
+
+/// A bulk edit of several selected keys in one layout slot. Keys are remap
+/// sources on one layer, in the order [`Rotate`](Self::Rotate) and
+/// [`Shift`](Self::Shift) walk them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionOperation {
+    /// Remove simple remaps, keeping macros
+    ClearRemaps,
+    /// Remove remaps and macros, leaving the keys at their defaults
+    Reset,
+    /// Make the keys in slot `to` do what they do in the selected slot
+    CopyTo { to: usize },
+    /// Move what each key sends on to the next key, the last key's wrapping
+    /// round to the first. `reverse` moves them the other way.
+    Rotate { reverse: bool },
+    /// Like [`Rotate`](Self::Rotate), but the last key's action is dropped
+    /// and the first key goes back to its default
+    Shift { reverse: bool },
+}
+
+impl SelectionOperation {
+    /// Slots whose contents change when the keys are selected in `slot`
+    pub fn affected_slots(&self, slot: usize) -> Vec<usize> {
+        match *self {
+            SelectionOperation::CopyTo { to } if to == slot => Vec::new(),
+            SelectionOperation::CopyTo { to } => vec![to],
+            _ => vec![slot],
+        }
+    }
+
+    pub fn apply(&self, keys: &[KeyToken], slot: usize, layouts: &mut [KinesisLayout]) {
+        match *self {
+            SelectionOperation::ClearRemaps => {
+                let layout = &mut layouts[slot];
+                for &key in keys {
+                    let kept = layout
+                        .find_by_source(key)
+                        .into_iter()
+                        .find(|action| matches!(action, KeyAction::Macro { .. }))
+                        .cloned();
+                    match kept {
+                        Some(action) => layout.set_mapping(action),
+                        None => layout.remove_by_source(key),
+                    }
+                }
+            }
+            SelectionOperation::Reset => {
+                for &key in keys {
+                    layouts[slot].remove_by_source(key);
+                }
+            }
+            SelectionOperation::CopyTo { to } if to == slot => {}
+            SelectionOperation::CopyTo { to } => {
+                for &key in keys {
+                    let action = layouts[slot]
+                        .find_by_source(key)
+                        .first()
+                        .map(|&action| action.clone());
+                    match action {
+                        Some(action) => layouts[to].set_mapping(action),
+                        None => layouts[to].remove_by_source(key),
+                    }
+                }
+            }
+            SelectionOperation::Rotate { reverse } => {
+                move_along(&mut layouts[slot], keys, reverse, true)
+            }
+            SelectionOperation::Shift { reverse } => {
+                move_along(&mut layouts[slot], keys, reverse, false)
+            }
+        }
+    }
+
+    /// A short description for the status line and undo history
+    pub fn describe(&self, key_count: usize) -> String {
+        match *self {
+            SelectionOperation::ClearRemaps => format!("clear of remaps on {} key(s)", key_count),
+            SelectionOperation::Reset => format!("reset of {} key(s)", key_count),
+            SelectionOperation::CopyTo { to } => {
+                format!("copy of {} key(s) to layout {}", key_count, to + 1)
+            }
+            SelectionOperation::Rotate { .. } => format!("rotation of {} key(s)", key_count),
+            SelectionOperation::Shift { .. } => format!("shift of {} key(s)", key_count),
+        }
+    }
+}
+
+/// Move what each key sends to the next one, wrapping round or dropping
+/// the last key's action
+fn move_along(layout: &mut KinesisLayout, keys: &[KeyToken], reverse: bool, wrap: bool) {
+    if wrap && keys.len() < 2 {
+        return;
+    }
+
+    let mut actions: Vec<Option<KeyAction>> =
+        keys.iter().map(|&key| Some(sends(layout, key))).collect();
+    if reverse {
+        actions.rotate_left(1);
+    } else {
+        actions.rotate_right(1);
+    }
+    if !wrap {
+        let emptied = if reverse {
+            actions.last_mut()
+        } else {
+            actions.first_mut()
+        };
+        if let Some(action) = emptied {
+            *action = None;
+        }
+    }
+
+    for (&key, action) in keys.iter().zip(actions) {
+        match action {
+            Some(action) => place(layout, key, action),
+            None => layout.remove_by_source(key),
+        }
+    }
+}
+
+/// A key's mapping, or a remap to what it sends by default
+fn sends(layout: &KinesisLayout, key: KeyToken) -> KeyAction {
+    layout
+        .find_by_source(key)
+        .first()
+        .map(|&action| action.clone())
+        .unwrap_or(KeyAction::SimpleRemap {
+            source: key,
+            target: Layer::of(key).default_action(key),
+        })
+}
+
+/// Give `key` another key's action. A remap to the key's own default is
+/// removed rather than written.
+fn place(layout: &mut KinesisLayout, key: KeyToken, action: KeyAction) {
+    let action = match action {
+        KeyAction::SimpleRemap { target, .. } if target == Layer::of(key).default_action(key) => {
+            layout.remove_by_source(key);
+            return;
+        }
+        KeyAction::SimpleRemap { target, .. } => KeyAction::SimpleRemap {
+            source: key,
+            target,
+        },
+        KeyAction::Macro { steps, .. } => KeyAction::Macro {
+            trigger: key,
+            steps,
+        },
+    };
+    layout.set_mapping(action);
+}
@@ -0,0 +1,93 @@
+use super::key_token::KeyToken;
+use super::kinesis_layout::KinesisLayout;
+use super::selection_operation::SelectionOperation;
+
+const KEYS: [KeyToken; 3] = [KeyToken::A, KeyToken::S, KeyToken::D];
+
+fn layouts() -> Vec<KinesisLayout> {
+    vec![
+        "[a]>[b]\n{s}>hi\n[x]>[y]\n".parse().unwrap(),
+        "[d]>[e]\n[x]>[z]\n".parse().unwrap(),
+    ]
+}
+
+#[test]
+fn test_clear_remaps_keeps_macros() {
+    let mut layouts = layouts();
+    SelectionOperation::ClearRemaps.apply(&KEYS, 0, &mut layouts);
+    assert_eq!(layouts[0].to_string(), "{s}>hi\n[x]>[y]\n");
+}
+
+#[test]
+fn test_reset() {
+    let mut layouts = layouts();
+    SelectionOperation::Reset.apply(&KEYS, 0, &mut layouts);
+    assert_eq!(layouts[0].to_string(), "[x]>[y]\n");
+    assert_eq!(layouts[1], self::layouts()[1]);
+}
+
+#[test]
+fn test_copy_to_another_slot() {
+    let mut layouts = layouts();
+    let op = SelectionOperation::CopyTo { to: 1 };
+
+    assert_eq!(op.affected_slots(0), vec![1]);
+    op.apply(&KEYS, 0, &mut layouts);
+    // `d` has no mapping in the first layout, so it loses its remap in the
+    // second; `x` was not selected and is left alone
+    assert_eq!(layouts[1].to_string(), "[x]>[z]\n[a]>[b]\n{s}>hi\n");
+    assert_eq!(layouts[0], self::layouts()[0]);
+}
+
+#[test]
+fn test_copy_to_same_slot_changes_nothing() {
+    let mut layouts = layouts();
+    let op = SelectionOperation::CopyTo { to: 0 };
+
+    assert!(op.affected_slots(0).is_empty());
+    op.apply(&KEYS, 0, &mut layouts);
+    assert_eq!(layouts, self::layouts());
+}
+
+#[test]
+fn test_rotate() {
+    let mut layouts = vec![KinesisLayout::new()];
+    SelectionOperation::Rotate { reverse: false }.apply(&KEYS, 0, &mut layouts);
+    assert_eq!(layouts[0].to_string(), "[a]>[d]\n[s]>[a]\n[d]>[s]\n");
+
+    // Rotating back removes the remaps instead of mapping keys to themselves
+    SelectionOperation::Rotate { reverse: true }.apply(&KEYS, 0, &mut layouts);
+    assert_eq!(layouts[0], KinesisLayout::new());
+
+    let mut layouts = vec!["{a}>hi\n".parse().unwrap()];
+    SelectionOperation::Rotate { reverse: true }.apply(&KEYS, 0, &mut layouts);
+    assert_eq!(layouts[0].to_string(), "[a]>[s]\n[s]>[d]\n{d}>hi\n");
+}
+
+#[test]
+fn test_shift() {
+    let mut layouts = layouts();
+    SelectionOperation::Shift { reverse: false }.apply(&KEYS, 0, &mut layouts);
+    assert_eq!(layouts[0].to_string(), "[s]>[b]\n[x]>[y]\n{d}>hi\n");
+
+    let mut layouts = self::layouts();
+    SelectionOperation::Shift { reverse: true }.apply(&KEYS, 0, &mut layouts);
+    assert_eq!(layouts[0].to_string(), "{a}>hi\n[s]>[d]\n[x]>[y]\n");
+}
+
+#[test]
+fn test_rotate_on_keypad_layer() {
+    let keys = [KeyToken::J.on_keypad(), KeyToken::K.on_keypad()];
+    let mut layouts = vec![KinesisLayout::new()];
+    SelectionOperation::Rotate { reverse: false }.apply(&keys, 0, &mut layouts);
+    assert_eq!(layouts[0].to_string(), "[kp-j]>[kp5]\n[kp-k]>[kp4]\n");
+}
+
+#[test]
+fn test_describe() {
+    assert_eq!(SelectionOperation::Reset.describe(3), "reset of 3 key(s)");
+    assert_eq!(
+        SelectionOperation::CopyTo { to: 1 }.describe(2),
+        "copy of 2 key(s) to layout 2"
+    );
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("line {line}: invalid value '{value}' for {key}")]
+pub struct SettingsError {
+    pub line: usize,
+    pub key: String,
+    pub value: String,
+}
+
+/// Settings stored as `key=value` lines on the V-Drive
+pub(crate) trait KeyValueSettings: Default {
+    /// Every key the type models, in the order missing ones are appended
+    fn keys() -> Vec<String>;
+
+    /// Current value of a modelled key, or `None` for other keys. Keys are
+    /// lower case.
+    fn get(&self, key: &str) -> Option<String>;
+
+    /// Set a modelled key from its text, returning `None` if the value is
+    /// invalid
+    fn set(&mut self, key: &str, value: &str) -> Option<()>;
+}
+
+/// A line of a settings file
+#[derive(Debug, Clone, PartialEq)]
+struct Line {
+    /// Original text including its line terminator
+    raw: String,
+    /// Lower-case key of a `key=value` line the settings model
+    key: Option<String>,
+    /// The modelled value as first read, so untouched settings are written
+    /// back byte for byte
+    read_as: Option<String>,
+}
+
+/// The lines of a settings file, so unknown keys, comments and formatting
+/// survive a round trip
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct SettingsLines {
+    lines: Vec<Line>,
+    /// Use `\r\n` for newly written lines, matching the original file
+    crlf: bool,
+}
+
+impl SettingsLines {
+    pub fn parse<T: KeyValueSettings>(content: &str) -> Result<(T, Self), SettingsError> {
+        let mut settings = T::default();
+        let mut lines = Self {
+            lines: Vec::new(),
+            crlf: content.contains("\r\n"),
+        };
+
+        for (line_num, raw) in content.split_inclusive('\n').enumerate() {
+            let mut line = Line {
+                raw: raw.to_string(),
+                key: None,
+                read_as: None,
+            };
+
+            if let Some((key, value)) = split_setting(raw.trim_end()) {
+                let key = key.to_ascii_lowercase();
+                if settings.get(&key).is_some() {
+                    settings.set(&key, value).ok_or_else(|| SettingsError {
+                        line: line_num + 1,
+                        key: key.clone(),
+                        value: value.to_string(),
+                    })?;
+                    line.read_as = settings.get(&key);
+                    line.key = Some(key);
+                }
+            }
+            lines.lines.push(line);
+        }
+
+        Ok((settings, lines))
+    }
+
+    /// Write the lines with the current values of `settings`. Modelled keys
+    /// missing from the file are added once they differ from the default.
+    pub fn write<T: KeyValueSettings>(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        settings: &T,
+    ) -> fmt::Result {
+        let eol = if self.crlf { "\r\n" } else { "\n" };
+        let mut at_line_start = true;
+
+        for line in &self.lines {
+            let current = line.key.as_deref().and_then(|key| settings.get(key));
+            match (&line.key, current) {
+                (Some(key), Some(value)) if line.read_as.as_ref() != Some(&value) => {
+                    write_setting(f, &mut at_line_start, eol, key, &value)?;
+                }
+                _ => {
+                    write!(f, "{}", line.raw)?;
+                    at_line_start = line.raw.ends_with('\n');
+                }
+            }
+        }
+
+        let defaults = T::default();
+        for key in T::keys() {
+            let in_file = self.lines.iter().any(|l| l.key.as_ref() == Some(&key));
+            let value = settings.get(&key);
+            if !in_file && value != defaults.get(&key) {
+                let value = value.unwrap_or_default();
+                write_setting(f, &mut at_line_start, eol, &key, &value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write a setting in canonical form on a line of its own. The original
+/// last line may have had no terminator.
+fn write_setting(
+    f: &mut fmt::Formatter<'_>,
+    at_line_start: &mut bool,
+    eol: &str,
+    key: &str,
+    value: &str,
+) -> fmt::Result {
+    if !*at_line_start {
+        write!(f, "{}", eol)?;
+    }
+    *at_line_start = true;
+    write!(f, "{}={}{}", key, value, eol)
+}
+
+/// Key and value of a `key=value` line
+fn split_setting(text: &str) -> Option<(&str, &str)> {
+    let (key, value) = text.split_once('=')?;
+    let key = key.trim();
+    (!key.is_empty() && !key.starts_with('#')).then(|| (key, value.trim()))
+}
+
+pub(crate) fn on_off(value: bool) -> String {
+    if value { "on" } else { "off" }.to_string()
+}
+
+pub(crate) fn parse_on_off(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
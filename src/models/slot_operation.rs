@@ -0,0 +1,70 @@
+use super::kinesis_layout::KinesisLayout;
+
+/// A whole-layout operation between two of the keyboard's layout slots.
+/// Slots are zero-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotOperation {
+    /// Replace `to` with a copy of `from`
+    Copy { from: usize, to: usize },
+    /// Exchange two layouts
+    Swap { a: usize, b: usize },
+    /// Empty a layout, leaving every key at its default
+    Reset { slot: usize },
+}
+
+impl SlotOperation {
+    /// Number of mappings that are replaced or removed
+    pub fn overwritten(&self, layouts: &[KinesisLayout]) -> usize {
+        match *self {
+            SlotOperation::Copy { from, to } if from == to => 0,
+            SlotOperation::Copy { to, .. } => layouts[to].mapping_count(),
+            SlotOperation::Swap { a, b } if a == b => 0,
+            SlotOperation::Swap { a, b } => layouts[a].mapping_count() + layouts[b].mapping_count(),
+            SlotOperation::Reset { slot } => layouts[slot].mapping_count(),
+        }
+    }
+
+    /// Slots whose contents change
+    pub fn affected_slots(&self) -> Vec<usize> {
+        match *self {
+            SlotOperation::Copy { from, to } if from == to => Vec::new(),
+            SlotOperation::Copy { to, .. } => vec![to],
+            SlotOperation::Swap { a, b } if a == b => Vec::new(),
+            SlotOperation::Swap { a, b } => vec![a, b],
+            SlotOperation::Reset { slot } => vec![slot],
+        }
+    }
+
+    pub fn apply(&self, layouts: &mut [KinesisLayout]) {
+        match *self {
+            SlotOperation::Copy { from, to } => layouts[to] = layouts[from].clone(),
+            SlotOperation::Swap { a, b } => layouts.swap(a, b),
+            SlotOperation::Reset { slot } => layouts[slot] = KinesisLayout::new(),
+        }
+    }
+
+    /// A sentence for the confirmation dialog, with layouts numbered from 1
+    pub fn describe(&self, layouts: &[KinesisLayout]) -> String {
+        let count = |slot: usize| layouts[slot].mapping_count();
+        match *self {
+            SlotOperation::Copy { from, to } => format!(
+                "Layout {} will be replaced by a copy of layout {}. Its {} mapping(s) will be overwritten.",
+                to + 1,
+                from + 1,
+                count(to)
+            ),
+            SlotOperation::Swap { a, b } => format!(
+                "Layout {} ({} mapping(s)) and layout {} ({} mapping(s)) will trade places.",
+                a + 1,
+                count(a),
+                b + 1,
+                count(b)
+            ),
+            SlotOperation::Reset { slot } => format!(
+                "All {} mapping(s) in layout {} will be removed.",
+                count(slot),
+                slot + 1
+            ),
+        }
+    }
+}
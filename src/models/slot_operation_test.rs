@@ -0,0 +1,75 @@
+use super::kinesis_layout::KinesisLayout;
+use super::slot_operation::SlotOperation;
+
+fn layouts() -> Vec<KinesisLayout> {
+    vec![
+        "[a]>[b]\n".parse().unwrap(),
+        "[c]>[d]\n[e]>[f]\n".parse().unwrap(),
+        KinesisLayout::new(),
+    ]
+}
+
+#[test]
+fn test_copy() {
+    let mut layouts = layouts();
+    let op = SlotOperation::Copy { from: 0, to: 1 };
+
+    assert_eq!(op.overwritten(&layouts), 2);
+    assert_eq!(op.affected_slots(), vec![1]);
+    op.apply(&mut layouts);
+    assert_eq!(layouts[1], layouts[0]);
+    assert_eq!(layouts[1].to_string(), "[a]>[b]\n");
+}
+
+#[test]
+fn test_swap() {
+    let mut layouts = layouts();
+    let op = SlotOperation::Swap { a: 0, b: 1 };
+
+    assert_eq!(op.overwritten(&layouts), 3);
+    op.apply(&mut layouts);
+    assert_eq!(layouts[0].mapping_count(), 2);
+    assert_eq!(layouts[1].mapping_count(), 1);
+
+    // Swapping twice restores the original order
+    op.apply(&mut layouts);
+    assert_eq!(layouts, self::layouts());
+}
+
+#[test]
+fn test_reset() {
+    let mut layouts = layouts();
+    let op = SlotOperation::Reset { slot: 1 };
+
+    assert_eq!(op.overwritten(&layouts), 2);
+    op.apply(&mut layouts);
+    assert_eq!(layouts[1].mapping_count(), 0);
+    assert_eq!(layouts[0].mapping_count(), 1);
+}
+
+#[test]
+fn test_same_slot_changes_nothing() {
+    let mut layouts = layouts();
+    for op in [
+        SlotOperation::Copy { from: 1, to: 1 },
+        SlotOperation::Swap { a: 1, b: 1 },
+    ] {
+        assert_eq!(op.overwritten(&layouts), 0);
+        assert!(op.affected_slots().is_empty());
+        op.apply(&mut layouts);
+    }
+    assert_eq!(layouts, self::layouts());
+}
+
+#[test]
+fn test_describe() {
+    let layouts = layouts();
+    assert_eq!(
+        SlotOperation::Copy { from: 0, to: 1 }.describe(&layouts),
+        "Layout 2 will be replaced by a copy of layout 1. Its 2 mapping(s) will be overwritten."
+    );
+    assert_eq!(
+        SlotOperation::Reset { slot: 0 }.describe(&layouts),
+        "All 1 mapping(s) in layout 1 will be removed."
+    );
+}
@@ -0,0 +1,249 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs, io};
+
+use super::detect::VDrive;
+use crate::fs_util::write_atomic;
+
+/// Number of backups kept before the oldest are deleted
+pub const BACKUP_RETENTION: usize = 10;
+
+/// V-Drive directories captured in a backup
+const BACKUP_DIRS: [&str; 2] = ["layouts", "settings"];
+
+/// Shape of a backup directory name as made by [`format_timestamp`], with
+/// `0` standing for any digit
+const BACKUP_NAME_PATTERN: &str = "0000-00-00T00-00-00Z";
+
+/// A snapshot of a V-Drive's configuration directories
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backup {
+    pub path: PathBuf,
+    /// Directory name, a UTC timestamp such as `2025-01-31T09-15-00Z`
+    pub name: String,
+}
+
+/// `$XDG_DATA_HOME/kinesis-configurator/backups`, falling back to
+/// `~/.local/share` when `XDG_DATA_HOME` is unset
+pub fn backups_dir() -> Option<PathBuf> {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+
+    Some(data_home.join("kinesis-configurator").join("backups"))
+}
+
+/// Copy the drive's `layouts/` and `settings/` trees into a new timestamped
+/// directory under `backups_dir`
+pub fn create_backup(drive: &VDrive, backups_dir: &Path) -> io::Result<Backup> {
+    fs::create_dir_all(backups_dir)?;
+
+    let timestamp = format_timestamp(SystemTime::now());
+    let mut name = timestamp.clone();
+    let mut attempt = 1;
+    while backups_dir.join(&name).exists() {
+        attempt += 1;
+        name = format!("{}-{}", timestamp, attempt);
+    }
+
+    let path = backups_dir.join(&name);
+    for dir in BACKUP_DIRS {
+        let source = drive.root.join(dir);
+        if source.is_dir() {
+            copy_tree(&source, &path.join(dir))?;
+        } else {
+            fs::create_dir_all(path.join(dir))?;
+        }
+    }
+
+    Ok(Backup { path, name })
+}
+
+/// Back up a drive into the default backups directory and apply the
+/// retention policy. Called before anything is written to the keyboard.
+pub fn backup_drive(drive: &VDrive) -> io::Result<Backup> {
+    let dir = default_backups_dir()?;
+    let backup = create_backup(drive, &dir)?;
+    prune_backups(&dir, BACKUP_RETENTION)?;
+    Ok(backup)
+}
+
+/// Restore a backup onto a drive, backing the drive up into the default
+/// backups directory first. See [`restore_with_backup`].
+pub fn restore_drive(backup: &Backup, drive: &VDrive) -> io::Result<Backup> {
+    restore_with_backup(backup, drive, &default_backups_dir()?)
+}
+
+/// Back up a drive into `backups_dir`, restore `backup` onto it, and only
+/// then apply the retention policy, so an old backup being restored is not
+/// pruned before it is read. Returns the new backup.
+pub fn restore_with_backup(
+    backup: &Backup,
+    drive: &VDrive,
+    backups_dir: &Path,
+) -> io::Result<Backup> {
+    let current = create_backup(drive, backups_dir)?;
+    restore_backup(backup, drive)?;
+    prune_backups(backups_dir, BACKUP_RETENTION)?;
+    Ok(current)
+}
+
+fn default_backups_dir() -> io::Result<PathBuf> {
+    backups_dir().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "neither XDG_DATA_HOME nor HOME is set",
+        )
+    })
+}
+
+/// All backups in `backups_dir`, newest first. Directories not named like
+/// a backup are ignored.
+pub fn list_backups(backups_dir: &Path) -> io::Result<Vec<Backup>> {
+    let entries = match fs::read_dir(backups_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut backups: Vec<Backup> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            backup_order(&name)?;
+            Some(Backup {
+                path: entry.path(),
+                name,
+            })
+        })
+        .collect();
+
+    // Compare suffixes as numbers, so `-10` is newer than `-2`
+    backups.sort_by(|a, b| backup_order(&b.name).cmp(&backup_order(&a.name)));
+    Ok(backups)
+}
+
+/// Delete all but the newest `keep` backups, returning the removed ones
+pub fn prune_backups(backups_dir: &Path, keep: usize) -> io::Result<Vec<Backup>> {
+    let stale: Vec<Backup> = list_backups(backups_dir)?.into_iter().skip(keep).collect();
+
+    for backup in &stale {
+        fs::remove_dir_all(&backup.path)?;
+    }
+
+    Ok(stale)
+}
+
+/// Write every file in a backup back onto the drive. Files on the drive
+/// that are not in the backup are left alone.
+pub fn restore_backup(backup: &Backup, drive: &VDrive) -> io::Result<()> {
+    if !backup.path.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("backup {} no longer exists", backup.name),
+        ));
+    }
+
+    for dir in BACKUP_DIRS {
+        let source = backup.path.join(dir);
+        if source.is_dir() {
+            restore_tree(&source, &drive.root.join(dir))?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_tree(source: &Path, dest: &Path) -> io::Result<()> {
+    fs::create_dir_all(dest)?;
+
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target = dest.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_tree(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn restore_tree(source: &Path, dest: &Path) -> io::Result<()> {
+    fs::create_dir_all(dest)?;
+
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target = dest.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            restore_tree(&entry.path(), &target)?;
+        } else {
+            write_atomic(&target, &fs::read(entry.path())?)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Where a directory name made by [`create_backup`] sorts: its timestamp,
+/// then the `-N` suffix added when several backups share a second, with no
+/// suffix counting as the first. `None` for names not made by
+/// [`create_backup`].
+fn backup_order(name: &str) -> Option<(&str, u64)> {
+    let (timestamp, suffix) = name.split_at_checked(BACKUP_NAME_PATTERN.len())?;
+    let timestamp_matches =
+        timestamp
+            .bytes()
+            .zip(BACKUP_NAME_PATTERN.bytes())
+            .all(|(c, pattern)| match pattern {
+                b'0' => c.is_ascii_digit(),
+                _ => c == pattern,
+            });
+    if !timestamp_matches {
+        return None;
+    }
+
+    let attempt = match suffix.strip_prefix('-') {
+        None if suffix.is_empty() => 1,
+        Some(n) if !n.is_empty() && n.bytes().all(|c| c.is_ascii_digit()) => n.parse().ok()?,
+        _ => return None,
+    };
+    Some((timestamp, attempt))
+}
+
+/// Format a time as a UTC timestamp usable in a file name
+pub(crate) fn format_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}-{:02}-{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+/// Convert days since the Unix epoch to a (year, month, day) civil date
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
@@ -0,0 +1,220 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+use super::backup::{
+    BACKUP_RETENTION, create_backup, format_timestamp, list_backups, prune_backups, restore_backup,
+    restore_with_backup,
+};
+use super::detect::VDrive;
+use super::fixture::make_vdrive;
+
+/// A drive with one layout and a settings file
+fn make_drive(root: &Path) -> VDrive {
+    make_vdrive(
+        root,
+        &[
+            ("layouts/layout1.txt", "[a]>[b]\n"),
+            ("settings/kbd_settings.txt", "macro_speed=3\n"),
+        ],
+    )
+}
+
+#[test]
+fn test_format_timestamp() {
+    assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00-00-00Z");
+    assert_eq!(
+        format_timestamp(UNIX_EPOCH + Duration::from_secs(1_709_251_199)),
+        "2024-02-29T23-59-59Z"
+    );
+}
+
+#[test]
+fn test_create_backup_copies_trees() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = make_drive(&dir.path().join("drive"));
+    let backups = dir.path().join("backups");
+
+    let backup = create_backup(&drive, &backups).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(backup.path.join("layouts/layout1.txt")).unwrap(),
+        "[a]>[b]\n"
+    );
+    assert_eq!(
+        fs::read_to_string(backup.path.join("settings/kbd_settings.txt")).unwrap(),
+        "macro_speed=3\n"
+    );
+}
+
+#[test]
+fn test_backups_in_same_second_get_unique_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = make_drive(&dir.path().join("drive"));
+    let backups = dir.path().join("backups");
+
+    let first = create_backup(&drive, &backups).unwrap();
+    let second = create_backup(&drive, &backups).unwrap();
+
+    assert_ne!(first.path, second.path);
+    assert_eq!(list_backups(&backups).unwrap().len(), 2);
+}
+
+#[test]
+fn test_list_backups_newest_first() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in [
+        "2024-01-02T00-00-00Z",
+        "2024-03-01T00-00-00Z",
+        "2023-12-31T00-00-00Z",
+    ] {
+        fs::create_dir_all(dir.path().join(name)).unwrap();
+    }
+
+    let names: Vec<String> = list_backups(dir.path())
+        .unwrap()
+        .into_iter()
+        .map(|b| b.name)
+        .collect();
+
+    assert_eq!(
+        names,
+        [
+            "2024-03-01T00-00-00Z",
+            "2024-01-02T00-00-00Z",
+            "2023-12-31T00-00-00Z"
+        ]
+    );
+    assert!(
+        list_backups(&dir.path().join("missing"))
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_prune_backups() {
+    let dir = tempfile::tempdir().unwrap();
+    for day in 1..=5 {
+        fs::create_dir_all(dir.path().join(format!("2024-01-0{}T00-00-00Z", day))).unwrap();
+    }
+
+    let removed = prune_backups(dir.path(), 2).unwrap();
+
+    assert_eq!(removed.len(), 3);
+    let kept: Vec<String> = list_backups(dir.path())
+        .unwrap()
+        .into_iter()
+        .map(|b| b.name)
+        .collect();
+    assert_eq!(kept, ["2024-01-05T00-00-00Z", "2024-01-04T00-00-00Z"]);
+}
+
+#[test]
+fn test_prune_orders_same_second_backups_by_number() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in [
+        "2024-01-01T00-00-00Z",
+        "2024-01-01T00-00-00Z-2",
+        "2024-01-01T00-00-00Z-10",
+        "2023-12-31T00-00-00Z-3",
+    ] {
+        fs::create_dir_all(dir.path().join(name)).unwrap();
+    }
+
+    let removed: Vec<String> = prune_backups(dir.path(), 2)
+        .unwrap()
+        .into_iter()
+        .map(|b| b.name)
+        .collect();
+
+    assert_eq!(removed, ["2024-01-01T00-00-00Z", "2023-12-31T00-00-00Z-3"]);
+    let kept: Vec<String> = list_backups(dir.path())
+        .unwrap()
+        .into_iter()
+        .map(|b| b.name)
+        .collect();
+    assert_eq!(kept, ["2024-01-01T00-00-00Z-10", "2024-01-01T00-00-00Z-2"]);
+}
+
+#[test]
+fn test_restore_backup() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = make_drive(&dir.path().join("drive"));
+    let backup = create_backup(&drive, &dir.path().join("backups")).unwrap();
+
+    fs::write(drive.root.join("layouts/layout1.txt"), "").unwrap();
+    fs::remove_file(drive.root.join("settings/kbd_settings.txt")).unwrap();
+
+    restore_backup(&backup, &drive).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(drive.root.join("layouts/layout1.txt")).unwrap(),
+        "[a]>[b]\n"
+    );
+    assert_eq!(
+        fs::read_to_string(drive.root.join("settings/kbd_settings.txt")).unwrap(),
+        "macro_speed=3\n"
+    );
+}
+
+#[test]
+fn test_other_directories_are_not_backups() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in [
+        "2024-01-01T00-00-00Z",
+        "2024-01-01T00-00-00Z-2",
+        "2024-01-01",
+        "2024-01-01T00-00-00Z-",
+        "notes",
+    ] {
+        fs::create_dir_all(dir.path().join(name)).unwrap();
+    }
+
+    let names: Vec<String> = list_backups(dir.path())
+        .unwrap()
+        .into_iter()
+        .map(|b| b.name)
+        .collect();
+    assert_eq!(names, ["2024-01-01T00-00-00Z-2", "2024-01-01T00-00-00Z"]);
+
+    prune_backups(dir.path(), 0).unwrap();
+    assert!(dir.path().join("notes").is_dir());
+    assert!(dir.path().join("2024-01-01").is_dir());
+}
+
+#[test]
+fn test_restore_missing_backup_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = make_drive(&dir.path().join("drive"));
+    let backup = create_backup(&drive, &dir.path().join("backups")).unwrap();
+    fs::remove_dir_all(&backup.path).unwrap();
+
+    assert!(restore_backup(&backup, &drive).is_err());
+}
+
+#[test]
+fn test_restore_oldest_backup_when_full() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = make_drive(&dir.path().join("drive"));
+    let backups = dir.path().join("backups");
+
+    // The oldest of a full set would be pruned by the pre-restore backup
+    let oldest = backups.join("2000-01-01T00-00-00Z");
+    fs::create_dir_all(oldest.join("layouts")).unwrap();
+    fs::write(oldest.join("layouts/layout1.txt"), "[c]>[d]\n").unwrap();
+    for n in 1..BACKUP_RETENTION {
+        fs::create_dir_all(backups.join(format!("2001-01-01T00-00-0{}Z", n))).unwrap();
+    }
+    let chosen = list_backups(&backups).unwrap().pop().unwrap();
+    assert_eq!(chosen.path, oldest);
+
+    restore_with_backup(&chosen, &drive, &backups).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(drive.root.join("layouts/layout1.txt")).unwrap(),
+        "[c]>[d]\n"
+    );
+    assert_eq!(list_backups(&backups).unwrap().len(), BACKUP_RETENTION);
+    assert!(!oldest.exists());
+}
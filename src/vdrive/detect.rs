@@ -0,0 +1,231 @@
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+/// Directories that identify an Advantage 360 V-Drive
+const REQUIRED_DIRS: [&str; 2] = ["layouts", "settings"];
+
+/// Filesystem types the keyboard presents its V-Drive as
+const FAT_FS_TYPES: [&str; 4] = ["vfat", "msdos", "fat", "exfat"];
+
+const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+const BY_LABEL_DIR: &str = "/dev/disk/by-label";
+
+/// A mounted V-Drive candidate
+#[derive(Debug, Clone, PartialEq)]
+pub struct VDrive {
+    pub root: PathBuf,
+    pub label: String,
+    pub device: Option<String>,
+    pub fs_type: Option<String>,
+}
+
+impl VDrive {
+    pub fn layouts_dir(&self) -> PathBuf {
+        self.root.join("layouts")
+    }
+
+    pub fn settings_dir(&self) -> PathBuf {
+        self.root.join("settings")
+    }
+
+    /// Path of the keyboard-wide settings file
+    pub fn settings_path(&self) -> PathBuf {
+        self.settings_dir().join("kbd_settings.txt")
+    }
+
+    /// Path of the indicator LED settings file
+    pub fn led_path(&self) -> PathBuf {
+        self.settings_dir().join("led.txt")
+    }
+
+    /// Path of `layoutN.txt` for a zero-based layout slot
+    pub fn layout_path(&self, index: usize) -> PathBuf {
+        self.layouts_dir().join(format!("layout{}.txt", index + 1))
+    }
+}
+
+impl std::fmt::Display for VDrive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.label, self.root.display())
+    }
+}
+
+/// Find every mounted V-Drive on this system
+pub fn detect() -> Vec<VDrive> {
+    let mountinfo = fs::read_to_string(MOUNTINFO_PATH).unwrap_or_default();
+    detect_in(&mountinfo, &default_search_roots(), Path::new(BY_LABEL_DIR))
+}
+
+/// Find V-Drives from a mountinfo table and a list of directories whose
+/// children are likely mount points
+pub fn detect_in(mountinfo: &str, search_roots: &[PathBuf], by_label_dir: &Path) -> Vec<VDrive> {
+    let mut found: Vec<VDrive> = Vec::new();
+
+    for mount in parse_mountinfo(mountinfo) {
+        if !FAT_FS_TYPES.contains(&mount.fs_type.as_str()) || !is_vdrive(&mount.mount_point) {
+            continue;
+        }
+
+        let label = device_label(&mount.source, by_label_dir)
+            .unwrap_or_else(|| dir_label(&mount.mount_point));
+        push_unique(
+            &mut found,
+            VDrive {
+                root: mount.mount_point,
+                label,
+                device: Some(mount.source),
+                fs_type: Some(mount.fs_type),
+            },
+        );
+    }
+
+    for root in search_roots {
+        let Ok(entries) = fs::read_dir(root) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if is_vdrive(&path) {
+                push_unique(
+                    &mut found,
+                    VDrive {
+                        label: dir_label(&path),
+                        root: path,
+                        device: None,
+                        fs_type: None,
+                    },
+                );
+            }
+        }
+    }
+
+    found
+}
+
+/// Check whether a directory has the V-Drive layout
+pub fn is_vdrive(path: &Path) -> bool {
+    REQUIRED_DIRS.iter().all(|dir| path.join(dir).is_dir())
+}
+
+fn push_unique(found: &mut Vec<VDrive>, candidate: VDrive) {
+    let key = fs::canonicalize(&candidate.root).unwrap_or_else(|_| candidate.root.clone());
+    let duplicate = found
+        .iter()
+        .any(|v| fs::canonicalize(&v.root).unwrap_or_else(|_| v.root.clone()) == key);
+    if !duplicate {
+        found.push(candidate);
+    }
+}
+
+fn default_search_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Ok(user) = env::var("USER") {
+        roots.push(PathBuf::from("/run/media").join(&user));
+        roots.push(PathBuf::from("/media").join(&user));
+    }
+    roots.push(PathBuf::from("/media"));
+    roots.push(PathBuf::from("/mnt"));
+    roots
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MountEntry {
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    pub source: String,
+}
+
+/// Parse `/proc/self/mountinfo` lines into mount entries, skipping malformed lines
+pub(crate) fn parse_mountinfo(content: &str) -> Vec<MountEntry> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let separator = fields.iter().position(|f| *f == "-")?;
+            let mount_point = fields.get(4)?;
+            let fs_type = fields.get(separator + 1)?;
+            let source = fields.get(separator + 2)?;
+
+            Some(MountEntry {
+                mount_point: PathBuf::from(unescape_octal(mount_point)),
+                fs_type: fs_type.to_string(),
+                source: unescape_octal(source),
+            })
+        })
+        .collect()
+}
+
+/// Decode the `\040`-style escapes the kernel uses for whitespace in paths
+fn unescape_octal(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match octal_escape(&bytes[i..]) {
+            Some(value) => {
+                out.push(value);
+                i += 4;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// The byte a `\ooo` escape at the start of `bytes` stands for. `\400`
+/// and above are not bytes, so are left as written.
+fn octal_escape(bytes: &[u8]) -> Option<u8> {
+    let digits = bytes.strip_prefix(b"\\")?.get(..3)?;
+    if !digits.iter().all(|b| (b'0'..=b'7').contains(b)) {
+        return None;
+    }
+    let value = digits
+        .iter()
+        .fold(0u32, |value, b| value * 8 + u32::from(b - b'0'));
+    u8::try_from(value).ok()
+}
+
+/// Look up the filesystem label of a block device via udev's by-label links
+fn device_label(device: &str, by_label_dir: &Path) -> Option<String> {
+    let device = fs::canonicalize(device).ok()?;
+
+    fs::read_dir(by_label_dir)
+        .ok()?
+        .flatten()
+        .find(|entry| fs::canonicalize(entry.path()).ok().as_ref() == Some(&device))
+        .map(|entry| unescape_hex(&entry.file_name().to_string_lossy()))
+}
+
+/// Decode the `\x20`-style escapes udev uses in by-label link names
+fn unescape_hex(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut rest = name;
+
+    while let Some(pos) = rest.find("\\x") {
+        out.push_str(&rest[..pos]);
+        let hex = rest.get(pos + 2..pos + 4);
+        match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(byte) => {
+                out.push(byte as char);
+                rest = &rest[pos + 4..];
+            }
+            None => {
+                out.push_str("\\x");
+                rest = &rest[pos + 2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn dir_label(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
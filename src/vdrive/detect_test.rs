@@ -0,0 +1,105 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::detect::{detect_in, is_vdrive, parse_mountinfo};
+use super::fixture::make_vdrive;
+
+fn mountinfo_line(mount_point: &Path, fs_type: &str, source: &str) -> String {
+    format!(
+        "36 35 8:17 / {} rw,nosuid,nodev shared:1 - {} {} rw,uid=1000\n",
+        mount_point.display().to_string().replace(' ', "\\040"),
+        fs_type,
+        source
+    )
+}
+
+#[test]
+fn test_parse_mountinfo() {
+    let content = "22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw\n\
+                   36 35 8:17 / /run/media/me/ADV\\040360 rw shared:2 - vfat /dev/sdb1 rw\n\
+                   garbage line\n";
+    let mounts = parse_mountinfo(content);
+
+    assert_eq!(mounts.len(), 2);
+    assert_eq!(
+        mounts[1].mount_point,
+        PathBuf::from("/run/media/me/ADV 360")
+    );
+    assert_eq!(mounts[1].fs_type, "vfat");
+    assert_eq!(mounts[1].source, "/dev/sdb1");
+}
+
+#[test]
+fn test_parse_mountinfo_keeps_escapes_above_a_byte() {
+    let content = "36 35 8:17 / /media/a\\777b\\400\\134c rw shared:2 - vfat /dev/sdb1 rw\n";
+    let mounts = parse_mountinfo(content);
+
+    assert_eq!(
+        mounts[0].mount_point,
+        PathBuf::from("/media/a\\777b\\400\\c")
+    );
+}
+
+#[test]
+fn test_is_vdrive() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(!is_vdrive(dir.path()));
+
+    make_vdrive(dir.path(), &[]);
+    assert!(is_vdrive(dir.path()));
+}
+
+#[test]
+fn test_detect_from_mountinfo() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = dir.path().join("KINESIS KB");
+    let other = dir.path().join("USB");
+    make_vdrive(&drive, &[]);
+    make_vdrive(&other, &[]);
+
+    let mountinfo =
+        mountinfo_line(&drive, "vfat", "/dev/sdz1") + &mountinfo_line(&other, "ext4", "/dev/sdz2");
+    let found = detect_in(&mountinfo, &[], dir.path());
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].root, drive);
+    assert_eq!(found[0].label, "KINESIS KB");
+    assert_eq!(found[0].device.as_deref(), Some("/dev/sdz1"));
+    assert_eq!(found[0].fs_type.as_deref(), Some("vfat"));
+}
+
+#[test]
+fn test_detect_from_search_roots() {
+    let dir = tempfile::tempdir().unwrap();
+    let media = dir.path().join("media");
+    make_vdrive(&media.join("ADV360"), &[]);
+    fs::create_dir_all(media.join("THUMBDRIVE/documents")).unwrap();
+
+    let found = detect_in("", &[media.clone(), dir.path().join("missing")], dir.path());
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].label, "ADV360");
+    assert_eq!(
+        found[0].layout_path(0),
+        media.join("ADV360/layouts/layout1.txt")
+    );
+    assert_eq!(
+        found[0].settings_path(),
+        media.join("ADV360/settings/kbd_settings.txt")
+    );
+    assert_eq!(found[0].led_path(), media.join("ADV360/settings/led.txt"));
+}
+
+#[test]
+fn test_detect_reports_each_drive_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let media = dir.path().join("media");
+    let drive = media.join("ADV360");
+    make_vdrive(&drive, &[]);
+
+    let mountinfo = mountinfo_line(&drive, "vfat", "/dev/sdz1");
+    let found = detect_in(&mountinfo, &[media], dir.path());
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].device.as_deref(), Some("/dev/sdz1"));
+}
@@ -0,0 +1,22 @@
+use std::fs;
+use std::path::Path;
+
+use super::detect::VDrive;
+
+/// Create a V-Drive's `layouts/` and `settings/` directories under `root`,
+/// holding `files` given as paths relative to the drive and their contents
+pub(crate) fn make_vdrive(root: &Path, files: &[(&str, &str)]) -> VDrive {
+    for dir in ["layouts", "settings"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for (path, contents) in files {
+        fs::write(root.join(path), contents).unwrap();
+    }
+
+    VDrive {
+        root: root.to_path_buf(),
+        label: "ADV360".to_string(),
+        device: None,
+        fs_type: None,
+    }
+}
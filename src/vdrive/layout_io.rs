@@ -0,0 +1,117 @@
+use std::io;
+
+use super::detect::VDrive;
+use crate::models::{KinesisLayout, LAYOUT_COUNT, ParseError};
+
+/// Result of reading every layout file from a V-Drive
+#[derive(Debug)]
+pub struct LoadReport {
+    pub layouts: [KinesisLayout; LAYOUT_COUNT],
+    /// Slots whose layout file was read successfully
+    pub loaded: Vec<usize>,
+    /// Slots with no layout file on the drive
+    pub missing: Vec<usize>,
+    /// Lines that could not be parsed and were kept as written, by slot
+    pub warnings: Vec<(usize, ParseError)>,
+    /// Slots whose layout file could not be read
+    pub errors: Vec<(usize, io::Error)>,
+}
+
+impl LoadReport {
+    pub fn summary(&self) -> String {
+        let mut summary = format!("Loaded {} of {} layouts", self.loaded.len(), LAYOUT_COUNT);
+        if !self.missing.is_empty() {
+            summary.push_str(&format!(", {} missing", self.missing.len()));
+        }
+        if !self.errors.is_empty() {
+            summary.push_str(&format!(", {} failed", self.errors.len()));
+        }
+        if !self.warnings.is_empty() {
+            summary.push_str(&format!(
+                ", {} invalid lines kept as-is",
+                self.warnings.len()
+            ));
+        }
+        summary
+    }
+}
+
+/// Read `layout1.txt`…`layout9.txt`, collecting per-file errors rather than
+/// stopping at the first one. Invalid lines are kept as written and
+/// reported as warnings; slots whose file cannot be read are left empty.
+pub fn load_layouts(drive: &VDrive) -> LoadReport {
+    let mut report = LoadReport {
+        layouts: std::array::from_fn(|_| KinesisLayout::new()),
+        loaded: Vec::new(),
+        missing: Vec::new(),
+        warnings: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    for idx in 0..LAYOUT_COUNT {
+        let path = drive.layout_path(idx);
+        if !path.exists() {
+            report.missing.push(idx);
+            continue;
+        }
+
+        match KinesisLayout::from_file_lenient(&path) {
+            Ok((layout, warnings)) => {
+                report.layouts[idx] = layout;
+                report.loaded.push(idx);
+                report
+                    .warnings
+                    .extend(warnings.into_iter().map(|w| (idx, w)));
+            }
+            Err(e) => report.errors.push((idx, e)),
+        }
+    }
+
+    report
+}
+
+/// Result of writing changed layouts back to a V-Drive
+#[derive(Debug, Default)]
+pub struct SaveReport {
+    /// Slots that were written
+    pub written: Vec<usize>,
+    /// Slots whose layout file could not be written
+    pub errors: Vec<(usize, io::Error)>,
+}
+
+impl SaveReport {
+    pub fn summary(&self) -> String {
+        let mut summary = if self.written.is_empty() && self.errors.is_empty() {
+            "No changes to save".to_string()
+        } else {
+            format!("Saved {} layouts", self.written.len())
+        };
+        if !self.errors.is_empty() {
+            summary.push_str(&format!(", {} failed", self.errors.len()));
+        }
+        summary
+    }
+}
+
+/// Atomically write every layout that differs from its last loaded or
+/// saved state. Unchanged layout files are left untouched.
+pub fn save_layouts(
+    drive: &VDrive,
+    layouts: &[KinesisLayout; LAYOUT_COUNT],
+    saved: &[KinesisLayout; LAYOUT_COUNT],
+) -> SaveReport {
+    let mut report = SaveReport::default();
+
+    for (idx, (layout, saved)) in layouts.iter().zip(saved).enumerate() {
+        if layout.same_lines(saved) {
+            continue;
+        }
+
+        match layout.to_file(drive.layout_path(idx)) {
+            Ok(()) => report.written.push(idx),
+            Err(e) => report.errors.push((idx, e)),
+        }
+    }
+
+    report
+}
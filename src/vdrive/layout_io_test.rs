@@ -0,0 +1,146 @@
+use std::fs;
+
+use super::detect::VDrive;
+use super::fixture::make_vdrive;
+use super::layout_io::{load_layouts, save_layouts};
+use crate::models::{KeyAction, KeyToken, KinesisLayout};
+
+#[test]
+fn test_load_layouts() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = make_vdrive(dir.path(), &[]);
+    fs::write(drive.layout_path(0), "[a]>[b]\n").unwrap();
+    fs::write(drive.layout_path(8), "[caps]>[esc]\n{hyphen}>{speed5}\n").unwrap();
+
+    let report = load_layouts(&drive);
+
+    assert_eq!(report.loaded, vec![0, 8]);
+    assert_eq!(report.missing, vec![1, 2, 3, 4, 5, 6, 7]);
+    assert!(report.errors.is_empty());
+    assert_eq!(
+        *report.layouts[0].mappings().next().unwrap(),
+        KeyAction::SimpleRemap {
+            source: KeyToken::A,
+            target: KeyToken::B
+        }
+    );
+    assert_eq!(report.layouts[8].mapping_count(), 2);
+    assert!(report.layouts[1].mapping_count() == 0);
+}
+
+#[test]
+fn test_load_layouts_collects_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = make_vdrive(dir.path(), &[]);
+    fs::write(drive.layout_path(0), "[a]>[b]\n").unwrap();
+    fs::create_dir(drive.layout_path(1)).unwrap();
+    fs::write(drive.layout_path(2), "[c]>[d]\n").unwrap();
+
+    let report = load_layouts(&drive);
+
+    assert_eq!(report.loaded, vec![0, 2]);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].0, 1);
+    assert!(report.layouts[1].mapping_count() == 0);
+    assert_eq!(
+        report.summary(),
+        "Loaded 2 of 9 layouts, 6 missing, 1 failed"
+    );
+}
+
+#[test]
+fn test_load_layouts_keeps_invalid_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = make_vdrive(dir.path(), &[]);
+    fs::write(
+        drive.layout_path(3),
+        "[a]>[b]\nthis is not a layout\n[c]>[d]\n",
+    )
+    .unwrap();
+
+    let report = load_layouts(&drive);
+
+    assert_eq!(report.loaded, vec![3]);
+    assert!(report.errors.is_empty());
+    assert_eq!(report.layouts[3].mapping_count(), 2);
+    assert_eq!(
+        report.layouts[3].to_string(),
+        "[a]>[b]\nthis is not a layout\n[c]>[d]\n"
+    );
+    assert_eq!(report.warnings.len(), 1);
+    assert_eq!(report.warnings[0].0, 3);
+    assert_eq!(report.warnings[0].1.line, 2);
+    assert_eq!(
+        report.summary(),
+        "Loaded 1 of 9 layouts, 8 missing, 1 invalid lines kept as-is"
+    );
+}
+
+#[test]
+fn test_save_layouts_writes_only_changed() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = make_vdrive(dir.path(), &[]);
+    fs::write(drive.layout_path(0), "[a]>[b]\n").unwrap();
+    fs::write(drive.layout_path(1), "[c]>[d]\n").unwrap();
+
+    let saved = load_layouts(&drive).layouts;
+    let mut layouts = saved.clone();
+    layouts[1].add_remap(KeyToken::E, KeyToken::F);
+
+    // Changing the file behind our back shows whether slot 0 was rewritten
+    fs::write(drive.layout_path(0), "[a]>[z]\n").unwrap();
+
+    let report = save_layouts(&drive, &layouts, &saved);
+
+    assert_eq!(report.written, vec![1]);
+    assert!(report.errors.is_empty());
+    assert_eq!(
+        fs::read_to_string(drive.layout_path(0)).unwrap(),
+        "[a]>[z]\n"
+    );
+    assert_eq!(
+        fs::read_to_string(drive.layout_path(1)).unwrap(),
+        "[c]>[d]\n[e]>[f]\n"
+    );
+    assert!(!drive.layout_path(2).exists());
+}
+
+#[test]
+fn test_save_layouts_skips_layouts_changed_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = make_vdrive(dir.path(), &[]);
+    fs::write(drive.layout_path(0), "[a] > [b]\n").unwrap();
+
+    let saved = load_layouts(&drive).layouts;
+    let mut layouts = saved.clone();
+    layouts[0].remap_to_key(KeyToken::A, KeyToken::C);
+    layouts[0].remap_to_key(KeyToken::A, KeyToken::B);
+
+    let report = save_layouts(&drive, &layouts, &saved);
+
+    assert!(report.written.is_empty());
+    assert_eq!(
+        fs::read_to_string(drive.layout_path(0)).unwrap(),
+        "[a] > [b]\n"
+    );
+}
+
+#[test]
+fn test_save_layouts_reports_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = VDrive {
+        root: dir.path().join("unplugged"),
+        label: "ADV360".to_string(),
+        device: None,
+        fs_type: None,
+    };
+    let saved: [KinesisLayout; 9] = std::array::from_fn(|_| KinesisLayout::new());
+    let mut layouts = saved.clone();
+    layouts[4].add_remap(KeyToken::A, KeyToken::B);
+
+    let report = save_layouts(&drive, &layouts, &saved);
+
+    assert!(report.written.is_empty());
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].0, 4);
+}
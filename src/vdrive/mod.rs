@@ -0,0 +1,21 @@
+pub mod backup;
+pub mod detect;
+pub mod layout_io;
+
+#[cfg(test)]
+mod backup_test;
+#[cfg(test)]
+mod detect_test;
+#[cfg(test)]
+pub(crate) mod fixture;
+#[cfg(test)]
+mod layout_io_test;
+
+pub use crate::fs_util::write_atomic;
+pub use crate::models::LAYOUT_COUNT;
+pub use backup::{
+    BACKUP_RETENTION, Backup, backup_drive, backups_dir, create_backup, list_backups,
+    prune_backups, restore_backup, restore_drive, restore_with_backup,
+};
+pub use detect::{VDrive, detect, is_vdrive};
+pub use layout_io::{LoadReport, SaveReport, load_layouts, save_layouts};